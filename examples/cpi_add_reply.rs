@@ -0,0 +1,47 @@
+//! A program that replies to intros through CPI, signing as its own `replier` PDA.
+//!
+//! A deployable version depends on this crate with the `no-entrypoint` feature and wraps
+//! `process_instruction` in `entrypoint!`. The PDA needs SOL for the reply's rent and an
+//! associated token account with enough reward tokens for the reply burn.
+//!
+//! Accounts: every account `IntroInstruction::add_reply` names for the PDA replier,
+//! followed by the student intro program. The instruction data is the reply text.
+
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_student_intros_program::prelude::*;
+
+pub const REPLIER_SEED: &[u8] = b"replier";
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let reply = String::from_utf8(instruction_data.to_vec())
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    let (replier, bump_seed) = Pubkey::find_program_address(&[REPLIER_SEED], program_id);
+    let intro_program = accounts.last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !check_id(intro_program.key) {
+        msg!("Last account must be the student intro program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // Same order as the add_reply builder: replier, intro, reply counter, ...
+    let user_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let reply_counter = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let reply_index = ReplyCounter::try_from_account_data(&reply_counter.data.borrow())?.counter;
+
+    IntroInstruction::add_reply_cpi(
+        intro_program.key,
+        &replier,
+        user_account.key,
+        reply_index,
+        reply,
+        accounts,
+        &[&[REPLIER_SEED, &[bump_seed]]],
+    )
+}
@@ -1,6 +1,7 @@
-use crate::processor;
+use crate::{error::StudentIntroError, processor};
 use solana_program::{
-    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult, msg, pubkey::Pubkey,
+    account_info::AccountInfo, entrypoint, entrypoint::ProgramResult,
+    program_error::PrintProgramError, pubkey::Pubkey,
 };
 
 entrypoint!(process_instruction);
@@ -10,13 +11,16 @@ fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    msg!(
+    debug_msg!(
         "process_instruction: {}: {} accounts, data={:?}",
         program_id,
         accounts.len(),
         instruction_data
     );
-    processor::process_instruction(program_id, accounts, instruction_data)?;
+    if let Err(error) = processor::process_instruction(program_id, accounts, instruction_data) {
+        error.print::<StudentIntroError>();
+        return Err(error);
+    }
 
     Ok(())
 }
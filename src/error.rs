@@ -1,7 +1,15 @@
-use solana_program::program_error::ProgramError;
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+/// Variants map to `ProgramError::Custom` codes in declaration order, so new ones are
+/// only ever appended.
+#[derive(Clone, Copy, Debug, Eq, Error, FromPrimitive, PartialEq)]
 pub enum StudentIntroError {
     #[error("Account not initialized yet")]
     UninitializedAccount,
@@ -11,6 +19,38 @@ pub enum StudentIntroError {
     InvalidDataLength,
     #[error("Accounts do not match")]
     IncorrectAccountError,
+    #[error("Account data could not be deserialized")]
+    DeserializationFailed,
+    #[error("Input is empty or exceeds its maximum length")]
+    InvalidInput,
+    #[error("Arithmetic overflow")]
+    Overflow,
+    #[error("Account version is not supported, legacy accounts must be migrated")]
+    UnsupportedAccountVersion,
+    #[error("Intro has reached the maximum number of replies")]
+    ReplyLimitReached,
+    #[error("Signer is on the block list")]
+    Blocked,
+    #[error("Block list is full")]
+    BlockListFull,
+    #[error("Intro was updated too recently")]
+    CooldownActive,
+    #[error("Another intro already uses this name")]
+    NameTaken,
+    #[error("Token balance is too low")]
+    InsufficientTokens,
+    #[error("Minting reward tokens failed")]
+    TokenMintFailed,
+    #[error("Program is paused")]
+    ProgramPaused,
+    #[error("Unknown instruction")]
+    InvalidInstruction,
+    #[error("Word denylist is full")]
+    DenylistFull,
+    #[error("Intro still has replies")]
+    RepliesRemaining,
+    #[error("Reply has been deleted")]
+    ReplyDeleted,
 }
 
 impl From<StudentIntroError> for ProgramError {
@@ -18,3 +58,54 @@ impl From<StudentIntroError> for ProgramError {
         ProgramError::Custom(e as u32)
     }
 }
+
+impl<T> DecodeError<T> for StudentIntroError {
+    fn type_of() -> &'static str {
+        "StudentIntroError"
+    }
+}
+
+impl PrintProgramError for StudentIntroError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_keep_their_custom_codes() {
+        let codes = [
+            (StudentIntroError::UninitializedAccount, 0),
+            (StudentIntroError::InvalidPDA, 1),
+            (StudentIntroError::InvalidDataLength, 2),
+            (StudentIntroError::IncorrectAccountError, 3),
+            (StudentIntroError::DeserializationFailed, 4),
+            (StudentIntroError::InvalidInput, 5),
+            (StudentIntroError::Overflow, 6),
+            (StudentIntroError::UnsupportedAccountVersion, 7),
+            (StudentIntroError::ReplyLimitReached, 8),
+            (StudentIntroError::Blocked, 9),
+            (StudentIntroError::BlockListFull, 10),
+            (StudentIntroError::CooldownActive, 11),
+            (StudentIntroError::NameTaken, 12),
+            (StudentIntroError::InsufficientTokens, 13),
+            (StudentIntroError::TokenMintFailed, 14),
+            (StudentIntroError::ProgramPaused, 15),
+            (StudentIntroError::InvalidInstruction, 16),
+            (StudentIntroError::DenylistFull, 17),
+            (StudentIntroError::RepliesRemaining, 18),
+            (StudentIntroError::ReplyDeleted, 19),
+        ];
+        for (error, code) in codes {
+            assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+            assert_eq!(StudentIntroError::from_u32(code), Some(error));
+        }
+        assert_eq!(StudentIntroError::from_u32(codes.len() as u32), None);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::state::{FlagReason, ReactionKind};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct StudentIntroCreated {
+    pub author: Pubkey,
+    pub intro: Pubkey,
+    pub name: String,
+    pub created_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct StudentIntroUpdated {
+    pub author: Pubkey,
+    pub intro: Pubkey,
+    pub updated_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct IntroOwnershipTransferred {
+    pub old_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub old_intro: Pubkey,
+    pub new_intro: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyAdded {
+    pub intro: Pubkey,
+    pub reply: Pubkey,
+    pub replier: Pubkey,
+    pub parent: Option<Pubkey>,
+    pub index: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyUpdated {
+    pub reply: Pubkey,
+    pub replier: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyDeleted {
+    pub intro: Pubkey,
+    pub reply: Pubkey,
+    pub replier: Pubkey,
+    pub index: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplySoftDeleted {
+    pub reply: Pubkey,
+    pub authority: Pubkey,
+    pub deleted: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct IntroRated {
+    pub intro: Pubkey,
+    pub rater: Pubkey,
+    pub rating: u8,
+    pub average: u8,
+    pub rating_count: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyLiked {
+    pub reply: Pubkey,
+    pub liker: Pubkey,
+    pub likes: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReactionAdded {
+    pub reply: Pubkey,
+    pub reactor: Pubkey,
+    pub kind: ReactionKind,
+    pub count: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyFlagged {
+    pub reply: Pubkey,
+    pub reporter: Pubkey,
+    pub reason: FlagReason,
+    pub flags: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct BlockListUpdated {
+    pub key: Pubkey,
+    pub blocked: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct TreasuryWithdrawn {
+    pub admin: Pubkey,
+    pub destination: Pubkey,
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct TokensBurned {
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct TokenMintInitialized {
+    pub mint: Pubkey,
+    pub mint_authority: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub max_name_len: u32,
+    pub max_message_len: u32,
+    pub reply_reward: u64,
+    pub intro_reward: u64,
+    pub min_reply_interval: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct PauseUpdated {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct WordDenylistUpdated {
+    pub term: String,
+    pub denied: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct TipSent {
+    pub tipper: Pubkey,
+    pub author: Pubkey,
+    pub intro: Pubkey,
+    pub lamports: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct FreezeAuthorityUpdated {
+    pub admin: Pubkey,
+    pub mint: Pubkey,
+    pub new_authority: Option<Pubkey>,
+}
+
+/// Events are logged with `sol_log_data` as a borsh-encoded enum, so the leading
+/// variant byte tells an indexer which struct follows.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub enum IntroEvent {
+    StudentIntroCreated(StudentIntroCreated),
+    StudentIntroUpdated(StudentIntroUpdated),
+    ReplyAdded(ReplyAdded),
+    ReplyUpdated(ReplyUpdated),
+    ReplyDeleted(ReplyDeleted),
+    IntroRated(IntroRated),
+    ReplyLiked(ReplyLiked),
+    ReactionAdded(ReactionAdded),
+    IntroOwnershipTransferred(IntroOwnershipTransferred),
+    ReplyFlagged(ReplyFlagged),
+    BlockListUpdated(BlockListUpdated),
+    ReplySoftDeleted(ReplySoftDeleted),
+    TreasuryWithdrawn(TreasuryWithdrawn),
+    TokensBurned(TokensBurned),
+    TokenMintInitialized(TokenMintInitialized),
+    ConfigUpdated(ConfigUpdated),
+    PauseUpdated(PauseUpdated),
+    WordDenylistUpdated(WordDenylistUpdated),
+    TipSent(TipSent),
+    FreezeAuthorityUpdated(FreezeAuthorityUpdated),
+}
+
+impl IntroEvent {
+    pub fn emit(&self) {
+        // Serializing into a Vec cannot fail for these types
+        let data = self.try_to_vec().unwrap();
+        sol_log_data(&[&data]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip_behind_their_variant_byte() {
+        let reply_added = IntroEvent::ReplyAdded(ReplyAdded {
+            intro: Pubkey::new_unique(),
+            reply: Pubkey::new_unique(),
+            replier: Pubkey::new_unique(),
+            parent: Some(Pubkey::new_unique()),
+            index: 256,
+        });
+        let data = reply_added.try_to_vec().unwrap();
+        assert_eq!(data[0], 2);
+        assert_eq!(IntroEvent::try_from_slice(&data).unwrap(), reply_added);
+
+        let paused = IntroEvent::PauseUpdated(PauseUpdated {
+            admin: Pubkey::new_unique(),
+            paused: true,
+        });
+        let data = paused.try_to_vec().unwrap();
+        assert_eq!(data[0], 16);
+        assert_eq!(IntroEvent::try_from_slice(&data).unwrap(), paused);
+    }
+}
@@ -0,0 +1,158 @@
+//! Account checks shared by the instruction handlers. Each one logs why it failed and
+//! returns the error the handlers have always used for that case.
+
+use crate::error::StudentIntroError;
+use crate::processor::ADMIN_PUBKEY;
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub fn require_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        msg!("Missing required signature");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+pub fn require_owned_by(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner != owner {
+        msg!("Account is not owned by {}", owner);
+        return Err(ProgramError::IllegalOwner);
+    }
+    Ok(())
+}
+
+/// `expected` is the address derived from the account's seeds.
+pub fn require_pda(account: &AccountInfo, expected: &Pubkey) -> ProgramResult {
+    if account.key != expected {
+        msg!("Invalid seeds for PDA");
+        return Err(StudentIntroError::InvalidPDA.into());
+    }
+    Ok(())
+}
+
+/// For fixed addresses such as programs, the mint and the treasury. `name` completes
+/// the "Incorrect ..." log line.
+pub fn require_key_eq(account: &AccountInfo, expected: &Pubkey, name: &str) -> ProgramResult {
+    if account.key != expected {
+        msg!("Incorrect {}", name);
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+    Ok(())
+}
+
+/// `action` completes the "Only the admin can ..." log line.
+pub fn require_admin(account: &AccountInfo, action: &str) -> ProgramResult {
+    if *account.key != ADMIN_PUBKEY || !account.is_signer {
+        msg!("Only the admin can {}", action);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(
+        key: Pubkey,
+        owner: Pubkey,
+        is_signer: bool,
+        guard: impl FnOnce(&AccountInfo) -> ProgramResult,
+    ) -> ProgramResult {
+        let mut lamports = 0;
+        let mut data = [];
+        let account = AccountInfo::new(
+            &key,
+            is_signer,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        guard(&account)
+    }
+
+    #[test]
+    fn require_signer_checks_the_signature() {
+        let key = Pubkey::new_unique();
+        assert_eq!(check(key, Pubkey::default(), true, require_signer), Ok(()));
+        assert_eq!(
+            check(key, Pubkey::default(), false, require_signer),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+    }
+
+    #[test]
+    fn require_owned_by_checks_the_owner() {
+        let owner = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        assert_eq!(
+            check(key, owner, false, |account| require_owned_by(
+                account, &owner
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(key, Pubkey::new_unique(), false, |account| {
+                require_owned_by(account, &owner)
+            }),
+            Err(ProgramError::IllegalOwner)
+        );
+    }
+
+    #[test]
+    fn require_pda_checks_the_address() {
+        let expected = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        assert_eq!(
+            check(expected, owner, false, |account| require_pda(
+                account, &expected
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            check(Pubkey::new_unique(), owner, false, |account| {
+                require_pda(account, &expected)
+            }),
+            Err(StudentIntroError::InvalidPDA.into())
+        );
+    }
+
+    #[test]
+    fn require_key_eq_checks_the_address() {
+        let expected = Pubkey::new_unique();
+        let owner = Pubkey::default();
+        assert_eq!(
+            check(expected, owner, false, |account| {
+                require_key_eq(account, &expected, "account")
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            check(Pubkey::new_unique(), owner, false, |account| {
+                require_key_eq(account, &expected, "account")
+            }),
+            Err(StudentIntroError::IncorrectAccountError.into())
+        );
+    }
+
+    #[test]
+    fn require_admin_needs_the_admin_key_and_signature() {
+        let owner = Pubkey::default();
+        let admin = |account: &AccountInfo| require_admin(account, "test");
+        assert_eq!(check(ADMIN_PUBKEY, owner, true, admin), Ok(()));
+        assert_eq!(
+            check(ADMIN_PUBKEY, owner, false, admin),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        assert_eq!(
+            check(Pubkey::new_unique(), owner, true, admin),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+    }
+}
@@ -1,52 +1,1188 @@
-use borsh::BorshDeserialize;
-use solana_program::program_error::ProgramError;
+use crate::error::StudentIntroError;
+use crate::metadata::TOKEN_METADATA_PROGRAM_ID;
+use crate::pda::{
+    block_list_pda, config_pda, intro_reward_marker_pda, message_chunk_pda, mint_auth_pda,
+    name_index_pda, reply_counter_pda, reply_index_pda, reply_pda, reply_reward_marker_pda,
+    token_metadata_pda, token_mint_pda, treasury_pda, user_intro_pda, word_denylist_pda,
+};
+use crate::processor::{DEFAULT_INTRO_REWARD, REWARD_DECIMALS};
+use crate::state::ReactionKind;
+use borsh::{BorshDeserialize, BorshSerialize};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
 
+/// Apart from `GetStats`, `ListReplies`, `SetConfig` and `SetPaused`, every instruction must also be
+/// passed the `Config` PDA, at any position after its own accounts and even before the
+/// account exists, so a paused program cannot be reached by leaving it out. The
+/// builders below append it last. Instructions that write names, messages or replies
+/// must likewise be passed the `WordDenylist` PDA, which the builders put just before it.
 pub enum IntroInstruction {
-    InitUserInput { name: String, message: String },
-    UpdateStudentIntro { name: String, message: String },
-    AddReply { reply: String },
-    InitializeMint,
+    /// `reward_amount` is an optional trailing `u64`; older clients that omit it get the
+    /// configured intro reward. An amount above that needs the admin to co-sign, passed as
+    /// an extra signer after the other accounts. `avatar_uri` may follow it as a trailing
+    /// string. The reward is only minted for a wallet's first intro.
+    InitUserInput {
+        name: String,
+        message: String,
+        reward_amount: Option<u64>,
+        avatar_uri: Option<String>,
+    },
+    /// `avatar_uri` is an optional trailing string; when omitted the avatar is kept, and
+    /// an empty string clears it.
+    UpdateStudentIntro {
+        name: String,
+        message: String,
+        avatar_uri: Option<String>,
+    },
+    AddReply {
+        reply: String,
+    },
+    /// `decimals` must be at most 9. Data without the trailing byte uses 9.
+    InitializeMint {
+        decimals: u8,
+    },
+    /// Signed by the replier or by the intro's author. Leaves the reply as a tombstone and
+    /// refunds the rent it no longer needs; when the author signs, the replier is passed
+    /// as a 5th account to receive it.
+    DeleteReply {
+        index: u64,
+    },
+    UpdateReply {
+        reply: String,
+    },
+    RateIntro {
+        rating: u8,
+    },
+    LikeReply,
+    /// Rewrites a legacy or version 1 account to the current layout. Intros take the
+    /// author's intro reward marker as a 4th account.
+    Migrate,
+    CloseStudentIntro,
+    TipAuthor {
+        lamports: u64,
+    },
+    AddNestedReply {
+        reply: String,
+        parent: Pubkey,
+    },
+    AddReaction {
+        kind: ReactionKind,
+    },
+    TransferIntroOwnership {
+        new_owner: Pubkey,
+    },
+    /// `reason` is checked against `FlagReason` by the processor.
+    FlagReply {
+        reason: u8,
+    },
+    AddToBlockList {
+        key: Pubkey,
+    },
+    RemoveFromBlockList {
+        key: Pubkey,
+    },
+    /// Read-only; writes `IntroStats` as return data for clients that simulate it.
+    GetStats,
+    AddRepliesBatch {
+        replies: Vec<String>,
+    },
+    /// Sets or clears `Reply::deleted`.
+    SoftDeleteReply {
+        deleted: bool,
+    },
+    /// `destination` is an optional trailing pubkey, passed as the 4th account; without it
+    /// the lamports go to the admin.
+    WithdrawTreasury {
+        lamports: u64,
+        destination: Option<Pubkey>,
+    },
+    BurnTokens {
+        amount: u64,
+    },
+    /// Only works while the freeze authority PDA still holds the authority, i.e. on a
+    /// mint created with the `freeze-authority` feature and not yet handed over.
+    SetFreezeAuthority {
+        new_authority: Option<Pubkey>,
+    },
+    /// Creates the intro like `InitUserInput` or, when it already exists, updates it like
+    /// `UpdateStudentIntro`. Takes the `InitUserInput` data and accounts, followed by the
+    /// name index of the current name. The update skips the edit cooldown, so retrying a
+    /// create that already landed succeeds, and ignores `reward_amount`.
+    InitOrUpdate {
+        name: String,
+        message: String,
+        reward_amount: Option<u64>,
+        avatar_uri: Option<String>,
+    },
+    /// Permissionless; refunds an orphaned reply to its replier. `index` is the reply's
+    /// index under its intro and is only checked for top-level replies.
+    CloseOrphanReply {
+        index: u64,
+    },
+    /// Admin only; creates the `Config` account on first use. The name and message limits
+    /// and the reply interval must be positive.
+    SetConfig {
+        max_name_len: u32,
+        max_message_len: u32,
+        reply_reward: u64,
+        intro_reward: u64,
+        min_reply_interval: i64,
+    },
+    /// Admin only; requires the `Config` account created by `SetConfig`.
+    SetPaused {
+        paused: bool,
+    },
+    /// Writes the intro's next `MessageChunk`. `UpdateStudentIntro` only rewrites the
+    /// primary message and leaves existing chunks in place.
+    AppendMessageChunk {
+        text: String,
+    },
+    /// Read-only; writes a `ReplyPage` as return data. `limit` is capped at
+    /// `MAX_LIST_REPLIES` and the page covers only the current intro's reply indices.
+    ListReplies {
+        start: u64,
+        limit: u8,
+    },
+    /// `term` is stored lowercased and matched as a substring.
+    AddDeniedWord {
+        term: String,
+    },
+    RemoveDeniedWord {
+        term: String,
+    },
 }
 
-#[derive(BorshDeserialize, Debug)]
+/// First byte of the instruction data. The values are part of the wire format, so a new
+/// instruction always takes the next unused one.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Eq, FromPrimitive, PartialEq)]
+pub enum InstructionTag {
+    InitUserInput = 0,
+    UpdateStudentIntro = 1,
+    AddReply = 2,
+    InitializeMint = 3,
+    DeleteReply = 4,
+    UpdateReply = 5,
+    RateIntro = 6,
+    LikeReply = 7,
+    Migrate = 8,
+    CloseStudentIntro = 9,
+    TipAuthor = 10,
+    AddNestedReply = 11,
+    AddReaction = 12,
+    TransferIntroOwnership = 13,
+    FlagReply = 14,
+    AddToBlockList = 15,
+    RemoveFromBlockList = 16,
+    GetStats = 17,
+    AddRepliesBatch = 18,
+    SoftDeleteReply = 19,
+    WithdrawTreasury = 20,
+    BurnTokens = 21,
+    SetFreezeAuthority = 22,
+    InitOrUpdate = 23,
+    CloseOrphanReply = 24,
+    SetConfig = 25,
+    SetPaused = 26,
+    AppendMessageChunk = 27,
+    ListReplies = 28,
+    AddDeniedWord = 29,
+    RemoveDeniedWord = 30,
+}
+
+pub const CREATED_ACCOUNTS_VERSION: u8 = 1;
+
+/// Return data set by instructions that create accounts, so a client simulating the
+/// transaction can read the new addresses instead of deriving them again.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct CreatedAccounts {
+    pub version: u8,
+    pub addresses: Vec<Pubkey>,
+}
+
+/// Return data set by `GetStats`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct IntroStats {
+    pub reply_count: u64,
+    pub created_at: i64,
+    pub rating: u8,
+    pub rating_count: u64,
+}
+
+/// Return data set by `ListReplies`. `replies[i]` is the PDA of reply `start + i`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReplyPage {
+    pub start: u64,
+    pub replies: Vec<Pubkey>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
 struct StudentIntroPayload {
     name: String,
     message: String,
 }
 
-#[derive(BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize)]
 struct ReplyPayload {
     reply: String,
 }
 
+#[derive(BorshSerialize, BorshDeserialize)]
+struct RepliesBatchPayload {
+    replies: Vec<String>,
+}
+
+#[derive(BorshDeserialize)]
+struct RatingPayload {
+    rating: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct ReplyIndexPayload {
+    index: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct NestedReplyPayload {
+    reply: String,
+    parent: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct ReactionPayload {
+    kind: ReactionKind,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct TransferOwnershipPayload {
+    new_owner: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct FlagPayload {
+    reason: u8,
+}
+
+#[derive(BorshDeserialize)]
+struct BlockListPayload {
+    key: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct SoftDeletePayload {
+    deleted: bool,
+}
+
+#[derive(BorshDeserialize)]
+struct FreezeAuthorityPayload {
+    new_authority: Option<Pubkey>,
+}
+
+#[derive(BorshDeserialize)]
+struct BurnPayload {
+    amount: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct ConfigPayload {
+    max_name_len: u32,
+    max_message_len: u32,
+    reply_reward: u64,
+    intro_reward: u64,
+    min_reply_interval: i64,
+}
+
+#[derive(BorshDeserialize)]
+struct PausePayload {
+    paused: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct MessageChunkPayload {
+    text: String,
+}
+
+#[derive(BorshDeserialize)]
+struct ListRepliesPayload {
+    start: u64,
+    limit: u8,
+}
+
+#[derive(BorshDeserialize)]
+struct DeniedWordPayload {
+    term: String,
+}
+
+#[derive(BorshDeserialize)]
+struct LamportsPayload {
+    lamports: u64,
+}
+
 impl IntroInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (&variant, rest) = input
+        let (&tag, rest) = input
             .split_first()
             .ok_or(ProgramError::InvalidInstructionData)?;
-        Ok(match variant {
-            0 => {
-                let payload = StudentIntroPayload::try_from_slice(rest).unwrap();
+        let tag = InstructionTag::from_u8(tag).ok_or(StudentIntroError::InvalidInstruction)?;
+        Ok(match tag {
+            InstructionTag::InitUserInput => {
+                let (payload, reward_amount, avatar_uri) = unpack_intro_input(rest)?;
                 Self::InitUserInput {
                     name: payload.name,
                     message: payload.message,
+                    reward_amount,
+                    avatar_uri,
                 }
             }
-            1 => {
-                let payload = StudentIntroPayload::try_from_slice(rest).unwrap();
+            InstructionTag::UpdateStudentIntro => {
+                let mut data = rest;
+                let payload = StudentIntroPayload::deserialize(&mut data)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                let avatar_uri = if data.is_empty() {
+                    None
+                } else {
+                    Some(
+                        String::try_from_slice(data)
+                            .map_err(|_| ProgramError::InvalidInstructionData)?,
+                    )
+                };
                 Self::UpdateStudentIntro {
                     name: payload.name,
                     message: payload.message,
+                    avatar_uri,
                 }
             }
-            2 => {
-                let payload = ReplyPayload::try_from_slice(rest).unwrap();
+            InstructionTag::AddReply => {
+                let payload = ReplyPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
                 Self::AddReply {
                     reply: payload.reply,
                 }
             }
-            3 => Self::InitializeMint,
-            _ => return Err(ProgramError::InvalidInstructionData),
+            InstructionTag::InitializeMint => Self::InitializeMint {
+                decimals: rest.first().copied().unwrap_or(REWARD_DECIMALS),
+            },
+            InstructionTag::DeleteReply => {
+                let payload = ReplyIndexPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::DeleteReply {
+                    index: payload.index,
+                }
+            }
+            InstructionTag::UpdateReply => {
+                let payload = ReplyPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::UpdateReply {
+                    reply: payload.reply,
+                }
+            }
+            InstructionTag::RateIntro => {
+                let payload = RatingPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::RateIntro {
+                    rating: payload.rating,
+                }
+            }
+            InstructionTag::LikeReply => Self::LikeReply,
+            InstructionTag::Migrate => Self::Migrate,
+            InstructionTag::CloseStudentIntro => Self::CloseStudentIntro,
+            InstructionTag::TipAuthor => {
+                let payload = LamportsPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::TipAuthor {
+                    lamports: payload.lamports,
+                }
+            }
+            InstructionTag::AddNestedReply => {
+                let payload = NestedReplyPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AddNestedReply {
+                    reply: payload.reply,
+                    parent: payload.parent,
+                }
+            }
+            InstructionTag::AddReaction => {
+                let payload = ReactionPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AddReaction { kind: payload.kind }
+            }
+            InstructionTag::TransferIntroOwnership => {
+                let payload = TransferOwnershipPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::TransferIntroOwnership {
+                    new_owner: payload.new_owner,
+                }
+            }
+            InstructionTag::FlagReply => {
+                let payload = FlagPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::FlagReply {
+                    reason: payload.reason,
+                }
+            }
+            InstructionTag::AddToBlockList => {
+                let payload = BlockListPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AddToBlockList { key: payload.key }
+            }
+            InstructionTag::RemoveFromBlockList => {
+                let payload = BlockListPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::RemoveFromBlockList { key: payload.key }
+            }
+            InstructionTag::GetStats => Self::GetStats,
+            InstructionTag::AddRepliesBatch => {
+                let payload = RepliesBatchPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AddRepliesBatch {
+                    replies: payload.replies,
+                }
+            }
+            InstructionTag::SoftDeleteReply => {
+                let payload = SoftDeletePayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SoftDeleteReply {
+                    deleted: payload.deleted,
+                }
+            }
+            InstructionTag::WithdrawTreasury => {
+                let mut data = rest;
+                let payload = LamportsPayload::deserialize(&mut data)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                let destination = if data.is_empty() {
+                    None
+                } else {
+                    Some(
+                        Pubkey::try_from_slice(data)
+                            .map_err(|_| ProgramError::InvalidInstructionData)?,
+                    )
+                };
+                Self::WithdrawTreasury {
+                    lamports: payload.lamports,
+                    destination,
+                }
+            }
+            InstructionTag::BurnTokens => {
+                let payload = BurnPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::BurnTokens {
+                    amount: payload.amount,
+                }
+            }
+            InstructionTag::SetFreezeAuthority => {
+                let payload = FreezeAuthorityPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SetFreezeAuthority {
+                    new_authority: payload.new_authority,
+                }
+            }
+            InstructionTag::InitOrUpdate => {
+                let (payload, reward_amount, avatar_uri) = unpack_intro_input(rest)?;
+                Self::InitOrUpdate {
+                    name: payload.name,
+                    message: payload.message,
+                    reward_amount,
+                    avatar_uri,
+                }
+            }
+            InstructionTag::CloseOrphanReply => {
+                let payload = ReplyIndexPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::CloseOrphanReply {
+                    index: payload.index,
+                }
+            }
+            InstructionTag::SetConfig => {
+                let payload = ConfigPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SetConfig {
+                    max_name_len: payload.max_name_len,
+                    max_message_len: payload.max_message_len,
+                    reply_reward: payload.reply_reward,
+                    intro_reward: payload.intro_reward,
+                    min_reply_interval: payload.min_reply_interval,
+                }
+            }
+            InstructionTag::SetPaused => {
+                let payload = PausePayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SetPaused {
+                    paused: payload.paused,
+                }
+            }
+            InstructionTag::AppendMessageChunk => {
+                let payload = MessageChunkPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AppendMessageChunk { text: payload.text }
+            }
+            InstructionTag::ListReplies => {
+                let payload = ListRepliesPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::ListReplies {
+                    start: payload.start,
+                    limit: payload.limit,
+                }
+            }
+            InstructionTag::AddDeniedWord => {
+                let payload = DeniedWordPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AddDeniedWord { term: payload.term }
+            }
+            InstructionTag::RemoveDeniedWord => {
+                let payload = DeniedWordPayload::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::RemoveDeniedWord { term: payload.term }
+            }
         })
     }
 }
+
+/// The `InitUserInput` payload followed by its optional reward and avatar.
+fn unpack_intro_input(
+    mut data: &[u8],
+) -> Result<(StudentIntroPayload, Option<u64>, Option<String>), ProgramError> {
+    let payload = StudentIntroPayload::deserialize(&mut data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let reward_amount = if data.is_empty() {
+        None
+    } else {
+        Some(u64::deserialize(&mut data).map_err(|_| ProgramError::InvalidInstructionData)?)
+    };
+    let avatar_uri = if data.is_empty() {
+        None
+    } else {
+        Some(String::try_from_slice(data).map_err(|_| ProgramError::InvalidInstructionData)?)
+    };
+    Ok((payload, reward_amount, avatar_uri))
+}
+
+fn instruction_data<T: BorshSerialize>(tag: u8, payload: &T) -> Vec<u8> {
+    let mut data = vec![tag];
+    // Serializing into a Vec cannot fail for these payloads
+    data.extend(payload.try_to_vec().unwrap());
+    data
+}
+
+impl IntroInstruction {
+    /// The avatar is encoded after the reward, so passing one without a reward pins the
+    /// reward to `DEFAULT_INTRO_REWARD`, which needs the admin to co-sign while the config
+    /// sets a lower intro reward.
+    pub fn init_user_input(
+        program_id: &Pubkey,
+        initializer: &Pubkey,
+        name: String,
+        message: String,
+        reward_amount: Option<u64>,
+        avatar_uri: Option<String>,
+    ) -> Instruction {
+        let (user_account, _) = user_intro_pda(initializer, program_id);
+        let (reply_counter, _) = reply_counter_pda(&user_account, program_id);
+        let (token_mint, _) = token_mint_pda(program_id);
+        let (mint_auth, _) = mint_auth_pda(program_id);
+        let (name_index, _) = name_index_pda(&name, program_id);
+        let mut data = instruction_data(
+            InstructionTag::InitUserInput as u8,
+            &StudentIntroPayload { name, message },
+        );
+        let reward_amount = match avatar_uri {
+            Some(_) => Some(reward_amount.unwrap_or(DEFAULT_INTRO_REWARD)),
+            None => reward_amount,
+        };
+        if let Some(reward_amount) = reward_amount {
+            data.extend(reward_amount.to_le_bytes());
+        }
+        if let Some(avatar_uri) = avatar_uri {
+            // Serializing into a Vec cannot fail for these types
+            avatar_uri.serialize(&mut data).unwrap();
+        }
+        Instruction::new_with_bytes(
+            *program_id,
+            &data,
+            vec![
+                AccountMeta::new(*initializer, true),
+                AccountMeta::new(user_account, false),
+                AccountMeta::new(reply_counter, false),
+                AccountMeta::new(token_mint, false),
+                AccountMeta::new_readonly(mint_auth, false),
+                AccountMeta::new(
+                    get_associated_token_address(initializer, &token_mint),
+                    false,
+                ),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(block_list_pda(program_id).0, false),
+                AccountMeta::new(name_index, false),
+                AccountMeta::new(treasury_pda(program_id).0, false),
+                AccountMeta::new(intro_reward_marker_pda(initializer, program_id).0, false),
+                AccountMeta::new_readonly(word_denylist_pda(program_id).0, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+
+    /// `previous_name` is the intro's current name, or `None` if it may not exist yet.
+    /// `reward_amount` and `avatar_uri` are encoded as for `init_user_input`.
+    pub fn init_or_update(
+        program_id: &Pubkey,
+        initializer: &Pubkey,
+        previous_name: Option<&str>,
+        name: String,
+        message: String,
+        reward_amount: Option<u64>,
+        avatar_uri: Option<String>,
+    ) -> Instruction {
+        let (old_name_index, _) = name_index_pda(previous_name.unwrap_or(&name), program_id);
+        let mut instruction = Self::init_user_input(
+            program_id,
+            initializer,
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        );
+        instruction.data[0] = InstructionTag::InitOrUpdate as u8;
+        // Ahead of the trailing denylist and config accounts, where the processor expects it
+        let position = instruction.accounts.len() - 2;
+        instruction
+            .accounts
+            .insert(position, AccountMeta::new(old_name_index, false));
+        instruction
+    }
+
+    /// `previous_name` is the intro's current name, whose index is released when the
+    /// name changes.
+    pub fn update_student_intro(
+        program_id: &Pubkey,
+        initializer: &Pubkey,
+        previous_name: &str,
+        name: String,
+        message: String,
+        avatar_uri: Option<String>,
+    ) -> Instruction {
+        let (user_account, _) = user_intro_pda(initializer, program_id);
+        let (old_name_index, _) = name_index_pda(previous_name, program_id);
+        let (new_name_index, _) = name_index_pda(&name, program_id);
+        let mut data = instruction_data(
+            InstructionTag::UpdateStudentIntro as u8,
+            &StudentIntroPayload { name, message },
+        );
+        if let Some(avatar_uri) = avatar_uri {
+            // Serializing into a Vec cannot fail for these types
+            avatar_uri.serialize(&mut data).unwrap();
+        }
+        Instruction::new_with_bytes(
+            *program_id,
+            &data,
+            vec![
+                AccountMeta::new(*initializer, true),
+                AccountMeta::new(user_account, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(old_name_index, false),
+                AccountMeta::new(new_name_index, false),
+                AccountMeta::new_readonly(word_denylist_pda(program_id).0, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+
+    /// `reply_index` is the intro's current reply count, which seeds the new reply PDA.
+    pub fn add_reply(
+        program_id: &Pubkey,
+        replier: &Pubkey,
+        user_account: &Pubkey,
+        reply_index: u64,
+        reply: String,
+    ) -> Instruction {
+        let (reply_counter, _) = reply_counter_pda(user_account, program_id);
+        let (reply_account, _) = reply_pda(user_account, reply_index, program_id);
+        let (token_mint, _) = token_mint_pda(program_id);
+        let (mint_auth, _) = mint_auth_pda(program_id);
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data(InstructionTag::AddReply as u8, &ReplyPayload { reply }),
+            vec![
+                AccountMeta::new(*replier, true),
+                AccountMeta::new_readonly(*user_account, false),
+                AccountMeta::new(reply_counter, false),
+                AccountMeta::new(reply_account, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(token_mint, false),
+                AccountMeta::new_readonly(mint_auth, false),
+                AccountMeta::new(get_associated_token_address(replier, &token_mint), false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(block_list_pda(program_id).0, false),
+                AccountMeta::new(reply_index_pda(user_account, program_id).0, false),
+                AccountMeta::new(
+                    reply_reward_marker_pda(&reply_account, replier, program_id).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(word_denylist_pda(program_id).0, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+
+    /// Invokes `add_reply` from another program. `account_infos` must hold every account
+    /// the builder names plus this program, in any order. `signer_seeds` sign for a
+    /// replier that is a PDA of the calling program and may be empty otherwise.
+    pub fn add_reply_cpi(
+        program_id: &Pubkey,
+        replier: &Pubkey,
+        user_account: &Pubkey,
+        reply_index: u64,
+        reply: String,
+        account_infos: &[AccountInfo],
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke_signed(
+            &Self::add_reply(program_id, replier, user_account, reply_index, reply),
+            account_infos,
+            signer_seeds,
+        )
+    }
+
+    /// `first_reply_index` is the intro's current reply count; the batch takes the
+    /// following indices in order, each with its reward marker.
+    pub fn add_replies_batch(
+        program_id: &Pubkey,
+        replier: &Pubkey,
+        user_account: &Pubkey,
+        first_reply_index: u64,
+        replies: Vec<String>,
+    ) -> Instruction {
+        let (reply_counter, _) = reply_counter_pda(user_account, program_id);
+        let (token_mint, _) = token_mint_pda(program_id);
+        let (mint_auth, _) = mint_auth_pda(program_id);
+        let mut accounts = vec![
+            AccountMeta::new(*replier, true),
+            AccountMeta::new_readonly(*user_account, false),
+            AccountMeta::new(reply_counter, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(token_mint, false),
+            AccountMeta::new_readonly(mint_auth, false),
+            AccountMeta::new(get_associated_token_address(replier, &token_mint), false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(block_list_pda(program_id).0, false),
+            AccountMeta::new(reply_index_pda(user_account, program_id).0, false),
+        ];
+        for index in first_reply_index..first_reply_index + replies.len() as u64 {
+            let (reply_account, _) = reply_pda(user_account, index, program_id);
+            let (reward_marker, _) = reply_reward_marker_pda(&reply_account, replier, program_id);
+            accounts.push(AccountMeta::new(reply_account, false));
+            accounts.push(AccountMeta::new(reward_marker, false));
+        }
+        accounts.push(AccountMeta::new_readonly(
+            word_denylist_pda(program_id).0,
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(config_pda(program_id).0, false));
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data(
+                InstructionTag::AddRepliesBatch as u8,
+                &RepliesBatchPayload { replies },
+            ),
+            accounts,
+        )
+    }
+
+    /// `chunk_index` is the intro's current `message_chunks`, which seeds the new chunk PDA.
+    pub fn append_message_chunk(
+        program_id: &Pubkey,
+        initializer: &Pubkey,
+        chunk_index: u8,
+        text: String,
+    ) -> Instruction {
+        let (user_account, _) = user_intro_pda(initializer, program_id);
+        let (chunk, _) = message_chunk_pda(&user_account, chunk_index, program_id);
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data(
+                InstructionTag::AppendMessageChunk as u8,
+                &MessageChunkPayload { text },
+            ),
+            vec![
+                AccountMeta::new(*initializer, true),
+                AccountMeta::new(user_account, false),
+                AccountMeta::new(chunk, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(word_denylist_pda(program_id).0, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+
+    /// `name` is the intro's current name, whose index is repointed at the new intro, and
+    /// `message_chunks` its current chunk count.
+    pub fn transfer_intro_ownership(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        new_owner: &Pubkey,
+        name: &str,
+        message_chunks: u8,
+    ) -> Instruction {
+        let (user_account, _) = user_intro_pda(owner, program_id);
+        let (new_user_account, _) = user_intro_pda(new_owner, program_id);
+        let mut accounts = vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new_readonly(*new_owner, true),
+            AccountMeta::new(user_account, false),
+            AccountMeta::new_readonly(reply_counter_pda(&user_account, program_id).0, false),
+            AccountMeta::new(new_user_account, false),
+            AccountMeta::new(reply_counter_pda(&new_user_account, program_id).0, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(name_index_pda(name, program_id).0, false),
+            AccountMeta::new(reply_index_pda(&user_account, program_id).0, false),
+        ];
+        for chunk_index in 0..message_chunks {
+            let (old_chunk, _) = message_chunk_pda(&user_account, chunk_index, program_id);
+            let (new_chunk, _) = message_chunk_pda(&new_user_account, chunk_index, program_id);
+            accounts.push(AccountMeta::new(old_chunk, false));
+            accounts.push(AccountMeta::new(new_chunk, false));
+        }
+        accounts.push(AccountMeta::new_readonly(config_pda(program_id).0, false));
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data(
+                InstructionTag::TransferIntroOwnership as u8,
+                &TransferOwnershipPayload {
+                    new_owner: *new_owner,
+                },
+            ),
+            accounts,
+        )
+    }
+
+    /// `name` is the intro's current name, whose index is closed with it, and
+    /// `message_chunks` its current chunk count.
+    pub fn close_student_intro(
+        program_id: &Pubkey,
+        initializer: &Pubkey,
+        name: &str,
+        message_chunks: u8,
+    ) -> Instruction {
+        let (user_account, _) = user_intro_pda(initializer, program_id);
+        let mut accounts = vec![
+            AccountMeta::new(*initializer, true),
+            AccountMeta::new(user_account, false),
+            AccountMeta::new(reply_counter_pda(&user_account, program_id).0, false),
+            AccountMeta::new(name_index_pda(name, program_id).0, false),
+            AccountMeta::new(reply_index_pda(&user_account, program_id).0, false),
+        ];
+        for chunk_index in 0..message_chunks {
+            let (chunk, _) = message_chunk_pda(&user_account, chunk_index, program_id);
+            accounts.push(AccountMeta::new(chunk, false));
+        }
+        accounts.push(AccountMeta::new_readonly(config_pda(program_id).0, false));
+        Instruction::new_with_bytes(
+            *program_id,
+            &[InstructionTag::CloseStudentIntro as u8],
+            accounts,
+        )
+    }
+
+    /// `index` is the reply's index under `user_account`.
+    pub fn close_orphan_reply(
+        program_id: &Pubkey,
+        replier: &Pubkey,
+        user_account: &Pubkey,
+        index: u64,
+    ) -> Instruction {
+        Instruction::new_with_bytes(
+            *program_id,
+            &instruction_data(
+                InstructionTag::CloseOrphanReply as u8,
+                &ReplyIndexPayload { index },
+            ),
+            vec![
+                AccountMeta::new(*replier, false),
+                AccountMeta::new_readonly(*user_account, false),
+                AccountMeta::new(reply_pda(user_account, index, program_id).0, false),
+                AccountMeta::new_readonly(reply_counter_pda(user_account, program_id).0, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+
+    pub fn initialize_mint(program_id: &Pubkey, initializer: &Pubkey, decimals: u8) -> Instruction {
+        let (token_mint, _) = token_mint_pda(program_id);
+        let (mint_auth, _) = mint_auth_pda(program_id);
+        let (metadata, _) = token_metadata_pda(&token_mint);
+        Instruction::new_with_bytes(
+            *program_id,
+            &[InstructionTag::InitializeMint as u8, decimals],
+            vec![
+                AccountMeta::new(*initializer, true),
+                AccountMeta::new(token_mint, false),
+                AccountMeta::new_readonly(mint_auth, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(sysvar::rent::ID, false),
+                AccountMeta::new(metadata, false),
+                AccountMeta::new_readonly(TOKEN_METADATA_PROGRAM_ID, false),
+                AccountMeta::new_readonly(config_pda(program_id).0, false),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unpack_err(data: &[u8]) -> ProgramError {
+        match IntroInstruction::unpack(data) {
+            Ok(_) => panic!("{:?} unpacked", data),
+            Err(error) => error,
+        }
+    }
+
+    fn unpack(data: &[u8]) -> IntroInstruction {
+        match IntroInstruction::unpack(data) {
+            Ok(instruction) => instruction,
+            Err(error) => panic!("{:?} failed to unpack: {}", data, error),
+        }
+    }
+
+    #[test]
+    fn unpack_init_user_input() {
+        let program_id = Pubkey::new_unique();
+        let initializer = Pubkey::new_unique();
+        let instruction = IntroInstruction::init_user_input(
+            &program_id,
+            &initializer,
+            "Alice".to_string(),
+            "Hello, I am Alice".to_string(),
+            None,
+            None,
+        );
+        match unpack(&instruction.data) {
+            IntroInstruction::InitUserInput {
+                name,
+                message,
+                reward_amount,
+                avatar_uri,
+            } => {
+                assert_eq!(name, "Alice");
+                assert_eq!(message, "Hello, I am Alice");
+                assert_eq!(reward_amount, None);
+                assert_eq!(avatar_uri, None);
+            }
+            _ => panic!("unpacked to another variant"),
+        }
+
+        let instruction = IntroInstruction::init_user_input(
+            &program_id,
+            &initializer,
+            "Alice".to_string(),
+            "Hello".to_string(),
+            Some(5),
+            Some("https://example.com/alice.png".to_string()),
+        );
+        match unpack(&instruction.data) {
+            IntroInstruction::InitUserInput {
+                reward_amount,
+                avatar_uri,
+                ..
+            } => {
+                assert_eq!(reward_amount, Some(5));
+                assert_eq!(avatar_uri.as_deref(), Some("https://example.com/alice.png"));
+            }
+            _ => panic!("unpacked to another variant"),
+        }
+    }
+
+    #[test]
+    fn unpack_init_or_update() {
+        let instruction = IntroInstruction::init_or_update(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            None,
+            "Alice".to_string(),
+            "Hello".to_string(),
+            Some(5),
+            Some("https://example.com/alice.png".to_string()),
+        );
+        match unpack(&instruction.data) {
+            IntroInstruction::InitOrUpdate {
+                name,
+                message,
+                reward_amount,
+                avatar_uri,
+            } => {
+                assert_eq!(name, "Alice");
+                assert_eq!(message, "Hello");
+                assert_eq!(reward_amount, Some(5));
+                assert_eq!(avatar_uri.as_deref(), Some("https://example.com/alice.png"));
+            }
+            _ => panic!("unpacked to another variant"),
+        }
+    }
+
+    #[test]
+    fn unpack_update_student_intro() {
+        let instruction = IntroInstruction::update_student_intro(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            "Alice",
+            "Alicia".to_string(),
+            "Hello again".to_string(),
+            Some(String::new()),
+        );
+        match unpack(&instruction.data) {
+            IntroInstruction::UpdateStudentIntro {
+                name,
+                message,
+                avatar_uri,
+            } => {
+                assert_eq!(name, "Alicia");
+                assert_eq!(message, "Hello again");
+                assert_eq!(avatar_uri.as_deref(), Some(""));
+            }
+            _ => panic!("unpacked to another variant"),
+        }
+    }
+
+    #[test]
+    fn unpack_add_reply() {
+        let instruction = IntroInstruction::add_reply(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            3,
+            "Welcome, Alice".to_string(),
+        );
+        match unpack(&instruction.data) {
+            IntroInstruction::AddReply { reply } => assert_eq!(reply, "Welcome, Alice"),
+            _ => panic!("unpacked to another variant"),
+        }
+    }
+
+    #[test]
+    fn unpack_initialize_mint() {
+        let instruction =
+            IntroInstruction::initialize_mint(&Pubkey::new_unique(), &Pubkey::new_unique(), 6);
+        match unpack(&instruction.data) {
+            IntroInstruction::InitializeMint { decimals } => assert_eq!(decimals, 6),
+            _ => panic!("unpacked to another variant"),
+        }
+
+        match unpack(&[InstructionTag::InitializeMint as u8]) {
+            IntroInstruction::InitializeMint { decimals } => {
+                assert_eq!(decimals, REWARD_DECIMALS)
+            }
+            _ => panic!("unpacked to another variant"),
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_empty_data() {
+        assert_eq!(unpack_err(&[]), ProgramError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn unpack_rejects_unknown_tag() {
+        assert_eq!(
+            unpack_err(&[u8::MAX]),
+            StudentIntroError::InvalidInstruction.into()
+        );
+    }
+
+    #[test]
+    fn unpack_rejects_missing_payloads() {
+        let tags = [
+            InstructionTag::InitUserInput,
+            InstructionTag::UpdateStudentIntro,
+            InstructionTag::AddReply,
+            InstructionTag::DeleteReply,
+            InstructionTag::UpdateReply,
+            InstructionTag::RateIntro,
+            InstructionTag::TipAuthor,
+            InstructionTag::AddNestedReply,
+            InstructionTag::AddReaction,
+            InstructionTag::TransferIntroOwnership,
+            InstructionTag::FlagReply,
+            InstructionTag::AddToBlockList,
+            InstructionTag::RemoveFromBlockList,
+            InstructionTag::AddRepliesBatch,
+            InstructionTag::SoftDeleteReply,
+            InstructionTag::WithdrawTreasury,
+            InstructionTag::BurnTokens,
+            InstructionTag::SetFreezeAuthority,
+            InstructionTag::InitOrUpdate,
+            InstructionTag::CloseOrphanReply,
+            InstructionTag::SetConfig,
+            InstructionTag::SetPaused,
+            InstructionTag::AppendMessageChunk,
+            InstructionTag::ListReplies,
+            InstructionTag::AddDeniedWord,
+            InstructionTag::RemoveDeniedWord,
+        ];
+        for tag in tags {
+            assert_eq!(
+                unpack_err(&[tag as u8]),
+                ProgramError::InvalidInstructionData,
+                "{:?}",
+                tag
+            );
+        }
+    }
+
+    #[test]
+    fn unpack_rejects_truncated_string() {
+        // Claims a 10 byte reply but carries only 2
+        let data = [InstructionTag::AddReply as u8, 10, 0, 0, 0, b'h', b'i'];
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn unpack_rejects_invalid_utf8() {
+        let data = [InstructionTag::AddReply as u8, 2, 0, 0, 0, 0xff, 0xfe];
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn unpack_rejects_trailing_bytes() {
+        let data = [InstructionTag::RateIntro as u8, 5, 0];
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn unpack_rejects_unknown_reaction_kind() {
+        let data = [
+            InstructionTag::AddReaction as u8,
+            crate::state::REACTION_KINDS as u8,
+        ];
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+    }
+
+    #[test]
+    fn unpack_rejects_truncated_optional_fields() {
+        let mut data = instruction_data(
+            InstructionTag::InitUserInput as u8,
+            &StudentIntroPayload {
+                name: "Alice".to_string(),
+                message: "Hello".to_string(),
+            },
+        );
+        // Half of the optional reward amount
+        data.extend([1, 2, 3, 4]);
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+
+        let mut data = vec![InstructionTag::WithdrawTreasury as u8];
+        data.extend(1u64.to_le_bytes());
+        // Half of the optional destination
+        data.extend([7; 16]);
+        assert_eq!(unpack_err(&data), ProgramError::InvalidInstructionData);
+    }
+}
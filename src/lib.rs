@@ -1,5 +1,77 @@
+//! Student intro program.
+//!
+//! Programs that call this one through CPI should depend on it with the
+//! `no-entrypoint` feature so its `entrypoint!` symbol is not linked into theirs:
+//!
+//! ```toml
+//! [dependencies]
+//! solana-student-intros-program = { version = "0.1.0", features = ["no-entrypoint"] }
+//! ```
+//!
+//! The instruction builders, account types and PDA helpers are re-exported from
+//! [`prelude`]:
+//!
+//! ```
+//! use solana_program::pubkey::Pubkey;
+//! use solana_student_intros_program::prelude::*;
+//!
+//! let program_id = id();
+//! let initializer = Pubkey::new_unique();
+//! let instruction = IntroInstruction::init_user_input(
+//!     &program_id,
+//!     &initializer,
+//!     "Alice".to_string(),
+//!     "Hello!".to_string(),
+//!     None,
+//!     None,
+//! );
+//! assert_eq!(instruction.program_id, program_id);
+//! assert_eq!(instruction.accounts[1].pubkey, user_intro_pda(&initializer, &program_id).0);
+//! // invoke(&instruction, &account_infos)?;
+//! ```
+//!
+//! `examples/cpi_add_reply.rs` replies through CPI with `IntroInstruction::add_reply_cpi`,
+//! signing as a PDA of the calling program.
+//!
+//! Step-by-step trace logging is compiled out unless the `debug-logs` feature is enabled.
+//! With the `freeze-authority` feature, the reward mint is created with a program-owned
+//! freeze authority that the admin can later hand over with `SetFreezeAuthority`.
+
+/// `msg!` for trace output that only matters while debugging. Logging costs compute units
+/// on every call, so these are skipped unless the `debug-logs` feature is enabled.
+macro_rules! debug_msg {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "debug-logs") {
+            solana_program::msg!($($arg)*);
+        }
+    };
+}
+
+solana_program::declare_id!("3W2j4rfAuGca4FuuJbcb7HHqskoZzCvchRH6Z3m5nq8y");
+
+#[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
 pub mod error;
+pub mod events;
+pub mod guards;
 pub mod instruction;
+pub mod metadata;
+pub mod pda;
+pub mod prelude;
 pub mod processor;
 pub mod state;
+
+#[cfg(test)]
+mod tests {
+    use solana_program::pubkey;
+
+    #[test]
+    fn program_id_is_the_deployed_key() {
+        assert_eq!(
+            crate::id(),
+            pubkey!("3W2j4rfAuGca4FuuJbcb7HHqskoZzCvchRH6Z3m5nq8y")
+        );
+        assert!(crate::check_id(&crate::id()));
+        assert!(!crate::check_id(&spl_token::ID));
+    }
+}
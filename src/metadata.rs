@@ -0,0 +1,54 @@
+//! Minimal client for the Metaplex Token Metadata program, covering only the
+//! `CreateMetadataAccountV3` instruction used to name the reward token with the
+//! fixed name, symbol and URI below.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+pub const TOKEN_NAME: &str = "Student Intro Token";
+pub const TOKEN_SYMBOL: &str = "INTRO";
+/// No off-chain JSON is published for the token yet.
+pub const TOKEN_URI: &str = "";
+
+const CREATE_METADATA_ACCOUNT_V3: u8 = 33;
+
+pub fn create_metadata_account_v3(
+    metadata: &Pubkey,
+    mint: &Pubkey,
+    mint_authority: &Pubkey,
+    payer: &Pubkey,
+    update_authority: &Pubkey,
+) -> Instruction {
+    let mut data = vec![CREATE_METADATA_ACCOUNT_V3];
+    // Serializing into a Vec cannot fail for these types
+    (TOKEN_NAME, TOKEN_SYMBOL, TOKEN_URI, 0u16)
+        .serialize(&mut data)
+        .unwrap();
+    // creators, collection and uses: None
+    data.extend_from_slice(&[0, 0, 0]);
+    // is_mutable, so the update authority can fix the URI later
+    data.push(1);
+    // collection_details: None
+    data.push(0);
+
+    Instruction {
+        program_id: TOKEN_METADATA_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*metadata, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*mint_authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*update_authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+        data,
+    }
+}
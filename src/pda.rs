@@ -0,0 +1,230 @@
+use crate::metadata::TOKEN_METADATA_PROGRAM_ID;
+use crate::state::ReactionKind;
+use solana_program::{hash::hash, pubkey::Pubkey};
+use std::ops::Range;
+
+pub const REPLY_COUNTER_SEED: &[u8] = b"reply";
+pub const TOKEN_MINT_SEED: &[u8] = b"token_mint";
+pub const MINT_AUTH_SEED: &[u8] = b"token_auth";
+pub const FREEZE_AUTH_SEED: &[u8] = b"freeze_auth";
+pub const LIKE_SEED: &[u8] = b"like";
+pub const REACTION_SEED: &[u8] = b"reaction";
+pub const METADATA_SEED: &[u8] = b"metadata";
+pub const FLAG_SEED: &[u8] = b"flag";
+pub const BLOCKLIST_SEED: &[u8] = b"blocklist";
+pub const NAME_SEED: &[u8] = b"name";
+pub const TREASURY_SEED: &[u8] = b"treasury";
+pub const REPLY_INDEX_SEED: &[u8] = b"reply_index";
+pub const CONFIG_SEED: &[u8] = b"config";
+pub const RATING_SEED: &[u8] = b"rating";
+pub const CHUNK_SEED: &[u8] = b"chunk";
+pub const DENYLIST_SEED: &[u8] = b"denylist";
+pub const REPLY_REWARD_SEED: &[u8] = b"reply_reward";
+pub const INTRO_REWARD_SEED: &[u8] = b"reward_claimed";
+
+pub fn user_intro_pda(initializer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[initializer.as_ref()], program_id)
+}
+
+pub fn message_chunk_pda(intro: &Pubkey, index: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[intro.as_ref(), CHUNK_SEED, &[index]], program_id)
+}
+
+pub fn reply_counter_pda(intro: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[intro.as_ref(), REPLY_COUNTER_SEED], program_id)
+}
+
+pub fn reply_pda(intro: &Pubkey, index: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[intro.as_ref(), index.to_be_bytes().as_ref()], program_id)
+}
+
+/// Reply PDAs for `indices` in order, for fetching with one `getMultipleAccounts` call.
+/// Pass `ReplyCounter::indices` for the current intro's replies. Deleted replies leave
+/// missing accounts in the list.
+pub fn reply_pdas_for(intro: &Pubkey, indices: Range<u64>, program_id: &Pubkey) -> Vec<Pubkey> {
+    indices
+        .map(|index| reply_pda(intro, index, program_id).0)
+        .collect()
+}
+
+pub fn reply_index_pda(intro: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[intro.as_ref(), REPLY_INDEX_SEED], program_id)
+}
+
+pub fn like_marker_pda(reply: &Pubkey, liker: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[reply.as_ref(), liker.as_ref(), LIKE_SEED], program_id)
+}
+
+/// Seeded by the wallet alone, so closing and recreating an intro, or receiving one by
+/// transfer, never earns a second creation reward.
+pub fn intro_reward_marker_pda(initializer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[INTRO_REWARD_SEED, initializer.as_ref()], program_id)
+}
+
+/// Seeded by the reply's address, which is itself seeded by its intro and index, so one
+/// reply index pays its replier at most once.
+pub fn reply_reward_marker_pda(
+    reply: &Pubkey,
+    replier: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[REPLY_REWARD_SEED, reply.as_ref(), replier.as_ref()],
+        program_id,
+    )
+}
+
+pub fn rating_marker_pda(intro: &Pubkey, rater: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RATING_SEED, intro.as_ref(), rater.as_ref()], program_id)
+}
+
+pub fn reaction_marker_pda(
+    reply: &Pubkey,
+    reactor: &Pubkey,
+    kind: ReactionKind,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            reply.as_ref(),
+            reactor.as_ref(),
+            REACTION_SEED,
+            &[kind as u8],
+        ],
+        program_id,
+    )
+}
+
+pub fn flag_marker_pda(reply: &Pubkey, reporter: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[reply.as_ref(), reporter.as_ref(), FLAG_SEED], program_id)
+}
+
+pub fn block_list_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BLOCKLIST_SEED], program_id)
+}
+
+/// First 8 bytes of the sha256 of the lowercased name, so names differing only in case
+/// map to the same index.
+pub fn name_hash(name: &str) -> [u8; 8] {
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&hash(name.to_lowercase().as_bytes()).to_bytes()[..8]);
+    prefix
+}
+
+pub fn name_index_pda(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[NAME_SEED, &name_hash(name)], program_id)
+}
+
+/// System-owned PDA that collects intro creation fees.
+pub fn treasury_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TREASURY_SEED], program_id)
+}
+
+pub fn word_denylist_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[DENYLIST_SEED], program_id)
+}
+
+pub fn config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+pub fn token_mint_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[TOKEN_MINT_SEED], program_id)
+}
+
+pub fn mint_auth_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_AUTH_SEED], program_id)
+}
+
+pub fn freeze_auth_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FREEZE_AUTH_SEED], program_id)
+}
+
+/// Derived under the Token Metadata program, not this one.
+pub fn token_metadata_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            METADATA_SEED,
+            TOKEN_METADATA_PROGRAM_ID.as_ref(),
+            mint.as_ref(),
+        ],
+        &TOKEN_METADATA_PROGRAM_ID,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_pda_is_deterministic() {
+        let program_id = Pubkey::new_unique();
+        let intro = Pubkey::new_unique();
+        for index in [0, 1, 255, 256] {
+            assert_eq!(
+                reply_pda(&intro, index, &program_id),
+                reply_pda(&intro, index, &program_id)
+            );
+        }
+    }
+
+    #[test]
+    fn reply_pda_is_seeded_by_big_endian_index() {
+        let program_id = Pubkey::new_unique();
+        let intro = Pubkey::new_unique();
+        for index in [0u64, 1, 255, 256] {
+            assert_eq!(
+                reply_pda(&intro, index, &program_id),
+                Pubkey::find_program_address(&[intro.as_ref(), &index.to_be_bytes()], &program_id)
+            );
+        }
+    }
+
+    #[test]
+    fn reply_pdas_differ_across_indices() {
+        let program_id = Pubkey::new_unique();
+        let intro = Pubkey::new_unique();
+        let pdas: Vec<_> = [0, 1, 255, 256]
+            .iter()
+            .map(|index| reply_pda(&intro, *index, &program_id).0)
+            .collect();
+        for (i, pda) in pdas.iter().enumerate() {
+            assert!(!pdas[i + 1..].contains(pda), "index {} collides", i);
+        }
+    }
+
+    #[test]
+    fn reply_pdas_differ_across_intros() {
+        let program_id = Pubkey::new_unique();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for index in [0, 1, 255, 256] {
+            assert_ne!(
+                reply_pda(&first, index, &program_id).0,
+                reply_pda(&second, index, &program_id).0
+            );
+        }
+    }
+
+    #[test]
+    fn reply_pdas_for_follows_reply_pda() {
+        let program_id = Pubkey::new_unique();
+        let intro = Pubkey::new_unique();
+        let expected: Vec<_> = (254..258)
+            .map(|index| reply_pda(&intro, index, &program_id).0)
+            .collect();
+        assert_eq!(reply_pdas_for(&intro, 254..258, &program_id), expected);
+    }
+
+    #[test]
+    fn name_index_pda_ignores_case() {
+        let program_id = Pubkey::new_unique();
+        assert_eq!(
+            name_index_pda("Alice", &program_id),
+            name_index_pda("aLICE", &program_id)
+        );
+        assert_ne!(
+            name_index_pda("Alice", &program_id),
+            name_index_pda("Alicia", &program_id)
+        );
+    }
+}
@@ -0,0 +1,23 @@
+//! The supported surface for clients and programs calling this one through CPI.
+//! Everything here is kept stable across releases; items reached only through the
+//! other modules may change.
+
+pub use crate::error::StudentIntroError;
+pub use crate::events::IntroEvent;
+pub use crate::instruction::{
+    CreatedAccounts, InstructionTag, IntroInstruction, IntroStats, ReplyPage,
+};
+pub use crate::pda::{
+    block_list_pda, config_pda, flag_marker_pda, intro_reward_marker_pda, like_marker_pda,
+    message_chunk_pda, mint_auth_pda, name_index_pda, rating_marker_pda, reaction_marker_pda,
+    reply_counter_pda, reply_index_pda, reply_pda, reply_pdas_for, reply_reward_marker_pda,
+    token_metadata_pda, token_mint_pda, treasury_pda, user_intro_pda, word_denylist_pda,
+};
+pub use crate::processor::process_instruction;
+pub use crate::state::{
+    account_header, identify_account, AccountType, BlockList, Config, FlagMarker, FlagReason,
+    LikeMarker, MessageChunk, NameIndex, RatingMarker, ReactionKind, ReactionMarker, Reply,
+    ReplyCounter, ReplyEdit, ReplyIndex, RewardMarker, StudentInfo, TypedAccount, WordDenylist,
+    ACCOUNT_MAGIC,
+};
+pub use crate::{check_id, id, ID};
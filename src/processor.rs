@@ -1,43 +1,199 @@
 use crate::error::StudentIntroError;
-use crate::instruction::IntroInstruction;
-use crate::state::{Reply, ReplyCounter, StudentInfo};
-use borsh::BorshSerialize;
+use crate::events::{
+    BlockListUpdated, ConfigUpdated, FreezeAuthorityUpdated, IntroEvent, IntroOwnershipTransferred,
+    IntroRated, PauseUpdated, ReactionAdded, ReplyAdded, ReplyDeleted, ReplyFlagged, ReplyLiked,
+    ReplySoftDeleted, ReplyUpdated, StudentIntroCreated, StudentIntroUpdated, TipSent,
+    TokenMintInitialized, TokensBurned, TreasuryWithdrawn, WordDenylistUpdated,
+};
+use crate::guards::{require_admin, require_key_eq, require_owned_by, require_pda, require_signer};
+use crate::instruction::{
+    CreatedAccounts, IntroInstruction, IntroStats, ReplyPage, CREATED_ACCOUNTS_VERSION,
+};
+use crate::metadata::{create_metadata_account_v3, TOKEN_METADATA_PROGRAM_ID};
+use crate::pda::{
+    block_list_pda, config_pda, flag_marker_pda, intro_reward_marker_pda, like_marker_pda,
+    message_chunk_pda, name_hash, name_index_pda, rating_marker_pda, reaction_marker_pda,
+    reply_counter_pda, reply_index_pda, reply_pda, reply_reward_marker_pda, treasury_pda,
+    user_intro_pda, word_denylist_pda, BLOCKLIST_SEED, CHUNK_SEED, CONFIG_SEED, DENYLIST_SEED,
+    FLAG_SEED, FREEZE_AUTH_SEED, INTRO_REWARD_SEED, LIKE_SEED, MINT_AUTH_SEED, NAME_SEED,
+    RATING_SEED, REACTION_SEED, REPLY_COUNTER_SEED, REPLY_INDEX_SEED, REPLY_REWARD_SEED,
+    TOKEN_MINT_SEED, TREASURY_SEED,
+};
+use crate::state::{
+    account_header, legacy_account_type, v1_account_type, AccountType, BlockList, Config,
+    FlagMarker, FlagReason, LegacyReply, LegacyReplyCounter, LegacyStudentInfo, LikeMarker,
+    MessageChunk, NameIndex, RatingMarker, ReactionKind, ReactionMarker, Reply, ReplyCounter,
+    ReplyCounterV1, ReplyEdit, ReplyIndex, RewardMarker, StudentInfo, StudentInfoV1, TypedAccount,
+    WordDenylist, ACCOUNT_MAGIC, CURRENT_VERSION, MAX_AVATAR_URI_LEN, MAX_BATCH_REPLIES,
+    MAX_BLOCKED_KEYS, MAX_DENIED_WORDS, MAX_DENIED_WORD_LEN, MAX_INTRO_ACCOUNT_LEN,
+    MAX_LIST_REPLIES, MAX_MESSAGE_CHARS, MAX_MESSAGE_CHUNKS, MAX_NAME_LEN, MAX_REPLIES,
+    MAX_REPLY_CHARS, REACTION_KINDS, RECENT_REPLIES, REPLY_EDIT_HISTORY,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     borsh::try_from_slice_unchecked,
     entrypoint::ProgramResult,
+    hash::hash,
     msg,
     native_token::LAMPORTS_PER_SOL,
-    program::invoke_signed,
+    program::{invoke, invoke_signed, set_return_data},
     program_error::ProgramError,
-    program_pack::IsInitialized,
+    program_option::COption,
+    program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
     system_instruction,
     system_program::ID as SYSTEM_PROGRAM_ID,
-    sysvar::{rent::Rent, rent::ID as RENT_PROGRAM_ID, Sysvar},
+    sysvar::{clock::Clock, rent::Rent, rent::ID as RENT_PROGRAM_ID, Sysvar},
 };
 use spl_associated_token_account::get_associated_token_address;
 use spl_token::{
-    instruction::{initialize_mint, mint_to},
+    instruction::{burn, initialize_mint, mint_to, set_authority, AuthorityType},
+    state::{Account as TokenAccount, Mint},
     ID as TOKEN_PROGRAM_ID,
 };
 use std::convert::TryInto;
 
+/// The only key allowed to create the reward token mint.
+pub const ADMIN_PUBKEY: Pubkey =
+    solana_program::pubkey!("fVuRgip1qDdoG6ZfPtWMhj8Sq7XWahzrzMFKojJC2Fq");
+
+/// Reward and burn amounts are written with this many decimals and scaled down to the
+/// mint's own precision when tokens move.
+pub const REWARD_DECIMALS: u8 = 9;
+/// Tokens minted to a replier for each reply. Reply indices are never reused and each one
+/// pays out once, guarded by a `RewardMarker` that deleting the reply leaves in place, so
+/// an intro pays at most `MAX_REPLIES` rewards. Self-replies are rejected.
+pub const REPLY_REWARD: u64 = LAMPORTS_PER_SOL;
+/// Least number of tokens burned from the replier before each reply. A reply burns the
+/// configured reply reward when that is higher, so a reply never adds to the replier's
+/// balance and farming the reward gains nothing.
+pub const REPLY_BURN_AMOUNT: u64 = REPLY_REWARD;
+/// Tokens minted for a new intro when the instruction does not name an amount.
+pub const DEFAULT_INTRO_REWARD: u64 = 10 * LAMPORTS_PER_SOL;
+pub const MAX_INTRO_REWARD: u64 = 100 * LAMPORTS_PER_SOL;
+/// Lamports paid into the treasury PDA for each new intro. Zero disables the fee.
+pub const INTRO_CREATION_FEE: u64 = LAMPORTS_PER_SOL / 100;
+/// Minimum number of seconds between two updates of the same intro.
+pub const EDIT_COOLDOWN_SECONDS: i64 = 60;
+/// Default minimum number of seconds between two replies to the same intro.
+pub const MIN_REPLY_INTERVAL_SECONDS: i64 = 10;
+
 pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
     let instruction = IntroInstruction::unpack(instruction_data)?;
+    // GetStats and ListReplies only read, and the config instructions must keep working to lift a pause
+    if !matches!(
+        instruction,
+        IntroInstruction::GetStats
+            | IntroInstruction::ListReplies { .. }
+            | IntroInstruction::SetConfig { .. }
+            | IntroInstruction::SetPaused { .. }
+    ) {
+        check_not_paused(program_id, accounts)?;
+    }
     match instruction {
-        IntroInstruction::InitUserInput { name, message } => {
-            add_student_intro(program_id, accounts, name, message)
+        IntroInstruction::InitUserInput {
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        } => add_student_intro(
+            program_id,
+            accounts,
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        ),
+        IntroInstruction::UpdateStudentIntro {
+            name,
+            message,
+            avatar_uri,
+        } => update_student_intro(program_id, accounts, name, message, avatar_uri),
+        IntroInstruction::AddReply { reply } => add_reply(program_id, accounts, reply),
+        IntroInstruction::InitializeMint { decimals } => {
+            initialize_token_mint(program_id, accounts, decimals)
         }
-        IntroInstruction::UpdateStudentIntro { name, message } => {
-            update_student_intro(program_id, accounts, name, message)
+        IntroInstruction::DeleteReply { index } => delete_reply(program_id, accounts, index),
+        IntroInstruction::UpdateReply { reply } => update_reply(program_id, accounts, reply),
+        IntroInstruction::RateIntro { rating } => rate_intro(program_id, accounts, rating),
+        IntroInstruction::LikeReply => like_reply(program_id, accounts),
+        IntroInstruction::Migrate => migrate_account(program_id, accounts),
+        IntroInstruction::CloseStudentIntro => close_student_intro(program_id, accounts),
+        IntroInstruction::TipAuthor { lamports } => tip_author(program_id, accounts, lamports),
+        IntroInstruction::AddNestedReply { reply, parent } => {
+            add_nested_reply(program_id, accounts, reply, parent)
+        }
+        IntroInstruction::AddReaction { kind } => add_reaction(program_id, accounts, kind),
+        IntroInstruction::TransferIntroOwnership { new_owner } => {
+            transfer_intro_ownership(program_id, accounts, new_owner)
+        }
+        IntroInstruction::FlagReply { reason } => flag_reply(program_id, accounts, reason),
+        IntroInstruction::AddToBlockList { key } => add_to_block_list(program_id, accounts, key),
+        IntroInstruction::RemoveFromBlockList { key } => {
+            remove_from_block_list(program_id, accounts, key)
+        }
+        IntroInstruction::GetStats => get_stats(program_id, accounts),
+        IntroInstruction::AddRepliesBatch { replies } => {
+            add_replies_batch(program_id, accounts, replies)
+        }
+        IntroInstruction::SoftDeleteReply { deleted } => {
+            soft_delete_reply(program_id, accounts, deleted)
+        }
+        IntroInstruction::WithdrawTreasury {
+            lamports,
+            destination,
+        } => withdraw_treasury(program_id, accounts, lamports, destination),
+        IntroInstruction::BurnTokens { amount } => burn_tokens(program_id, accounts, amount),
+        IntroInstruction::SetFreezeAuthority { new_authority } => {
+            set_freeze_authority(program_id, accounts, new_authority)
+        }
+        IntroInstruction::InitOrUpdate {
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        } => init_or_update(
+            program_id,
+            accounts,
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        ),
+        IntroInstruction::CloseOrphanReply { index } => {
+            close_orphan_reply(program_id, accounts, index)
+        }
+        IntroInstruction::SetConfig {
+            max_name_len,
+            max_message_len,
+            reply_reward,
+            intro_reward,
+            min_reply_interval,
+        } => set_config(
+            program_id,
+            accounts,
+            max_name_len,
+            max_message_len,
+            reply_reward,
+            intro_reward,
+            min_reply_interval,
+        ),
+        IntroInstruction::SetPaused { paused } => set_paused(program_id, accounts, paused),
+        IntroInstruction::AppendMessageChunk { text } => {
+            append_message_chunk(program_id, accounts, text)
+        }
+        IntroInstruction::AddDeniedWord { term } => add_denied_word(program_id, accounts, term),
+        IntroInstruction::RemoveDeniedWord { term } => {
+            remove_denied_word(program_id, accounts, term)
+        }
+        IntroInstruction::ListReplies { start, limit } => {
+            list_replies(program_id, accounts, start, limit)
         }
-        IntroInstruction::AddReply { reply } => add_reply(program_id, accounts, reply),
-        IntroInstruction::InitializeMint => initialize_token_mint(program_id, accounts),
     }
 }
 
@@ -46,10 +202,12 @@ pub fn add_student_intro(
     accounts: &[AccountInfo],
     name: String,
     message: String,
+    reward_amount: Option<u64>,
+    avatar_uri: Option<String>,
 ) -> ProgramResult {
     msg!("Adding student intro...");
-    msg!("Name: {}", name);
-    msg!("Message: {}", message);
+    debug_msg!("Name: {}", name);
+    debug_msg!("Message: {}", message);
     let account_info_iter = &mut accounts.iter();
 
     let initializer = next_account_info(account_info_iter)?;
@@ -60,160 +218,325 @@ pub fn add_student_intro(
     let user_ata = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
     let token_program = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+    let name_index = next_account_info(account_info_iter)?;
+    let treasury = next_account_info(account_info_iter)?;
+    let reward_marker = next_account_info(account_info_iter)?;
 
-    if !initializer.is_signer {
-        msg!("Missing required signature");
-        return Err(ProgramError::MissingRequiredSignature);
-    }
+    // Every account is validated before the first CPI, so a rejected call never leaves
+    // the intro half created
+    require_signer(initializer)?;
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
 
-    let (user_pda, bump_seed) =
-        Pubkey::find_program_address(&[initializer.key.as_ref()], program_id);
-    if user_pda != *user_account.key {
-        msg!("Invalid seeds for PDA");
-        return Err(StudentIntroError::InvalidPDA.into());
-    }
+    check_not_blocked(program_id, block_list, initializer.key)?;
+
+    require_key_eq(treasury, &treasury_pda(program_id).0, "treasury account")?;
+
+    let (user_pda, bump_seed) = user_intro_pda(initializer.key, program_id);
+    require_pda(user_account, &user_pda)?;
+
+    let (counter, counter_bump) = reply_counter_pda(&user_pda, program_id);
+    require_pda(reply_counter, &counter)?;
+
+    let (marker, marker_bump) = intro_reward_marker_pda(initializer.key, program_id);
+    require_pda(reward_marker, &marker)?;
 
-    msg!("Deriving mint and mint authority");
-    let (expected_mint_pda, _mint_bump) =
-        Pubkey::find_program_address(&[b"token_mint"], program_id);
-    let (expected_auth_pda, auth_bump) = Pubkey::find_program_address(&[b"token_auth"], program_id);
+    debug_msg!("Deriving mint and mint authority");
+    let (expected_mint_pda, _mint_bump) = crate::pda::token_mint_pda(program_id);
+    let (expected_auth_pda, auth_bump) = crate::pda::mint_auth_pda(program_id);
 
-    if *token_mint_pda.key != expected_mint_pda {
-        msg!("Incorrect token mint");
+    require_key_eq(token_mint_pda, &expected_mint_pda, "token mint")?;
+    require_key_eq(mint_auth_pda, &expected_auth_pda, "token auth")?;
+
+    if *token_mint_pda.owner != TOKEN_PROGRAM_ID {
+        msg!("Token mint is not owned by the token program");
         return Err(StudentIntroError::IncorrectAccountError.into());
     }
 
-    if *mint_auth_pda.key != expected_auth_pda {
-        msg!("Incorrect token auth");
+    let mint = Mint::unpack(&token_mint_pda.data.borrow())?;
+    if mint.mint_authority != COption::Some(expected_auth_pda) {
+        msg!("Token mint authority is not the mint auth PDA");
         return Err(StudentIntroError::IncorrectAccountError.into());
     }
 
-    if *user_ata.key != get_associated_token_address(initializer.key, token_mint_pda.key) {
-        msg!("Incorrect token mint");
+    require_key_eq(
+        user_ata,
+        &get_associated_token_address(initializer.key, token_mint_pda.key),
+        "associated token account",
+    )?;
+
+    // mint_to fails opaquely on an ATA that was never created, so say why up front
+    if user_ata.data_is_empty() || *user_ata.owner != TOKEN_PROGRAM_ID {
+        msg!("User associated token account does not exist");
         return Err(StudentIntroError::IncorrectAccountError.into());
     }
 
-    if *token_program.key != TOKEN_PROGRAM_ID {
-        msg!("Incorrect token program");
-        return Err(StudentIntroError::IncorrectAccountError.into());
+    let config = load_config(program_id, accounts)?;
+    validate_intro_input(&name, &message, &config)?;
+    check_denied_words(program_id, accounts, &[&name, &message])?;
+    let avatar_uri = avatar_uri.unwrap_or_default();
+    validate_avatar_uri(&avatar_uri)?;
+
+    // The amount comes from the caller, so only the admin may raise it above the config
+    let reward_amount = match reward_amount {
+        Some(amount) if amount > config.intro_reward && !admin_signed(accounts) => {
+            msg!(
+                "Only the admin can set a reward above {}",
+                config.intro_reward
+            );
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        Some(amount) => amount,
+        None => config.intro_reward,
+    };
+    if reward_amount > MAX_INTRO_REWARD {
+        msg!("Reward amount exceeds {}", MAX_INTRO_REWARD);
+        return Err(StudentIntroError::InvalidInput.into());
     }
 
-    let studentinfo_discriminator = "studentinfo";
-    let account_len: usize = 1000;
-    let total_len: usize =
-        (4 + studentinfo_discriminator.len()) + 1 + (4 + name.len()) + (4 + message.len());
-    if total_len > account_len {
-        msg!("Data length is larger than 1000 bytes");
+    let account_len = StudentInfo::get_account_size(&name, &message, &avatar_uri)?;
+    if account_len > MAX_INTRO_ACCOUNT_LEN {
+        msg!("Data length is larger than {} bytes", MAX_INTRO_ACCOUNT_LEN);
         return Err(StudentIntroError::InvalidDataLength.into());
     }
 
     let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
 
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,
-            user_account.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
-        &[
-            initializer.clone(),
-            user_account.clone(),
-            system_program.clone(),
-        ],
-        &[&[initializer.key.as_ref(), &[bump_seed]]],
+    // The transaction fee is taken before the program runs, so only the creation fee and
+    // rent for the new accounts remain to be covered
+    let mut new_accounts = vec![account_len, NameIndex::SIZE];
+    if reply_counter.data_is_empty() {
+        new_accounts.push(ReplyCounter::SIZE);
+    }
+    if reward_marker.data_is_empty() {
+        new_accounts.push(RewardMarker::SIZE);
+    }
+    let required = new_accounts
+        .iter()
+        .try_fold(INTRO_CREATION_FEE, |total, len| {
+            total.checked_add(rent.minimum_balance(*len))
+        })
+        .ok_or(StudentIntroError::Overflow)?;
+    if initializer.lamports() < required {
+        msg!(
+            "Initializer needs {} lamports to create an intro, has {}",
+            required,
+            initializer.lamports()
+        );
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    if INTRO_CREATION_FEE > 0 {
+        debug_msg!("Paying creation fee of {} lamports", INTRO_CREATION_FEE);
+        invoke(
+            &system_instruction::transfer(initializer.key, treasury.key, INTRO_CREATION_FEE),
+            &[
+                initializer.clone(),
+                treasury.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+
+    create_name_index(
+        program_id,
+        initializer,
+        name_index,
+        system_program,
+        &rent,
+        &name,
+        &user_pda,
+    )?;
+
+    create_pda_account(
+        initializer,
+        user_account,
+        system_program,
+        program_id,
+        account_len,
+        &rent,
+        &[initializer.key.as_ref(), &[bump_seed]],
     )?;
 
     msg!("PDA created: {}", user_pda);
 
-    msg!("unpacking state account");
-    let mut account_data =
-        try_from_slice_unchecked::<StudentInfo>(&user_account.data.borrow()).unwrap();
-    msg!("borrowed account data");
+    debug_msg!("unpacking state account");
+    let mut account_data = load_account::<StudentInfo>(user_account)?;
+    debug_msg!("borrowed account data");
 
-    msg!("checking if studentinfo account is already initialized");
+    debug_msg!("checking if studentinfo account is already initialized");
     if account_data.is_initialized() {
         msg!("Account already initialized");
         return Err(ProgramError::AccountAlreadyInitialized);
     }
 
-    account_data.discriminator = studentinfo_discriminator.to_string();
+    account_data.magic = ACCOUNT_MAGIC;
+    account_data.version = CURRENT_VERSION;
+    account_data.discriminator = AccountType::StudentInfo;
+    account_data.author = *initializer.key;
     account_data.name = name;
     account_data.msg = message;
+    account_data.avatar_uri = avatar_uri;
+    let now = Clock::get()?.unix_timestamp;
+    account_data.created_at = now;
+    account_data.updated_at = now;
     account_data.is_initialized = true;
-    msg!("serializing account");
+    debug_msg!("serializing account");
     account_data.serialize(&mut &mut user_account.data.borrow_mut()[..])?;
-    msg!("state account serialized");
+    debug_msg!("state account serialized");
 
-    msg!("create reply counter");
-    let counter_discriminator = "counter";
-    let counter_len: usize = (4 + counter_discriminator.len()) + 1 + 1;
+    if reply_counter.data_is_empty() {
+        debug_msg!("create reply counter");
+        create_pda_account(
+            initializer,
+            reply_counter,
+            system_program,
+            program_id,
+            ReplyCounter::SIZE,
+            &rent,
+            &[user_pda.as_ref(), REPLY_COUNTER_SEED, &[counter_bump]],
+        )?;
+        debug_msg!("reply counter created");
+    } else {
+        require_owned_by(reply_counter, program_id)?;
+    }
 
-    let rent = Rent::get()?;
-    let counter_rent_lamports = rent.minimum_balance(counter_len);
+    let mut counter_data = load_account::<ReplyCounter>(reply_counter)?;
+    counter_data.magic = ACCOUNT_MAGIC;
+    counter_data.version = CURRENT_VERSION;
+    counter_data.discriminator = AccountType::ReplyCounter;
+    // A counter left behind by a transferred intro carries on from its count, so the replies
+    // of the old intro keep their indices and the new one never reuses them
+    counter_data.first_index = counter_data.counter;
+    counter_data.last_reply_at = 0;
+    counter_data.is_initialized = true;
+    debug_msg!("first reply index: {}", counter_data.first_index);
+    counter_data.serialize(&mut &mut reply_counter.data.borrow_mut()[..])?;
 
-    let (counter, counter_bump) =
-        Pubkey::find_program_address(&[user_pda.as_ref(), "reply".as_ref()], program_id);
-    if counter != *reply_counter.key {
-        msg!("Invalid seeds for PDA");
-        return Err(ProgramError::InvalidArgument);
+    let claimed = claim_reward(
+        program_id,
+        initializer,
+        reward_marker,
+        system_program,
+        &rent,
+        &[INTRO_REWARD_SEED, initializer.key.as_ref(), &[marker_bump]],
+    )?;
+    if claimed {
+        let reward_amount = scale_to_decimals(reward_amount, mint.decimals);
+        msg!("Minting {} to User associated token account", reward_amount);
+        invoke_signed(
+            // Instruction
+            &mint_to(
+                token_program.key,
+                token_mint_pda.key,
+                user_ata.key,
+                mint_auth_pda.key,
+                &[],
+                reward_amount,
+            )?, // ? unwraps and returns the error if there is one
+            // Account_infos
+            &[
+                token_mint_pda.clone(),
+                user_ata.clone(),
+                mint_auth_pda.clone(),
+            ],
+            // Seeds
+            &[&[MINT_AUTH_SEED, &[auth_bump]]],
+        )
+        .map_err(mint_failed)?;
+    } else {
+        msg!("Intro reward already claimed");
     }
 
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,
-            reply_counter.key,
-            counter_rent_lamports,
-            counter_len.try_into().unwrap(),
-            program_id,
-        ),
-        &[
-            initializer.clone(),
-            reply_counter.clone(),
-            system_program.clone(),
-        ],
-        &[&[user_pda.as_ref(), "reply".as_ref(), &[counter_bump]]],
-    )?;
-    msg!("reply counter created");
+    IntroEvent::StudentIntroCreated(StudentIntroCreated {
+        author: *initializer.key,
+        intro: *user_account.key,
+        name: account_data.name,
+        created_at: account_data.created_at,
+    })
+    .emit();
 
-    let mut counter_data =
-        try_from_slice_unchecked::<ReplyCounter>(&reply_counter.data.borrow()).unwrap();
+    let created = CreatedAccounts {
+        version: CREATED_ACCOUNTS_VERSION,
+        addresses: vec![*user_account.key, *reply_counter.key, *name_index.key],
+    };
+    set_return_data(&created.try_to_vec()?);
 
-    msg!("checking if counter account is already initialized");
-    if counter_data.is_initialized() {
-        msg!("Account already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
+    Ok(())
+}
+
+/// Lets clients retry intro creation safely. An existing intro is updated instead, and an
+/// identical retry is a no-op, so rewards are only ever minted by the create path.
+pub fn init_or_update(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    name: String,
+    message: String,
+    reward_amount: Option<u64>,
+    avatar_uri: Option<String>,
+) -> ProgramResult {
+    msg!("Creating or updating student intro...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let initializer = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let _reply_counter = next_account_info(account_info_iter)?;
+    let _token_mint_pda = next_account_info(account_info_iter)?;
+    let _mint_auth_pda = next_account_info(account_info_iter)?;
+    let _user_ata = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let _token_program = next_account_info(account_info_iter)?;
+    let _block_list = next_account_info(account_info_iter)?;
+    let name_index = next_account_info(account_info_iter)?;
+    let _treasury = next_account_info(account_info_iter)?;
+    let _reward_marker = next_account_info(account_info_iter)?;
+    let old_name_index = next_account_info(account_info_iter)?;
+
+    if user_account.data_is_empty() {
+        return add_student_intro(
+            program_id,
+            accounts,
+            name,
+            message,
+            reward_amount,
+            avatar_uri,
+        );
     }
-    counter_data.discriminator = counter_discriminator.to_string();
-    counter_data.counter = 0;
-    counter_data.is_initialized = true;
-    msg!("reply count: {}", counter_data.counter);
-    counter_data.serialize(&mut &mut reply_counter.data.borrow_mut()[..])?;
 
-    msg!("Minting 10 tokens to User associated token account");
-    invoke_signed(
-        // Instruction
-        &mint_to(
-            token_program.key,
-            token_mint_pda.key,
-            user_ata.key,
-            mint_auth_pda.key,
-            &[],
-            10 * LAMPORTS_PER_SOL,
-        )?, // ? unwraps and returns the error if there is one
-        // Account_infos
-        &[
-            token_mint_pda.clone(),
-            user_ata.clone(),
-            mint_auth_pda.clone(),
-        ],
-        // Seeds
-        &[&[b"token_auth", &[auth_bump]]],
-    )?;
+    require_owned_by(user_account, program_id)?;
 
-    Ok(())
+    let account_data = load_account::<StudentInfo>(user_account)?;
+    if account_data.is_initialized()
+        && account_data.name == name
+        && account_data.msg == message
+        && avatar_uri
+            .as_ref()
+            .is_none_or(|uri| *uri == account_data.avatar_uri)
+    {
+        msg!("Intro is already up to date");
+        return Ok(());
+    }
+
+    let mut update_accounts = vec![
+        initializer.clone(),
+        user_account.clone(),
+        system_program.clone(),
+        old_name_index.clone(),
+        name_index.clone(),
+    ];
+    // Keeps the config account in reach of update_intro
+    update_accounts.extend(account_info_iter.cloned());
+    // A retried create lands here right after the first one, so the cooldown is skipped
+    update_intro(
+        program_id,
+        &update_accounts,
+        name,
+        message,
+        avatar_uri,
+        false,
+    )
 }
 
 pub fn update_student_intro(
@@ -221,207 +544,3012 @@ pub fn update_student_intro(
     accounts: &[AccountInfo],
     name: String,
     message: String,
+    avatar_uri: Option<String>,
+) -> ProgramResult {
+    update_intro(program_id, accounts, name, message, avatar_uri, true)
+}
+
+fn update_intro(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    name: String,
+    message: String,
+    avatar_uri: Option<String>,
+    cooldown: bool,
 ) -> ProgramResult {
     msg!("Updating student intro...");
-    msg!("Name: {}", name);
-    msg!("Message: {}", message);
+    debug_msg!("Name: {}", name);
+    debug_msg!("Message: {}", message);
     let account_info_iter = &mut accounts.iter();
 
     let initializer = next_account_info(account_info_iter)?;
     let user_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let old_name_index = next_account_info(account_info_iter)?;
+    let new_name_index = next_account_info(account_info_iter)?;
 
-    msg!("unpacking state account");
-    let mut account_data =
-        try_from_slice_unchecked::<StudentInfo>(&user_account.data.borrow()).unwrap();
-    msg!("borrowed account data");
+    require_signer(initializer)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
 
-    msg!("checking if movie account is initialized");
+    // Checked before deserializing so a foreign account's data is never read
+    require_owned_by(user_account, program_id)?;
+
+    debug_msg!("unpacking state account");
+    let mut account_data = load_account::<StudentInfo>(user_account)?;
+    debug_msg!("borrowed account data");
+
+    debug_msg!("checking if movie account is initialized");
     if !account_data.is_initialized() {
         msg!("Account is not initialized");
         return Err(StudentIntroError::UninitializedAccount.into());
     }
 
-    if user_account.owner != program_id {
-        return Err(ProgramError::IllegalOwner);
-    }
+    let (pda, _bump_seed) = user_intro_pda(initializer.key, program_id);
+    require_pda(user_account, &pda)?;
 
-    let (pda, _bump_seed) = Pubkey::find_program_address(&[initializer.key.as_ref()], program_id);
-    if pda != *user_account.key {
-        msg!("Invalid seeds for PDA");
-        return Err(StudentIntroError::InvalidPDA.into());
+    let config = load_config(program_id, accounts)?;
+    validate_intro_input(&name, &message, &config)?;
+    check_denied_words(program_id, accounts, &[&name, &message])?;
+    let avatar_uri = avatar_uri.unwrap_or_else(|| account_data.avatar_uri.clone());
+    validate_avatar_uri(&avatar_uri)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    if cooldown && now.saturating_sub(account_data.updated_at) < EDIT_COOLDOWN_SECONDS {
+        msg!(
+            "Intro can be updated again {} seconds after its last update",
+            EDIT_COOLDOWN_SECONDS
+        );
+        return Err(StudentIntroError::CooldownActive.into());
     }
-    let update_len: usize = 1 + (4 + account_data.name.len()) + (4 + message.len());
-    if update_len > 1000 {
-        msg!("Data length is larger than 1000 bytes");
+
+    let update_len = StudentInfo::get_account_size(&name, &message, &avatar_uri)?;
+    if update_len > MAX_INTRO_ACCOUNT_LEN {
+        msg!("Data length is larger than {} bytes", MAX_INTRO_ACCOUNT_LEN);
         return Err(StudentIntroError::InvalidDataLength.into());
     }
 
-    account_data.name = account_data.name;
+    if name_hash(&name) != name_hash(&account_data.name) {
+        let (old_index, _old_index_bump) = name_index_pda(&account_data.name, program_id);
+        require_pda(old_name_index, &old_index)?;
+
+        let rent = Rent::get()?;
+        create_name_index(
+            program_id,
+            initializer,
+            new_name_index,
+            system_program,
+            &rent,
+            &name,
+            user_account.key,
+        )?;
+
+        // Intros migrated from the legacy layout were never indexed
+        if !old_name_index.data_is_empty() {
+            require_owned_by(old_name_index, program_id)?;
+            close_account(old_name_index, initializer)?;
+            msg!("Released old name index");
+        }
+    }
+
+    resize_account(user_account, initializer, system_program, update_len)?;
+
+    account_data.name = name;
     account_data.msg = message;
-    msg!("serializing account");
+    account_data.avatar_uri = avatar_uri;
+    account_data.updated_at = now;
+    debug_msg!("serializing account");
     account_data.serialize(&mut &mut user_account.data.borrow_mut()[..])?;
-    msg!("state account serialized");
+    debug_msg!("state account serialized");
+
+    IntroEvent::StudentIntroUpdated(StudentIntroUpdated {
+        author: *initializer.key,
+        intro: *user_account.key,
+        updated_at: account_data.updated_at,
+    })
+    .emit();
 
     Ok(())
 }
 
-pub fn add_reply(program_id: &Pubkey, accounts: &[AccountInfo], reply: String) -> ProgramResult {
-    msg!("Adding Reply...");
-    msg!("Reply: {}", reply);
+pub fn append_message_chunk(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    text: String,
+) -> ProgramResult {
+    msg!("Appending message chunk...");
+    debug_msg!("Text: {}", text);
 
     let account_info_iter = &mut accounts.iter();
 
-    let replier = next_account_info(account_info_iter)?;
+    let initializer = next_account_info(account_info_iter)?;
     let user_account = next_account_info(account_info_iter)?;
-    let reply_counter = next_account_info(account_info_iter)?;
-    let reply_account = next_account_info(account_info_iter)?;
+    let chunk_account = next_account_info(account_info_iter)?;
     let system_program = next_account_info(account_info_iter)?;
 
-    let mut counter_data =
-        try_from_slice_unchecked::<ReplyCounter>(&reply_counter.data.borrow()).unwrap();
+    require_signer(initializer)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_owned_by(user_account, program_id)?;
 
-    let reply_discriminator = "reply";
-    let account_len: usize = (4 + reply_discriminator.len()) + 1 + 32 + (4 + reply.len());
+    let (pda, _bump_seed) = user_intro_pda(initializer.key, program_id);
+    require_pda(user_account, &pda)?;
 
-    let rent = Rent::get()?;
-    let rent_lamports = rent.minimum_balance(account_len);
+    let mut account_data = load_account::<StudentInfo>(user_account)?;
 
-    let (pda, bump_seed) = Pubkey::find_program_address(
-        &[
-            user_account.key.as_ref(),
-            counter_data.counter.to_be_bytes().as_ref(),
-        ],
-        program_id,
-    );
-    if pda != *reply_account.key {
-        msg!("Invalid seeds for PDA");
-        return Err(StudentIntroError::InvalidPDA.into());
+    debug_msg!("checking if studentinfo account is initialized");
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
     }
 
-    invoke_signed(
-        &system_instruction::create_account(
-            replier.key,
-            reply_account.key,
-            rent_lamports,
-            account_len.try_into().unwrap(),
-            program_id,
-        ),
-        &[
-            replier.clone(),
-            reply_account.clone(),
-            system_program.clone(),
-        ],
-        &[&[
-            user_account.key.as_ref(),
-            counter_data.counter.to_be_bytes().as_ref(),
-            &[bump_seed],
-        ]],
+    let config = load_config(program_id, accounts)?;
+    if text.trim().is_empty() {
+        msg!("Message chunk must not be empty");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    if text.chars().count() > config.max_message_len as usize {
+        msg!(
+            "Message chunk is longer than {} characters",
+            config.max_message_len
+        );
+        return Err(StudentIntroError::InvalidDataLength.into());
+    }
+    check_denied_words(program_id, accounts, &[&text])?;
+
+    let index = account_data.message_chunks;
+    if index >= MAX_MESSAGE_CHUNKS {
+        msg!(
+            "Intro can have at most {} message chunks",
+            MAX_MESSAGE_CHUNKS
+        );
+        return Err(StudentIntroError::InvalidDataLength.into());
+    }
+
+    let (chunk, chunk_bump) = message_chunk_pda(user_account.key, index, program_id);
+    require_pda(chunk_account, &chunk)?;
+
+    let chunk_len = MessageChunk::get_account_size(&text)?;
+    let rent = Rent::get()?;
+    create_pda_account(
+        initializer,
+        chunk_account,
+        system_program,
+        program_id,
+        chunk_len,
+        &rent,
+        &[user_account.key.as_ref(), CHUNK_SEED, &[index, chunk_bump]],
     )?;
+    msg!("Created message chunk {}", index);
 
-    msg!("Created Reply Account");
-    let mut reply_data = try_from_slice_unchecked::<Reply>(&reply_account.data.borrow()).unwrap();
+    let chunk_data = MessageChunk {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::MessageChunk,
+        is_initialized: true,
+        intro: *user_account.key,
+        index,
+        text,
+    };
+    chunk_data.serialize(&mut &mut chunk_account.data.borrow_mut()[..])?;
+
+    account_data.message_chunks = index + 1;
+    account_data.serialize(&mut &mut user_account.data.borrow_mut()[..])?;
 
-    msg!("checking if comment account is already initialized");
-    if reply_data.is_initialized() {
-        msg!("Account already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
-    }
-    reply_data.discriminator = reply_discriminator.to_string();
-    reply_data.studentinfo = *user_account.key;
-    reply_data.reply = reply;
-    reply_data.is_initialized = true;
-    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
-    msg!("Reply Count: {}", counter_data.counter);
-    counter_data.counter += 1;
-    counter_data.serialize(&mut &mut reply_counter.data.borrow_mut()[..])?;
     Ok(())
 }
 
-pub fn initialize_token_mint(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Closes the intro, its reply counter, its name index, its recent reply index and its
+/// message chunks, which follow the fixed accounts in index order, and refunds their rent
+/// to the author. A recreated intro can then write its chunks from index 0 again. An
+/// intro that ever handed out a reply index is refused, deleted replies included, so a
+/// recreated intro starting a fresh counter never reuses the index of a reply.
+pub fn close_student_intro(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Closing student intro...");
+
     let account_info_iter = &mut accounts.iter();
 
-    // The order of accounts is not arbitrary, the client will send them in this order
-    // Whoever sent in the transaction
     let initializer = next_account_info(account_info_iter)?;
-    // Token mint PDA - derived on the client
-    let token_mint_pda = next_account_info(account_info_iter)?;
-    // Token mint authorirty (this should be you)
-    let mint_auth_pda = next_account_info(account_info_iter)?;
-    // System program to create a new account
-    let system_program = next_account_info(account_info_iter)?;
-    // Solana Token program address
-    let token_program = next_account_info(account_info_iter)?;
-    // System account to calcuate the rent
-    let sysvar_rent = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+    let name_index = next_account_info(account_info_iter)?;
+    let reply_index = next_account_info(account_info_iter)?;
 
-    let (expected_token_mint_pda, mint_bump) =
-        Pubkey::find_program_address(&[b"token_mint"], program_id);
+    require_signer(initializer)?;
 
-    let (expected_mint_auth_pda, _auth_bump) =
-        Pubkey::find_program_address(&[b"token_auth"], program_id);
+    let (pda, _bump_seed) = user_intro_pda(initializer.key, program_id);
+    require_pda(user_account, &pda)?;
 
-    msg!("Token mint: {:?}", expected_token_mint_pda);
-    msg!("Mint authority: {:?}", expected_mint_auth_pda);
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+    require_owned_by(user_account, program_id)?;
+    require_owned_by(reply_counter, program_id)?;
 
-    if expected_token_mint_pda != *token_mint_pda.key {
-        msg!("Incorrect token mint account");
-        return Err(StudentIntroError::IncorrectAccountError.into());
+    let account_data = load_account::<StudentInfo>(user_account)?;
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
     }
 
-    if *token_program.key != TOKEN_PROGRAM_ID {
-        msg!("Incorrect token program");
-        return Err(StudentIntroError::IncorrectAccountError.into());
+    let (index, _index_bump) = name_index_pda(&account_data.name, program_id);
+    require_pda(name_index, &index)?;
+
+    let (recent, _recent_bump) = reply_index_pda(user_account.key, program_id);
+    require_pda(reply_index, &recent)?;
+
+    let counter_data = load_account::<ReplyCounter>(reply_counter)?;
+    if !counter_data.indices().is_empty() {
+        msg!(
+            "Intro has {} replies and cannot be closed",
+            counter_data.reply_count()
+        );
+        return Err(StudentIntroError::RepliesRemaining.into());
     }
 
-    if *mint_auth_pda.key != expected_mint_auth_pda {
-        msg!("Incorrect mint auth account");
-        return Err(StudentIntroError::IncorrectAccountError.into());
+    let mut closing = vec![user_account];
+    // A counter taken over from a transferred intro still tells that intro's orphaned
+    // replies apart by their indices, so it stays open for them
+    if counter_data.first_index == 0 {
+        closing.push(reply_counter);
+    }
+    // Intros migrated from the legacy layout were never indexed
+    if !name_index.data_is_empty() {
+        require_owned_by(name_index, program_id)?;
+        closing.push(name_index);
+    }
+    // Created with the first reply
+    if !reply_index.data_is_empty() {
+        require_owned_by(reply_index, program_id)?;
+        closing.push(reply_index);
+    }
+    for chunk_index in 0..account_data.message_chunks {
+        let chunk_account = next_account_info(account_info_iter)?;
+        let (chunk, _chunk_bump) = message_chunk_pda(user_account.key, chunk_index, program_id);
+        require_pda(chunk_account, &chunk)?;
+        require_owned_by(chunk_account, program_id)?;
+        closing.push(chunk_account);
     }
 
-    if *system_program.key != SYSTEM_PROGRAM_ID {
-        msg!("Incorrect system program");
-        return Err(StudentIntroError::IncorrectAccountError.into());
+    for account in closing {
+        close_account(account, initializer)?;
     }
+    msg!(
+        "Closed intro, reply counter, name index, reply index and {} message chunks",
+        account_data.message_chunks
+    );
+
+    Ok(())
+}
+
+/// Moves an intro to the PDA derived from `new_owner` and closes the old one along with
+/// its recent reply index. Message chunks are moved too: each old chunk and the new
+/// chunk PDA with the same index follow the fixed accounts as a pair. Existing replies
+/// are seeded by the old intro address, so they are left behind as orphans for
+/// `CloseOrphanReply` and the moved intro starts a fresh reply count. The old counter
+/// stays open so an intro later created at the old address carries on from its count.
+pub fn transfer_intro_ownership(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_owner: Pubkey,
+) -> ProgramResult {
+    msg!("Transferring student intro...");
+    debug_msg!("New owner: {}", new_owner);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let owner = next_account_info(account_info_iter)?;
+    let new_owner_account = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+    let new_user_account = next_account_info(account_info_iter)?;
+    let new_reply_counter = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let name_index = next_account_info(account_info_iter)?;
+    let reply_index = next_account_info(account_info_iter)?;
 
-    if *sysvar_rent.key != RENT_PROGRAM_ID {
-        msg!("Incorrect rent program");
+    require_signer(owner)?;
+    require_signer(new_owner_account)?;
+
+    if *new_owner_account.key != new_owner {
+        msg!("New owner account does not match instruction data");
         return Err(StudentIntroError::IncorrectAccountError.into());
     }
 
-    // Calculate the rent
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let (pda, _bump_seed) = user_intro_pda(owner.key, program_id);
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(user_account, &pda)?;
+    require_pda(reply_counter, &counter)?;
+
+    let (new_pda, new_bump_seed) = user_intro_pda(&new_owner, program_id);
+    let (new_counter, new_counter_bump) = reply_counter_pda(&new_pda, program_id);
+    require_pda(new_user_account, &new_pda)?;
+    require_pda(new_reply_counter, &new_counter)?;
+    require_owned_by(user_account, program_id)?;
+    require_owned_by(reply_counter, program_id)?;
+
+    let account_data = load_account::<StudentInfo>(user_account)?;
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+    load_account::<ReplyCounter>(reply_counter)?;
+
+    if !new_user_account.data_is_empty() {
+        msg!("New owner already has an intro");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (index, _index_bump) = name_index_pda(&account_data.name, program_id);
+    require_pda(name_index, &index)?;
+
+    let (recent, _recent_bump) = reply_index_pda(user_account.key, program_id);
+    require_pda(reply_index, &recent)?;
+
     let rent = Rent::get()?;
-    // We know the size of a mint account is 82 (remember it lol)
-    let rent_lamports = rent.minimum_balance(82);
 
-    // Create the token mint PDA
-    invoke_signed(
-        &system_instruction::create_account(
-            initializer.key,
-            token_mint_pda.key,
-            rent_lamports,
-            82, // Size of the token mint account
-            token_program.key,
-        ),
-        // Accounts we're reading from or writing to
-        &[
-            initializer.clone(),
-            token_mint_pda.clone(),
-            system_program.clone(),
-        ],
-        // Seeds for our token mint account
-        &[&[b"token_mint", &[mint_bump]]],
+    create_pda_account(
+        owner,
+        new_user_account,
+        system_program,
+        program_id,
+        user_account.data_len(),
+        &rent,
+        &[new_owner.as_ref(), &[new_bump_seed]],
     )?;
+    let message_chunks = account_data.message_chunks;
+    let mut new_account_data = account_data;
+    new_account_data.author = new_owner;
+    new_account_data.serialize(&mut &mut new_user_account.data.borrow_mut()[..])?;
+    msg!("PDA created: {}", new_pda);
 
-    msg!("Created token mint account");
+    // Chunks are seeded by the intro address, so each one is rewritten under the new PDA
+    for chunk_index in 0..message_chunks {
+        let old_chunk_account = next_account_info(account_info_iter)?;
+        let new_chunk_account = next_account_info(account_info_iter)?;
+        let (old_chunk, _old_chunk_bump) =
+            message_chunk_pda(user_account.key, chunk_index, program_id);
+        let (new_chunk, new_chunk_bump) = message_chunk_pda(&new_pda, chunk_index, program_id);
+        require_pda(old_chunk_account, &old_chunk)?;
+        require_pda(new_chunk_account, &new_chunk)?;
+        require_owned_by(old_chunk_account, program_id)?;
 
-    // Initialize the mint account
-    invoke_signed(
+        let mut chunk_data = load_account::<MessageChunk>(old_chunk_account)?;
+        create_pda_account(
+            owner,
+            new_chunk_account,
+            system_program,
+            program_id,
+            old_chunk_account.data_len(),
+            &rent,
+            &[new_pda.as_ref(), CHUNK_SEED, &[chunk_index, new_chunk_bump]],
+        )?;
+        chunk_data.intro = new_pda;
+        chunk_data.serialize(&mut &mut new_chunk_account.data.borrow_mut()[..])?;
+        close_account(old_chunk_account, owner)?;
+    }
+    debug_msg!("Moved {} message chunks", message_chunks);
+
+    if new_reply_counter.data_is_empty() {
+        create_pda_account(
+            owner,
+            new_reply_counter,
+            system_program,
+            program_id,
+            ReplyCounter::SIZE,
+            &rent,
+            &[new_pda.as_ref(), REPLY_COUNTER_SEED, &[new_counter_bump]],
+        )?;
+        msg!("reply counter created");
+    } else {
+        // Left behind by an intro the new owner closed
+        require_owned_by(new_reply_counter, program_id)?;
+    }
+    let mut counter_data = load_account::<ReplyCounter>(new_reply_counter)?;
+    counter_data.magic = ACCOUNT_MAGIC;
+    counter_data.version = CURRENT_VERSION;
+    counter_data.discriminator = AccountType::ReplyCounter;
+    counter_data.is_initialized = true;
+    counter_data.first_index = counter_data.counter;
+    counter_data.last_reply_at = 0;
+    counter_data.serialize(&mut &mut new_reply_counter.data.borrow_mut()[..])?;
+
+    close_account(user_account, owner)?;
+    if !reply_index.data_is_empty() {
+        require_owned_by(reply_index, program_id)?;
+        close_account(reply_index, owner)?;
+    }
+    msg!("Closed old intro and reply index");
+
+    // Intros migrated from the legacy layout were never indexed
+    if !name_index.data_is_empty() {
+        require_owned_by(name_index, program_id)?;
+        let mut index_data = load_account::<NameIndex>(name_index)?;
+        index_data.intro = new_pda;
+        index_data.serialize(&mut &mut name_index.data.borrow_mut()[..])?;
+    }
+
+    IntroEvent::IntroOwnershipTransferred(IntroOwnershipTransferred {
+        old_owner: *owner.key,
+        new_owner,
+        old_intro: *user_account.key,
+        new_intro: new_pda,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// Creates the name index for `name` pointing at `intro`, failing with `NameTaken` if
+/// another intro already holds the name.
+fn create_name_index<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    name_index: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &Rent,
+    name: &str,
+    intro: &Pubkey,
+) -> ProgramResult {
+    let (pda, bump_seed) = name_index_pda(name, program_id);
+    require_pda(name_index, &pda)?;
+
+    if !name_index.data_is_empty() {
+        msg!("Name {} is already taken", name);
+        return Err(StudentIntroError::NameTaken.into());
+    }
+
+    create_pda_account(
+        payer,
+        name_index,
+        system_program,
+        program_id,
+        NameIndex::SIZE,
+        rent,
+        &[NAME_SEED, &name_hash(name), &[bump_seed]],
+    )?;
+
+    NameIndex {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::NameIndex,
+        is_initialized: true,
+        intro: *intro,
+    }
+    .serialize(&mut &mut name_index.data.borrow_mut()[..])?;
+    msg!("Created name index");
+    Ok(())
+}
+
+/// Appends `replies` to the intro's recent reply index, creating the index on the first
+/// reply and dropping the oldest entries once it holds `RECENT_REPLIES`.
+fn record_recent_replies<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    reply_index: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &Rent,
+    intro: &Pubkey,
+    replies: &[Pubkey],
+) -> ProgramResult {
+    let (pda, bump_seed) = reply_index_pda(intro, program_id);
+    require_pda(reply_index, &pda)?;
+
+    if reply_index.data_is_empty() {
+        create_pda_account(
+            payer,
+            reply_index,
+            system_program,
+            program_id,
+            ReplyIndex::SIZE,
+            rent,
+            &[intro.as_ref(), REPLY_INDEX_SEED, &[bump_seed]],
+        )?;
+        debug_msg!("Created reply index");
+    } else {
+        require_owned_by(reply_index, program_id)?;
+    }
+
+    let mut index_data = load_account::<ReplyIndex>(reply_index)?;
+    index_data.magic = ACCOUNT_MAGIC;
+    index_data.version = CURRENT_VERSION;
+    index_data.discriminator = AccountType::ReplyIndex;
+    index_data.is_initialized = true;
+    index_data.recent.extend_from_slice(replies);
+    let overflow = index_data.recent.len().saturating_sub(RECENT_REPLIES);
+    index_data.recent.drain(..overflow);
+    index_data.serialize(&mut &mut reply_index.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Creates the `RewardMarker` guarding a reward and returns `true`, or returns `false`
+/// when the marker already exists because the reward was paid before. The caller checks
+/// the marker's address against `signer_seeds`.
+fn claim_reward<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    marker: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent: &Rent,
+    signer_seeds: &[&[u8]],
+) -> Result<bool, ProgramError> {
+    if !marker.data_is_empty() {
+        require_owned_by(marker, program_id)?;
+        load_account::<RewardMarker>(marker)?;
+        return Ok(false);
+    }
+
+    create_pda_account(
+        payer,
+        marker,
+        system_program,
+        program_id,
+        RewardMarker::SIZE,
+        rent,
+        signer_seeds,
+    )?;
+    RewardMarker {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::RewardMarker,
+        is_initialized: true,
+    }
+    .serialize(&mut &mut marker.data.borrow_mut()[..])?;
+    debug_msg!("Created reward marker");
+    Ok(true)
+}
+
+/// Burns `amount` reward tokens from the replier's token account, checking the balance
+/// first so a short balance fails with a clear error instead of inside the CPI.
+fn burn_reply_fee<'a>(
+    token_program: &AccountInfo<'a>,
+    replier_ata: &AccountInfo<'a>,
+    token_mint: &AccountInfo<'a>,
+    replier: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    if *replier_ata.owner != TOKEN_PROGRAM_ID {
+        msg!("Replier associated token account does not exist");
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+    let balance = TokenAccount::unpack(&replier_ata.data.borrow())?.amount;
+    if balance < amount {
+        msg!("Replying burns {} tokens, balance is {}", amount, balance);
+        return Err(StudentIntroError::InsufficientTokens.into());
+    }
+
+    invoke(
+        &burn(
+            token_program.key,
+            replier_ata.key,
+            token_mint.key,
+            replier.key,
+            &[],
+            amount,
+        )?,
+        &[replier_ata.clone(), token_mint.clone(), replier.clone()],
+    )
+}
+
+/// Keeps the token program's error in the log but reports it as ours, so a failed
+/// reward mint is recognisable without decoding token program errors.
+fn mint_failed(error: ProgramError) -> ProgramError {
+    msg!("Token mint CPI failed: {}", error);
+    StudentIntroError::TokenMintFailed.into()
+}
+
+/// Rejects `key` if it is on the block list. The block list PDA must always be passed,
+/// but before the admin first creates it nobody is blocked.
+fn check_not_blocked(program_id: &Pubkey, block_list: &AccountInfo, key: &Pubkey) -> ProgramResult {
+    let (pda, _bump_seed) = block_list_pda(program_id);
+    require_pda(block_list, &pda)?;
+
+    if block_list.data_is_empty() {
+        return Ok(());
+    }
+
+    require_owned_by(block_list, program_id)?;
+
+    let block_list_data = load_account::<BlockList>(block_list)?;
+    if block_list_data.blocked.contains(key) {
+        msg!("{} is blocked", key);
+        return Err(StudentIntroError::Blocked.into());
+    }
+    Ok(())
+}
+
+/// Deserializes a program account and rejects it when its tag belongs to another
+/// account type or it was written with another layout version. Freshly created
+/// accounts are still `Uninitialized` and pass through.
+fn load_account<T: BorshDeserialize + TypedAccount>(
+    account: &AccountInfo,
+) -> Result<T, ProgramError> {
+    let outdated = {
+        let data = account.data.borrow();
+        legacy_account_type(&data).is_some() || v1_account_type(&data).is_some()
+    };
+    if outdated {
+        msg!("Account uses an older layout and must be migrated");
+        return Err(StudentIntroError::UnsupportedAccountVersion.into());
+    }
+    check_account_type(account, T::ACCOUNT_TYPE)?;
+    let data = try_from_slice_unchecked::<T>(&account.data.borrow())
+        .map_err(|_| StudentIntroError::DeserializationFailed)?;
+    if data.account_type() == AccountType::Uninitialized {
+        return Ok(data);
+    }
+    if data.version() != CURRENT_VERSION {
+        msg!("Unsupported account version {}", data.version());
+        return Err(StudentIntroError::UnsupportedAccountVersion.into());
+    }
+    Ok(data)
+}
+
+/// Reads only the account header, so an account passed in the wrong slot is rejected
+/// before its body is deserialized as the wrong layout.
+fn check_account_type(account: &AccountInfo, expected: AccountType) -> ProgramResult {
+    let account_type = match account_header(&account.data.borrow()) {
+        Some((_version, account_type)) => account_type,
+        None => {
+            msg!("Account does not start with the program's account header");
+            return Err(StudentIntroError::IncorrectAccountError.into());
+        }
+    };
+    if account_type != AccountType::Uninitialized && account_type != expected {
+        msg!("Expected {:?} account, found {:?}", expected, account_type);
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+    Ok(())
+}
+
+/// Creates a PDA with `space` bytes owned by `owner`. `create_account` fails if the address
+/// already holds lamports, which anyone can send to a predictable PDA, so a pre-funded
+/// account is topped up to rent exemption and then allocated and assigned instead.
+fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    owner: &Pubkey,
+    space: usize,
+    rent: &Rent,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = rent.minimum_balance(space);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer.key,
+                account.key,
+                required_lamports,
+                space.try_into().unwrap(),
+                owner,
+            ),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[signer_seeds],
+        );
+    }
+
+    debug_msg!("Account is pre-funded, allocating in place");
+    let top_up = required_lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, top_up),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space.try_into().unwrap()),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account.clone(), system_program.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Reallocs a program-owned account to `new_len`, topping its rent up from `payer` when
+/// it grows and refunding the excess to `payer` when it shrinks. Growth fails with
+/// `InsufficientFunds` if `payer` cannot cover the shortfall.
+fn resize_account<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    let rent = Rent::get()?;
+    let new_minimum_balance = rent.minimum_balance(new_len);
+    if new_len > account.data_len() {
+        debug_msg!("Growing account to {} bytes", new_len);
+        let lamports_diff = new_minimum_balance.saturating_sub(account.lamports());
+        if lamports_diff > payer.lamports() {
+            msg!(
+                "Payer cannot cover {} lamports of additional rent",
+                lamports_diff
+            );
+            return Err(ProgramError::InsufficientFunds);
+        }
+        if lamports_diff > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, account.key, lamports_diff),
+                &[payer.clone(), account.clone(), system_program.clone()],
+            )?;
+        }
+        account.realloc(new_len, false)?;
+    } else if new_len < account.data_len() {
+        debug_msg!("Shrinking account to {} bytes", new_len);
+        account.realloc(new_len, false)?;
+        // The account is owned by this program, so excess rent can be moved out directly
+        let lamports_diff = account.lamports().saturating_sub(new_minimum_balance);
+        **account.lamports.borrow_mut() -= lamports_diff;
+        **payer.lamports.borrow_mut() += lamports_diff;
+    }
+    Ok(())
+}
+
+/// Closes a program-owned account into `destination`: the data is zeroed and truncated
+/// and the account handed back to the system program, so nothing is left to be revived
+/// by refunding it within the same transaction. Returns the lamports moved.
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<u64, ProgramError> {
+    account.data.borrow_mut().fill(0);
+    account.realloc(0, false)?;
+    let lamports = account.lamports();
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(StudentIntroError::Overflow)?;
+    **account.lamports.borrow_mut() = 0;
+    account.assign(&SYSTEM_PROGRAM_ID);
+    Ok(lamports)
+}
+
+/// Converts an amount written with `REWARD_DECIMALS` decimals into base units of a mint
+/// with `decimals` decimals, rounding down.
+fn scale_to_decimals(amount: u64, decimals: u8) -> u64 {
+    amount / 10u64.pow(u32::from(REWARD_DECIMALS.saturating_sub(decimals)))
+}
+
+/// Whether the admin signed, as the initializer or as an extra account.
+fn admin_signed(accounts: &[AccountInfo]) -> bool {
+    accounts
+        .iter()
+        .any(|account| *account.key == ADMIN_PUBKEY && account.is_signer)
+}
+
+fn find_config<'a, 'b>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'b>],
+) -> Option<&'a AccountInfo<'b>> {
+    let (pda, _bump_seed) = config_pda(program_id);
+    accounts.iter().find(|account| *account.key == pda)
+}
+
+fn check_not_paused(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    if find_config(program_id, accounts).is_none() {
+        msg!("Config account must be passed");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if load_config(program_id, accounts)?.paused {
+        msg!("Program is paused");
+        return Err(StudentIntroError::ProgramPaused.into());
+    }
+    Ok(())
+}
+
+/// The `Config` account is picked out of `accounts` by address, so it can be appended
+/// after any handler's own accounts. Until it is created the constants apply.
+fn load_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<Config, ProgramError> {
+    if let Some(config) = find_config(program_id, accounts) {
+        if !config.data_is_empty() {
+            require_owned_by(config, program_id)?;
+            let config_data = load_account::<Config>(config)?;
+            if config_data.is_initialized() {
+                return Ok(config_data);
+            }
+        }
+    }
+
+    Ok(Config {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::Config,
+        is_initialized: false,
+        max_name_len: MAX_NAME_LEN as u32,
+        max_message_len: MAX_MESSAGE_CHARS as u32,
+        reply_reward: REPLY_REWARD,
+        intro_reward: DEFAULT_INTRO_REWARD,
+        paused: false,
+        min_reply_interval: MIN_REPLY_INTERVAL_SECONDS,
+    })
+}
+
+fn validate_intro_input(name: &str, message: &str, config: &Config) -> ProgramResult {
+    if name.trim().is_empty() || message.trim().is_empty() {
+        msg!("Name and message must not be empty");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    if name.len() > config.max_name_len as usize {
+        msg!("Name is longer than {} bytes", config.max_name_len);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    if message.chars().count() > config.max_message_len as usize {
+        msg!(
+            "Message is longer than {} characters",
+            config.max_message_len
+        );
+        return Err(StudentIntroError::InvalidDataLength.into());
+    }
+    Ok(())
+}
+
+/// Rejects any of `texts` containing a denied term. Like the `Config` account, the
+/// denylist PDA is picked out of `accounts` by address and must always be passed, but
+/// until the admin first creates it nothing is denied.
+fn check_denied_words(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    texts: &[&str],
+) -> ProgramResult {
+    let (pda, _bump_seed) = word_denylist_pda(program_id);
+    let denylist = match accounts.iter().find(|account| *account.key == pda) {
+        Some(denylist) => denylist,
+        None => {
+            msg!("Word denylist account must be passed");
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+    };
+
+    if denylist.data_is_empty() {
+        return Ok(());
+    }
+
+    require_owned_by(denylist, program_id)?;
+
+    let denylist_data = load_account::<WordDenylist>(denylist)?;
+    for text in texts {
+        if let Some(term) = denylist_data.find_term(text) {
+            msg!("Input contains denied term \"{}\"", term);
+            return Err(StudentIntroError::InvalidInput.into());
+        }
+    }
+    Ok(())
+}
+
+fn validate_avatar_uri(avatar_uri: &str) -> ProgramResult {
+    if avatar_uri.len() > MAX_AVATAR_URI_LEN {
+        msg!("Avatar URI is longer than {} bytes", MAX_AVATAR_URI_LEN);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    if avatar_uri.chars().any(char::is_whitespace) {
+        msg!("Avatar URI must not contain whitespace");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    Ok(())
+}
+
+fn check_reply_interval(counter: &ReplyCounter, interval: i64, now: i64) -> ProgramResult {
+    if now.saturating_sub(counter.last_reply_at) < interval {
+        msg!(
+            "Intro can be replied to again {} seconds after its last reply",
+            interval
+        );
+        return Err(StudentIntroError::CooldownActive.into());
+    }
+    Ok(())
+}
+
+fn validate_reply_input(reply: &str) -> ProgramResult {
+    if reply.trim().is_empty() {
+        msg!("Reply must not be empty");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    if reply.chars().count() > MAX_REPLY_CHARS {
+        msg!("Reply is longer than {} characters", MAX_REPLY_CHARS);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+    Ok(())
+}
+
+pub fn add_reply(program_id: &Pubkey, accounts: &[AccountInfo], reply: String) -> ProgramResult {
+    msg!("Adding Reply...");
+    debug_msg!("Reply: {}", reply);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_mint_pda = next_account_info(account_info_iter)?;
+    let mint_auth_pda = next_account_info(account_info_iter)?;
+    let replier_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+    let reply_index = next_account_info(account_info_iter)?;
+    let reward_marker = next_account_info(account_info_iter)?;
+
+    require_signer(replier)?;
+
+    check_not_blocked(program_id, block_list, replier.key)?;
+
+    let (expected_mint_pda, _mint_bump) = crate::pda::token_mint_pda(program_id);
+    let (expected_auth_pda, auth_bump) = crate::pda::mint_auth_pda(program_id);
+
+    require_key_eq(token_mint_pda, &expected_mint_pda, "token mint")?;
+    require_key_eq(mint_auth_pda, &expected_auth_pda, "token auth")?;
+
+    require_key_eq(
+        replier_ata,
+        &get_associated_token_address(replier.key, token_mint_pda.key),
+        "token account",
+    )?;
+
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+    require_owned_by(user_account, program_id)?;
+
+    let intro_data = load_account::<StudentInfo>(user_account)?;
+    if !intro_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+    require_owned_by(reply_counter, program_id)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    validate_reply_input(&reply)?;
+    check_denied_words(program_id, accounts, &[&reply])?;
+    let decimals = Mint::unpack(&token_mint_pda.data.borrow())?.decimals;
+    let config = load_config(program_id, accounts)?;
+    let reply_reward = scale_to_decimals(config.reply_reward, decimals);
+
+    let (replier_intro, _replier_bump) = user_intro_pda(replier.key, program_id);
+    if replier_intro == *user_account.key {
+        msg!("Authors cannot reply to their own intro");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let mut counter_data = load_account::<ReplyCounter>(reply_counter)?;
+    if counter_data.reply_count() >= MAX_REPLIES {
+        msg!("Intro already has {} replies", MAX_REPLIES);
+        return Err(StudentIntroError::ReplyLimitReached.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+    check_reply_interval(&counter_data, config.min_reply_interval, now)?;
+    let next_counter = counter_data
+        .counter
+        .checked_add(1)
+        .ok_or(StudentIntroError::Overflow)?;
+
+    burn_reply_fee(
+        token_program,
+        replier_ata,
+        token_mint_pda,
+        replier,
+        scale_to_decimals(config.reply_reward.max(REPLY_BURN_AMOUNT), decimals),
+    )?;
+
+    let account_len = Reply::get_account_size(&reply, None)?;
+
+    let rent = Rent::get()?;
+
+    let (pda, bump_seed) = reply_pda(user_account.key, counter_data.counter, program_id);
+    require_pda(reply_account, &pda)?;
+    let (marker, marker_bump) = reply_reward_marker_pda(&pda, replier.key, program_id);
+    require_pda(reward_marker, &marker)?;
+
+    create_pda_account(
+        replier,
+        reply_account,
+        system_program,
+        program_id,
+        account_len,
+        &rent,
+        &[
+            user_account.key.as_ref(),
+            counter_data.counter.to_be_bytes().as_ref(),
+            &[bump_seed],
+        ],
+    )?;
+
+    msg!("Created Reply Account");
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if comment account is already initialized");
+    if reply_data.is_initialized() {
+        msg!("Account already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    reply_data.magic = ACCOUNT_MAGIC;
+    reply_data.version = CURRENT_VERSION;
+    reply_data.discriminator = AccountType::Reply;
+    reply_data.studentinfo = *user_account.key;
+    reply_data.replier = *replier.key;
+    reply_data.parent = None;
+    reply_data.reply = reply;
+    reply_data.is_initialized = true;
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+    debug_msg!("Reply Count: {}", counter_data.counter);
+
+    IntroEvent::ReplyAdded(ReplyAdded {
+        intro: *user_account.key,
+        reply: *reply_account.key,
+        replier: *replier.key,
+        parent: None,
+        index: counter_data.counter,
+    })
+    .emit();
+
+    counter_data.counter = next_counter;
+    counter_data.last_reply_at = now;
+    counter_data.serialize(&mut &mut reply_counter.data.borrow_mut()[..])?;
+
+    record_recent_replies(
+        program_id,
+        replier,
+        reply_index,
+        system_program,
+        &rent,
+        user_account.key,
+        &[*reply_account.key],
+    )?;
+
+    let claimed = claim_reward(
+        program_id,
+        replier,
+        reward_marker,
+        system_program,
+        &rent,
+        &[
+            REPLY_REWARD_SEED,
+            pda.as_ref(),
+            replier.key.as_ref(),
+            &[marker_bump],
+        ],
+    )?;
+    if claimed {
+        msg!("Minting reply reward to replier associated token account");
+        invoke_signed(
+            &mint_to(
+                token_program.key,
+                token_mint_pda.key,
+                replier_ata.key,
+                mint_auth_pda.key,
+                &[],
+                reply_reward,
+            )?,
+            &[
+                token_mint_pda.clone(),
+                replier_ata.clone(),
+                mint_auth_pda.clone(),
+            ],
+            &[&[MINT_AUTH_SEED, &[auth_bump]]],
+        )
+        .map_err(mint_failed)?;
+    } else {
+        msg!("Reply reward already claimed");
+    }
+
+    let created = CreatedAccounts {
+        version: CREATED_ACCOUNTS_VERSION,
+        addresses: vec![*reply_account.key],
+    };
+    set_return_data(&created.try_to_vec()?);
+    Ok(())
+}
+
+/// Adds several top-level replies in one instruction. Each reply account and its reward
+/// marker follow the fixed accounts as a pair, in index order starting at the intro's
+/// current reply count.
+pub fn add_replies_batch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    replies: Vec<String>,
+) -> ProgramResult {
+    msg!("Adding Replies...");
+    debug_msg!("Replies: {}", replies.len());
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let token_mint_pda = next_account_info(account_info_iter)?;
+    let mint_auth_pda = next_account_info(account_info_iter)?;
+    let replier_ata = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+    let reply_index = next_account_info(account_info_iter)?;
+
+    require_signer(replier)?;
+
+    check_not_blocked(program_id, block_list, replier.key)?;
+
+    if replies.is_empty() || replies.len() > MAX_BATCH_REPLIES {
+        msg!(
+            "A batch must have between 1 and {} replies",
+            MAX_BATCH_REPLIES
+        );
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let (expected_mint_pda, _mint_bump) = crate::pda::token_mint_pda(program_id);
+    let (expected_auth_pda, auth_bump) = crate::pda::mint_auth_pda(program_id);
+
+    require_key_eq(token_mint_pda, &expected_mint_pda, "token mint")?;
+    require_key_eq(mint_auth_pda, &expected_auth_pda, "token auth")?;
+
+    require_key_eq(
+        replier_ata,
+        &get_associated_token_address(replier.key, token_mint_pda.key),
+        "token account",
+    )?;
+
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+    require_owned_by(user_account, program_id)?;
+
+    let intro_data = load_account::<StudentInfo>(user_account)?;
+    if !intro_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+    require_owned_by(reply_counter, program_id)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    for reply in &replies {
+        validate_reply_input(reply)?;
+        check_denied_words(program_id, accounts, &[reply])?;
+    }
+
+    let (replier_intro, _replier_bump) = user_intro_pda(replier.key, program_id);
+    if replier_intro == *user_account.key {
+        msg!("Authors cannot reply to their own intro");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let mut counter_data = load_account::<ReplyCounter>(reply_counter)?;
+    let batch_len = replies.len() as u64;
+    if counter_data
+        .reply_count()
+        .checked_add(batch_len)
+        .ok_or(StudentIntroError::Overflow)?
+        > MAX_REPLIES
+    {
+        msg!("Intro can have at most {} replies", MAX_REPLIES);
+        return Err(StudentIntroError::ReplyLimitReached.into());
+    }
+    let config = load_config(program_id, accounts)?;
+    // The whole batch counts as a single reply against the interval
+    let now = Clock::get()?.unix_timestamp;
+    check_reply_interval(&counter_data, config.min_reply_interval, now)?;
+
+    let decimals = Mint::unpack(&token_mint_pda.data.borrow())?.decimals;
+    let reply_reward = scale_to_decimals(config.reply_reward, decimals);
+
+    let burn_amount = scale_to_decimals(config.reply_reward.max(REPLY_BURN_AMOUNT), decimals)
+        .checked_mul(batch_len)
+        .ok_or(StudentIntroError::Overflow)?;
+    burn_reply_fee(
+        token_program,
+        replier_ata,
+        token_mint_pda,
+        replier,
+        burn_amount,
+    )?;
+
+    let rent = Rent::get()?;
+    let mut created = Vec::with_capacity(replies.len());
+    let mut reward: u64 = 0;
+
+    for reply in replies {
+        let reply_account = next_account_info(account_info_iter)?;
+        let reward_marker = next_account_info(account_info_iter)?;
+        let index = counter_data.counter;
+
+        let (pda, bump_seed) = reply_pda(user_account.key, index, program_id);
+        require_pda(reply_account, &pda)?;
+        let (marker, marker_bump) = reply_reward_marker_pda(&pda, replier.key, program_id);
+        require_pda(reward_marker, &marker)?;
+
+        let account_len = Reply::get_account_size(&reply, None)?;
+        create_pda_account(
+            replier,
+            reply_account,
+            system_program,
+            program_id,
+            account_len,
+            &rent,
+            &[
+                user_account.key.as_ref(),
+                index.to_be_bytes().as_ref(),
+                &[bump_seed],
+            ],
+        )?;
+        msg!("Created Reply Account");
+
+        let reply_data = Reply {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::Reply,
+            is_initialized: true,
+            studentinfo: *user_account.key,
+            replier: *replier.key,
+            parent: None,
+            reply,
+            likes: 0,
+            reactions: [0; REACTION_KINDS],
+            flags: 0,
+            deleted: false,
+            edits: Vec::new(),
+        };
+        reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+
+        IntroEvent::ReplyAdded(ReplyAdded {
+            intro: *user_account.key,
+            reply: *reply_account.key,
+            replier: *replier.key,
+            parent: None,
+            index,
+        })
+        .emit();
+
+        let claimed = claim_reward(
+            program_id,
+            replier,
+            reward_marker,
+            system_program,
+            &rent,
+            &[
+                REPLY_REWARD_SEED,
+                pda.as_ref(),
+                replier.key.as_ref(),
+                &[marker_bump],
+            ],
+        )?;
+        if claimed {
+            reward = reward
+                .checked_add(reply_reward)
+                .ok_or(StudentIntroError::Overflow)?;
+        }
+
+        // Bounded by the MAX_REPLIES check above
+        counter_data.counter = index + 1;
+        created.push(*reply_account.key);
+    }
+    debug_msg!("Reply Count: {}", counter_data.counter);
+    counter_data.last_reply_at = now;
+    counter_data.serialize(&mut &mut reply_counter.data.borrow_mut()[..])?;
+
+    record_recent_replies(
+        program_id,
+        replier,
+        reply_index,
+        system_program,
+        &rent,
+        user_account.key,
+        &created,
+    )?;
+
+    if reward > 0 {
+        msg!("Minting batch reply reward to replier associated token account");
+        invoke_signed(
+            &mint_to(
+                token_program.key,
+                token_mint_pda.key,
+                replier_ata.key,
+                mint_auth_pda.key,
+                &[],
+                reward,
+            )?,
+            &[
+                token_mint_pda.clone(),
+                replier_ata.clone(),
+                mint_auth_pda.clone(),
+            ],
+            &[&[MINT_AUTH_SEED, &[auth_bump]]],
+        )
+        .map_err(mint_failed)?;
+    } else {
+        msg!("Batch reply rewards already claimed");
+    }
+
+    let created = CreatedAccounts {
+        version: CREATED_ACCOUNTS_VERSION,
+        addresses: created,
+    };
+    set_return_data(&created.try_to_vec()?);
+    Ok(())
+}
+
+/// Replies to a reply. Children are counted by a `ReplyCounter` seeded by the parent
+/// reply, created on the first child, and each child's PDA is seeded by the parent and
+/// that count just like top-level replies are seeded by the intro.
+pub fn add_nested_reply(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    reply: String,
+    parent: Pubkey,
+) -> ProgramResult {
+    msg!("Adding Nested Reply...");
+    debug_msg!("Parent: {}", parent);
+    debug_msg!("Reply: {}", reply);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let parent_reply = next_account_info(account_info_iter)?;
+    let child_counter = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+
+    require_signer(replier)?;
+
+    check_not_blocked(program_id, block_list, replier.key)?;
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    if *parent_reply.key != parent {
+        msg!("Parent reply account does not match instruction data");
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+
+    require_owned_by(parent_reply, program_id)?;
+
+    let parent_data = load_account::<Reply>(parent_reply)?;
+    if !parent_data.is_initialized() {
+        msg!("Parent reply is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    if parent_data.studentinfo != *user_account.key {
+        msg!("Parent reply belongs to a different intro");
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+
+    validate_reply_input(&reply)?;
+    check_denied_words(program_id, accounts, &[&reply])?;
+
+    let (counter, counter_bump) = reply_counter_pda(parent_reply.key, program_id);
+    require_pda(child_counter, &counter)?;
+
+    if child_counter.data_is_empty() {
+        debug_msg!("create child reply counter");
+        let counter_len = ReplyCounter::SIZE;
+        let rent = Rent::get()?;
+        create_pda_account(
+            replier,
+            child_counter,
+            system_program,
+            program_id,
+            counter_len,
+            &rent,
+            &[
+                parent_reply.key.as_ref(),
+                REPLY_COUNTER_SEED,
+                &[counter_bump],
+            ],
+        )?;
+        ReplyCounter {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized: true,
+            counter: 0,
+            first_index: 0,
+            last_reply_at: 0,
+        }
+        .serialize(&mut &mut child_counter.data.borrow_mut()[..])?;
+    } else {
+        require_owned_by(child_counter, program_id)?;
+    }
+
+    let mut counter_data = load_account::<ReplyCounter>(child_counter)?;
+    if counter_data.reply_count() >= MAX_REPLIES {
+        msg!("Reply already has {} replies", MAX_REPLIES);
+        return Err(StudentIntroError::ReplyLimitReached.into());
+    }
+    let next_counter = counter_data
+        .counter
+        .checked_add(1)
+        .ok_or(StudentIntroError::Overflow)?;
+
+    let account_len = Reply::get_account_size(&reply, Some(parent_reply.key))?;
+
+    let (pda, bump_seed) = reply_pda(parent_reply.key, counter_data.counter, program_id);
+    require_pda(reply_account, &pda)?;
+
+    let rent = Rent::get()?;
+    create_pda_account(
+        replier,
+        reply_account,
+        system_program,
+        program_id,
+        account_len,
+        &rent,
+        &[
+            parent_reply.key.as_ref(),
+            counter_data.counter.to_be_bytes().as_ref(),
+            &[bump_seed],
+        ],
+    )?;
+    msg!("Created Nested Reply Account");
+
+    let reply_data = Reply {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::Reply,
+        is_initialized: true,
+        studentinfo: *user_account.key,
+        replier: *replier.key,
+        parent: Some(parent),
+        reply,
+        likes: 0,
+        reactions: [0; REACTION_KINDS],
+        flags: 0,
+        deleted: false,
+        edits: Vec::new(),
+    };
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+    msg!("Child Reply Count: {}", counter_data.counter);
+
+    IntroEvent::ReplyAdded(ReplyAdded {
+        intro: *user_account.key,
+        reply: *reply_account.key,
+        replier: *replier.key,
+        parent: Some(parent),
+        index: counter_data.counter,
+    })
+    .emit();
+
+    counter_data.counter = next_counter;
+    counter_data.serialize(&mut &mut child_counter.data.borrow_mut()[..])?;
+
+    let created = CreatedAccounts {
+        version: CREATED_ACCOUNTS_VERSION,
+        addresses: vec![*reply_account.key],
+    };
+    set_return_data(&created.try_to_vec()?);
+    Ok(())
+}
+
+pub fn delete_reply(program_id: &Pubkey, accounts: &[AccountInfo], index: u64) -> ProgramResult {
+    msg!("Deleting Reply...");
+    debug_msg!("Reply index: {}", index);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let authority = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+
+    require_signer(authority)?;
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+
+    let (pda, _bump_seed) = reply_pda(user_account.key, index, program_id);
+    require_pda(reply_account, &pda)?;
+    require_owned_by(reply_counter, program_id)?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    // Intro authors can moderate their intro, but the rent always goes back to the replier
+    let replier = if reply_data.replier == *authority.key {
+        authority
+    } else {
+        require_owned_by(user_account, program_id)?;
+        let intro_data = load_account::<StudentInfo>(user_account)?;
+        if intro_data.author != *authority.key {
+            msg!("Signer is neither the author of the reply nor of the intro");
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        let replier = next_account_info(account_info_iter)?;
+        if *replier.key != reply_data.replier {
+            msg!("Rent must be refunded to the replier");
+            return Err(StudentIntroError::IncorrectAccountError.into());
+        }
+        replier
+    };
+
+    // The counter is never decremented, so the index is not handed to a new reply that
+    // would inherit this one's like, reaction and flag markers or its nested replies.
+    // The account stays as a tombstone: emptied, shrunk and no longer initialized, so
+    // every other handler rejects it, with the rent it no longer needs refunded
+    reply_data.is_initialized = false;
+    reply_data.deleted = true;
+    reply_data.reply.clear();
+    reply_data.edits.clear();
+    let tombstone_len = Reply::get_account_size("", reply_data.parent.as_ref())?;
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+    reply_account.realloc(tombstone_len, false)?;
+
+    let refund = reply_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(tombstone_len));
+    **reply_account.lamports.borrow_mut() -= refund;
+    **replier.lamports.borrow_mut() = replier
+        .lamports()
+        .checked_add(refund)
+        .ok_or(StudentIntroError::Overflow)?;
+    msg!("Left a tombstone, refunded {} lamports", refund);
+
+    IntroEvent::ReplyDeleted(ReplyDeleted {
+        intro: *user_account.key,
+        reply: *reply_account.key,
+        replier: *replier.key,
+        index,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// A reply is orphaned once its intro is closed, or once a new intro at the same address
+/// has taken over the counter and the reply's index lies below its `first_index`.
+pub fn close_orphan_reply(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u64,
+) -> ProgramResult {
+    msg!("Closing orphaned reply...");
+    debug_msg!("Reply index: {}", index);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+
+    require_owned_by(reply_account, program_id)?;
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+
+    let reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    // Tombstones left by DeleteReply still hold their remaining rent
+    if !reply_data.is_initialized() && !reply_data.deleted {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    if reply_data.studentinfo != *user_account.key {
+        msg!("Reply does not belong to this intro");
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+
+    if reply_data.replier != *replier.key {
+        msg!("Rent can only be refunded to the replier");
+        return Err(StudentIntroError::IncorrectAccountError.into());
+    }
+
+    let intro_closed = user_account.data_is_empty()
+        && (*user_account.owner == SYSTEM_PROGRAM_ID || user_account.lamports() == 0);
+
+    // Nested replies are seeded by their parent, so only top-level ones can be matched
+    // against the counter
+    let superseded = if reply_data.parent.is_none() && !reply_counter.data_is_empty() {
+        require_owned_by(reply_counter, program_id)?;
+        let counter_data = load_account::<ReplyCounter>(reply_counter)?;
+        index < counter_data.first_index
+            && *reply_account.key == reply_pda(user_account.key, index, program_id).0
+    } else {
+        false
+    };
+
+    if !intro_closed && !superseded {
+        msg!("Intro still exists");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let reply_lamports = close_account(reply_account, replier)?;
+    msg!("Refunded {} lamports to the replier", reply_lamports);
+
+    Ok(())
+}
+
+pub fn soft_delete_reply(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    deleted: bool,
+) -> ProgramResult {
+    msg!("Soft deleting Reply...");
+    debug_msg!("Deleted: {}", deleted);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let authority = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+
+    require_signer(authority)?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    if reply_data.replier != *authority.key && *authority.key != ADMIN_PUBKEY {
+        msg!("Signer is neither the author of the reply nor the admin");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    reply_data.deleted = deleted;
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+
+    IntroEvent::ReplySoftDeleted(ReplySoftDeleted {
+        reply: *reply_account.key,
+        authority: *authority.key,
+        deleted,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn update_reply(program_id: &Pubkey, accounts: &[AccountInfo], reply: String) -> ProgramResult {
+    msg!("Updating Reply...");
+    debug_msg!("Reply: {}", reply);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let replier = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+
+    require_signer(replier)?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    if reply_data.replier != *replier.key {
+        msg!("Signer is not the author of the reply");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // A hidden reply must be restored before it can be edited
+    if reply_data.deleted {
+        msg!("Reply has been deleted");
+        return Err(StudentIntroError::ReplyDeleted.into());
+    }
+
+    validate_reply_input(&reply)?;
+    check_denied_words(program_id, accounts, &[&reply])?;
+
+    // The reply account is sized to the original text, so edits can shrink but never grow it
+    let update_len = Reply::get_account_size(&reply, reply_data.parent.as_ref())?;
+    if update_len > reply_account.data_len() {
+        msg!("Reply is longer than the reply account");
+        return Err(StudentIntroError::InvalidDataLength.into());
+    }
+
+    if reply_data.edits.len() >= REPLY_EDIT_HISTORY {
+        reply_data.edits.remove(0);
+    }
+    reply_data.edits.push(ReplyEdit {
+        previous_hash: hash(reply_data.reply.as_bytes()).to_bytes(),
+        edited_at: Clock::get()?.unix_timestamp,
+    });
+    debug_msg!("Edits recorded: {}", reply_data.edits.len());
+
+    reply_data.reply = reply;
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+    msg!("Reply updated");
+
+    IntroEvent::ReplyUpdated(ReplyUpdated {
+        reply: *reply_account.key,
+        replier: *replier.key,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn rate_intro(program_id: &Pubkey, accounts: &[AccountInfo], rating: u8) -> ProgramResult {
+    msg!("Rating student intro...");
+    debug_msg!("Rating: {}", rating);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let rater = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let rating_marker = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(rater)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    if !(1..=5).contains(&rating) {
+        msg!("Rating must be between 1 and 5");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    require_owned_by(user_account, program_id)?;
+
+    let mut account_data = load_account::<StudentInfo>(user_account)?;
+
+    debug_msg!("checking if studentinfo account is initialized");
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (marker, marker_bump) = rating_marker_pda(user_account.key, rater.key, program_id);
+    require_pda(rating_marker, &marker)?;
+
+    if !rating_marker.data_is_empty() {
+        msg!("Intro already rated by this account");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let rent = Rent::get()?;
+    create_pda_account(
+        rater,
+        rating_marker,
+        system_program,
+        program_id,
+        RatingMarker::SIZE,
+        &rent,
+        &[
+            RATING_SEED,
+            user_account.key.as_ref(),
+            rater.key.as_ref(),
+            &[marker_bump],
+        ],
+    )?;
+    msg!("Created rating marker");
+
+    let marker_data = RatingMarker {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::RatingMarker,
+        is_initialized: true,
+        rating,
+    };
+    marker_data.serialize(&mut &mut rating_marker.data.borrow_mut()[..])?;
+
+    account_data.rating_sum = account_data
+        .rating_sum
+        .checked_add(rating.into())
+        .ok_or(StudentIntroError::Overflow)?;
+    account_data.rating_count = account_data
+        .rating_count
+        .checked_add(1)
+        .ok_or(StudentIntroError::Overflow)?;
+    let average = account_data.average_rating();
+    msg!("Average rating: {}", average);
+    account_data.serialize(&mut &mut user_account.data.borrow_mut()[..])?;
+
+    IntroEvent::IntroRated(IntroRated {
+        intro: *user_account.key,
+        rater: *rater.key,
+        rating,
+        average,
+        rating_count: account_data.rating_count,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn like_reply(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Liking Reply...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let liker = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let like_marker = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(liker)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (marker, marker_bump) = like_marker_pda(reply_account.key, liker.key, program_id);
+    require_pda(like_marker, &marker)?;
+
+    // One marker account per (reply, liker) pair; it existing means the like was already counted
+    if !like_marker.data_is_empty() {
+        msg!("Reply already liked by this account");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let marker_len = LikeMarker::SIZE;
+
+    let rent = Rent::get()?;
+
+    create_pda_account(
+        liker,
+        like_marker,
+        system_program,
+        program_id,
+        marker_len,
+        &rent,
+        &[
+            reply_account.key.as_ref(),
+            liker.key.as_ref(),
+            LIKE_SEED,
+            &[marker_bump],
+        ],
+    )?;
+    msg!("Created like marker");
+
+    let marker_data = LikeMarker {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::LikeMarker,
+        is_initialized: true,
+    };
+    marker_data.serialize(&mut &mut like_marker.data.borrow_mut()[..])?;
+
+    reply_data.likes = reply_data
+        .likes
+        .checked_add(1)
+        .ok_or(StudentIntroError::Overflow)?;
+    debug_msg!("Likes: {}", reply_data.likes);
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+
+    IntroEvent::ReplyLiked(ReplyLiked {
+        reply: *reply_account.key,
+        liker: *liker.key,
+        likes: reply_data.likes,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn add_reaction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    kind: ReactionKind,
+) -> ProgramResult {
+    msg!("Adding Reaction...");
+    debug_msg!("Kind: {:?}", kind);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let reactor = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let reaction_marker = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(reactor)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (marker, marker_bump) =
+        reaction_marker_pda(reply_account.key, reactor.key, kind, program_id);
+    require_pda(reaction_marker, &marker)?;
+
+    // One marker account per (reply, reactor, kind); it existing means the reaction was counted
+    if !reaction_marker.data_is_empty() {
+        msg!("Reply already has this reaction from this account");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let marker_len = ReactionMarker::SIZE;
+
+    let rent = Rent::get()?;
+
+    create_pda_account(
+        reactor,
+        reaction_marker,
+        system_program,
+        program_id,
+        marker_len,
+        &rent,
+        &[
+            reply_account.key.as_ref(),
+            reactor.key.as_ref(),
+            REACTION_SEED,
+            &[kind as u8],
+            &[marker_bump],
+        ],
+    )?;
+    msg!("Created reaction marker");
+
+    let marker_data = ReactionMarker {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::ReactionMarker,
+        is_initialized: true,
+    };
+    marker_data.serialize(&mut &mut reaction_marker.data.borrow_mut()[..])?;
+
+    let count = &mut reply_data.reactions[kind as usize];
+    *count = count.checked_add(1).ok_or(StudentIntroError::Overflow)?;
+    let count = *count;
+    debug_msg!("{:?} reactions: {}", kind, count);
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+
+    IntroEvent::ReactionAdded(ReactionAdded {
+        reply: *reply_account.key,
+        reactor: *reactor.key,
+        kind,
+        count,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn flag_reply(program_id: &Pubkey, accounts: &[AccountInfo], reason: u8) -> ProgramResult {
+    msg!("Flagging Reply...");
+    debug_msg!("Reason: {}", reason);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let reporter = next_account_info(account_info_iter)?;
+    let reply_account = next_account_info(account_info_iter)?;
+    let flag_marker = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(reporter)?;
+
+    let reason = match FlagReason::try_from(reason) {
+        Ok(reason) => reason,
+        Err(e) => {
+            msg!("Unknown flag reason {}", reason);
+            return Err(e.into());
+        }
+    };
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_owned_by(reply_account, program_id)?;
+
+    let mut reply_data = load_account::<Reply>(reply_account)?;
+
+    debug_msg!("checking if reply account is initialized");
+    if !reply_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let (marker, marker_bump) = flag_marker_pda(reply_account.key, reporter.key, program_id);
+    require_pda(flag_marker, &marker)?;
+
+    // One marker account per (reply, reporter) pair; it existing means the flag was counted
+    if !flag_marker.data_is_empty() {
+        msg!("Reply already flagged by this account");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let marker_len = FlagMarker::SIZE;
+
+    let rent = Rent::get()?;
+
+    create_pda_account(
+        reporter,
+        flag_marker,
+        system_program,
+        program_id,
+        marker_len,
+        &rent,
+        &[
+            reply_account.key.as_ref(),
+            reporter.key.as_ref(),
+            FLAG_SEED,
+            &[marker_bump],
+        ],
+    )?;
+    msg!("Created flag marker");
+
+    let marker_data = FlagMarker {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::FlagMarker,
+        is_initialized: true,
+        reason,
+    };
+    marker_data.serialize(&mut &mut flag_marker.data.borrow_mut()[..])?;
+
+    reply_data.flags = reply_data
+        .flags
+        .checked_add(1)
+        .ok_or(StudentIntroError::Overflow)?;
+    debug_msg!("Flags: {}", reply_data.flags);
+    reply_data.serialize(&mut &mut reply_account.data.borrow_mut()[..])?;
+
+    IntroEvent::ReplyFlagged(ReplyFlagged {
+        reply: *reply_account.key,
+        reporter: *reporter.key,
+        reason,
+        flags: reply_data.flags,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn add_to_block_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    key: Pubkey,
+) -> ProgramResult {
+    msg!("Adding to block list...");
+    debug_msg!("Key: {}", key);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "manage the block list")?;
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let (pda, bump_seed) = block_list_pda(program_id);
+    require_pda(block_list, &pda)?;
+
+    if block_list.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            admin,
+            block_list,
+            system_program,
+            program_id,
+            BlockList::SIZE,
+            &rent,
+            &[BLOCKLIST_SEED, &[bump_seed]],
+        )?;
+        msg!("Created block list");
+    } else {
+        require_owned_by(block_list, program_id)?;
+    }
+
+    let mut block_list_data = load_account::<BlockList>(block_list)?;
+    block_list_data.magic = ACCOUNT_MAGIC;
+    block_list_data.version = CURRENT_VERSION;
+    block_list_data.discriminator = AccountType::BlockList;
+    block_list_data.is_initialized = true;
+
+    if !block_list_data.blocked.contains(&key) {
+        if block_list_data.blocked.len() >= MAX_BLOCKED_KEYS {
+            msg!("Block list already has {} keys", MAX_BLOCKED_KEYS);
+            return Err(StudentIntroError::BlockListFull.into());
+        }
+        block_list_data.blocked.push(key);
+    }
+    block_list_data.serialize(&mut &mut block_list.data.borrow_mut()[..])?;
+
+    IntroEvent::BlockListUpdated(BlockListUpdated { key, blocked: true }).emit();
+
+    Ok(())
+}
+
+pub fn remove_from_block_list(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    key: Pubkey,
+) -> ProgramResult {
+    msg!("Removing from block list...");
+    debug_msg!("Key: {}", key);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let block_list = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "manage the block list")?;
+
+    let (pda, _bump_seed) = block_list_pda(program_id);
+    require_pda(block_list, &pda)?;
+    require_owned_by(block_list, program_id)?;
+
+    let mut block_list_data = load_account::<BlockList>(block_list)?;
+    if !block_list_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    block_list_data.blocked.retain(|blocked| *blocked != key);
+    // The vec only shrinks here, so clear the tail it no longer covers
+    let mut data = block_list.data.borrow_mut();
+    data.fill(0);
+    block_list_data.serialize(&mut &mut data[..])?;
+
+    IntroEvent::BlockListUpdated(BlockListUpdated {
+        key,
+        blocked: false,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn add_denied_word(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    term: String,
+) -> ProgramResult {
+    msg!("Adding denied word...");
+    debug_msg!("Term: {}", term);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let denylist = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "manage the word denylist")?;
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let term = term.to_lowercase();
+    if term.trim().is_empty() || term.len() > MAX_DENIED_WORD_LEN {
+        msg!(
+            "Denied word must be between 1 and {} bytes",
+            MAX_DENIED_WORD_LEN
+        );
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let (pda, bump_seed) = word_denylist_pda(program_id);
+    require_pda(denylist, &pda)?;
+
+    if denylist.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            admin,
+            denylist,
+            system_program,
+            program_id,
+            WordDenylist::SIZE,
+            &rent,
+            &[DENYLIST_SEED, &[bump_seed]],
+        )?;
+        msg!("Created word denylist");
+    } else {
+        require_owned_by(denylist, program_id)?;
+    }
+
+    let mut denylist_data = load_account::<WordDenylist>(denylist)?;
+    denylist_data.magic = ACCOUNT_MAGIC;
+    denylist_data.version = CURRENT_VERSION;
+    denylist_data.discriminator = AccountType::WordDenylist;
+    denylist_data.is_initialized = true;
+
+    if !denylist_data.terms.contains(&term) {
+        if denylist_data.terms.len() >= MAX_DENIED_WORDS {
+            msg!("Word denylist already has {} terms", MAX_DENIED_WORDS);
+            return Err(StudentIntroError::DenylistFull.into());
+        }
+        denylist_data.terms.push(term.clone());
+    }
+    denylist_data.serialize(&mut &mut denylist.data.borrow_mut()[..])?;
+
+    IntroEvent::WordDenylistUpdated(WordDenylistUpdated { term, denied: true }).emit();
+
+    Ok(())
+}
+
+pub fn remove_denied_word(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    term: String,
+) -> ProgramResult {
+    msg!("Removing denied word...");
+    debug_msg!("Term: {}", term);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let denylist = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "manage the word denylist")?;
+
+    let (pda, _bump_seed) = word_denylist_pda(program_id);
+    require_pda(denylist, &pda)?;
+    require_owned_by(denylist, program_id)?;
+
+    let mut denylist_data = load_account::<WordDenylist>(denylist)?;
+    if !denylist_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    let term = term.to_lowercase();
+    denylist_data.terms.retain(|denied| *denied != term);
+    // The vec only shrinks here, so clear the tail it no longer covers
+    let mut data = denylist.data.borrow_mut();
+    data.fill(0);
+    denylist_data.serialize(&mut &mut data[..])?;
+
+    IntroEvent::WordDenylistUpdated(WordDenylistUpdated {
+        term,
+        denied: false,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn get_stats(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Getting intro stats...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+
+    require_owned_by(user_account, program_id)?;
+    require_owned_by(reply_counter, program_id)?;
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+
+    let account_data = load_account::<StudentInfo>(user_account)?;
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+    let counter_data = load_account::<ReplyCounter>(reply_counter)?;
+
+    let stats = IntroStats {
+        reply_count: counter_data.reply_count(),
+        created_at: account_data.created_at,
+        rating: account_data.average_rating(),
+        rating_count: account_data.rating_count,
+    };
+    set_return_data(&stats.try_to_vec()?);
+
+    Ok(())
+}
+
+pub fn list_replies(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    start: u64,
+    limit: u8,
+) -> ProgramResult {
+    msg!("Listing replies...");
+    debug_msg!("Start: {}, limit: {}", start, limit);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let user_account = next_account_info(account_info_iter)?;
+    let reply_counter = next_account_info(account_info_iter)?;
+
+    require_owned_by(reply_counter, program_id)?;
+
+    let (counter, _counter_bump) = reply_counter_pda(user_account.key, program_id);
+    require_pda(reply_counter, &counter)?;
+
+    if limit > MAX_LIST_REPLIES {
+        msg!("Limit can be at most {}", MAX_LIST_REPLIES);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let counter_data = load_account::<ReplyCounter>(reply_counter)?;
+    // Indices below first_index belong to an earlier intro at this address
+    let start = start.max(counter_data.first_index);
+    let end = start.saturating_add(limit.into()).min(counter_data.counter);
+
+    let page = ReplyPage {
+        start,
+        replies: (start..end)
+            .map(|index| reply_pda(user_account.key, index, program_id).0)
+            .collect(),
+    };
+    set_return_data(&page.try_to_vec()?);
+
+    Ok(())
+}
+
+pub fn set_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_name_len: u32,
+    max_message_len: u32,
+    reply_reward: u64,
+    intro_reward: u64,
+    min_reply_interval: i64,
+) -> ProgramResult {
+    msg!("Setting config...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "set the config")?;
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let (pda, bump_seed) = config_pda(program_id);
+    require_pda(config, &pda)?;
+
+    if intro_reward > MAX_INTRO_REWARD {
+        msg!("Reward amount exceeds {}", MAX_INTRO_REWARD);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    // A zero limit would reject every intro or let replies through back to back
+    if max_name_len == 0 || max_message_len == 0 || min_reply_interval <= 0 {
+        msg!("Name and message limits and the reply interval must be positive");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if config.data_is_empty() {
+        let rent = Rent::get()?;
+        create_pda_account(
+            admin,
+            config,
+            system_program,
+            program_id,
+            Config::SIZE,
+            &rent,
+            &[CONFIG_SEED, &[bump_seed]],
+        )?;
+        msg!("Created config");
+    } else {
+        require_owned_by(config, program_id)?;
+    }
+
+    let mut config_data = load_account::<Config>(config)?;
+    config_data.magic = ACCOUNT_MAGIC;
+    config_data.version = CURRENT_VERSION;
+    config_data.discriminator = AccountType::Config;
+    config_data.is_initialized = true;
+    config_data.max_name_len = max_name_len;
+    config_data.max_message_len = max_message_len;
+    config_data.reply_reward = reply_reward;
+    config_data.intro_reward = intro_reward;
+    config_data.min_reply_interval = min_reply_interval;
+    config_data.serialize(&mut &mut config.data.borrow_mut()[..])?;
+
+    IntroEvent::ConfigUpdated(ConfigUpdated {
+        admin: *admin.key,
+        max_name_len,
+        max_message_len,
+        reply_reward,
+        intro_reward,
+        min_reply_interval,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn set_paused(program_id: &Pubkey, accounts: &[AccountInfo], paused: bool) -> ProgramResult {
+    msg!("Setting paused to {}...", paused);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let config = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "pause the program")?;
+
+    let (pda, _bump_seed) = config_pda(program_id);
+    require_pda(config, &pda)?;
+    require_owned_by(config, program_id)?;
+
+    let mut config_data = load_account::<Config>(config)?;
+    if !config_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    config_data.paused = paused;
+    config_data.serialize(&mut &mut config.data.borrow_mut()[..])?;
+
+    IntroEvent::PauseUpdated(PauseUpdated {
+        admin: *admin.key,
+        paused,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// The treasury is system-owned, so it must stay rent-exempt or be emptied completely;
+/// withdrawals that would leave it in between are rejected.
+pub fn withdraw_treasury(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    lamports: u64,
+    destination: Option<Pubkey>,
+) -> ProgramResult {
+    msg!("Withdrawing from treasury...");
+    debug_msg!("Lamports: {}", lamports);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let treasury = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+    let destination_account = match destination {
+        Some(destination) => {
+            let account = next_account_info(account_info_iter)?;
+            if *account.key != destination {
+                msg!("Destination account does not match the instruction");
+                return Err(StudentIntroError::IncorrectAccountError.into());
+            }
+            account
+        }
+        None => admin,
+    };
+
+    require_admin(admin, "withdraw from the treasury")?;
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let (pda, bump_seed) = treasury_pda(program_id);
+    require_pda(treasury, &pda)?;
+
+    if lamports == 0 {
+        msg!("Withdrawal must be greater than zero");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let remaining = treasury
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    if remaining != 0 && remaining < Rent::get()?.minimum_balance(0) {
+        msg!("Withdrawal would leave the treasury below rent exemption");
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    invoke_signed(
+        &system_instruction::transfer(treasury.key, destination_account.key, lamports),
+        &[
+            treasury.clone(),
+            destination_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[TREASURY_SEED, &[bump_seed]]],
+    )?;
+    msg!("Treasury withdrawn");
+
+    IntroEvent::TreasuryWithdrawn(TreasuryWithdrawn {
+        admin: *admin.key,
+        destination: *destination_account.key,
+        lamports,
+    })
+    .emit();
+
+    Ok(())
+}
+
+/// Burns reward tokens from the signer's associated token account.
+pub fn burn_tokens(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    msg!("Burning tokens...");
+    debug_msg!("Amount: {}", amount);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let owner = next_account_info(account_info_iter)?;
+    let owner_ata = next_account_info(account_info_iter)?;
+    let token_mint_pda = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    require_signer(owner)?;
+
+    let (expected_mint_pda, _mint_bump) = crate::pda::token_mint_pda(program_id);
+    require_key_eq(token_mint_pda, &expected_mint_pda, "token mint")?;
+
+    require_key_eq(
+        owner_ata,
+        &get_associated_token_address(owner.key, token_mint_pda.key),
+        "token account",
+    )?;
+
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+
+    if amount == 0 {
+        msg!("Burn amount must be greater than zero");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    invoke(
+        &burn(
+            token_program.key,
+            owner_ata.key,
+            token_mint_pda.key,
+            owner.key,
+            &[],
+            amount,
+        )?,
+        &[owner_ata.clone(), token_mint_pda.clone(), owner.clone()],
+    )?;
+    msg!("Burned {} tokens", amount);
+
+    IntroEvent::TokensBurned(TokensBurned {
+        owner: *owner.key,
+        amount,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn set_freeze_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    new_authority: Option<Pubkey>,
+) -> ProgramResult {
+    msg!("Setting freeze authority...");
+    debug_msg!("New authority: {:?}", new_authority);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let admin = next_account_info(account_info_iter)?;
+    let token_mint_pda = next_account_info(account_info_iter)?;
+    let freeze_auth_pda = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    require_admin(admin, "set the freeze authority")?;
+
+    let (expected_mint_pda, _mint_bump) = crate::pda::token_mint_pda(program_id);
+    require_key_eq(token_mint_pda, &expected_mint_pda, "token mint")?;
+
+    let (expected_freeze_pda, freeze_bump) = crate::pda::freeze_auth_pda(program_id);
+    require_key_eq(
+        freeze_auth_pda,
+        &expected_freeze_pda,
+        "freeze authority account",
+    )?;
+
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+
+    invoke_signed(
+        &set_authority(
+            token_program.key,
+            token_mint_pda.key,
+            new_authority.as_ref(),
+            AuthorityType::FreezeAccount,
+            freeze_auth_pda.key,
+            &[],
+        )?,
+        &[token_mint_pda.clone(), freeze_auth_pda.clone()],
+        &[&[FREEZE_AUTH_SEED, &[freeze_bump]]],
+    )?;
+    msg!("Freeze authority updated");
+
+    IntroEvent::FreezeAuthorityUpdated(FreezeAuthorityUpdated {
+        admin: *admin.key,
+        mint: *token_mint_pda.key,
+        new_authority,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn tip_author(program_id: &Pubkey, accounts: &[AccountInfo], lamports: u64) -> ProgramResult {
+    msg!("Tipping intro author...");
+    debug_msg!("Lamports: {}", lamports);
+
+    let account_info_iter = &mut accounts.iter();
+
+    let tipper = next_account_info(account_info_iter)?;
+    let author = next_account_info(account_info_iter)?;
+    let user_account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(tipper)?;
+
+    if lamports == 0 {
+        msg!("Tip must be greater than zero");
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+
+    let (pda, _bump_seed) = user_intro_pda(author.key, program_id);
+    if pda != *user_account.key {
+        msg!("Author does not own the intro");
+        return Err(StudentIntroError::InvalidPDA.into());
+    }
+
+    require_owned_by(user_account, program_id)?;
+
+    let account_data = load_account::<StudentInfo>(user_account)?;
+    if !account_data.is_initialized() {
+        msg!("Account is not initialized");
+        return Err(StudentIntroError::UninitializedAccount.into());
+    }
+
+    invoke(
+        &system_instruction::transfer(tipper.key, author.key, lamports),
+        &[tipper.clone(), author.clone(), system_program.clone()],
+    )?;
+    msg!("Tip sent");
+
+    IntroEvent::TipSent(TipSent {
+        tipper: *tipper.key,
+        author: *author.key,
+        intro: *user_account.key,
+        lamports,
+    })
+    .emit();
+
+    Ok(())
+}
+
+pub fn migrate_account(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    msg!("Migrating account...");
+
+    let account_info_iter = &mut accounts.iter();
+
+    // Owner of the intro for intro and counter accounts, any payer for replies
+    let payer = next_account_info(account_info_iter)?;
+    let account = next_account_info(account_info_iter)?;
+    let system_program = next_account_info(account_info_iter)?;
+
+    require_signer(payer)?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_owned_by(account, program_id)?;
+    // Accounts already written in the current layout, such as intros created with an
+    // avatar URI, have nothing to migrate
+    if let Some((CURRENT_VERSION, account_type)) = account_header(&account.data.borrow()) {
+        if account_type != AccountType::Uninitialized {
+            msg!("Account is already at version {}", CURRENT_VERSION);
+            return Ok(());
+        }
+    }
+    // Intros take the author's intro reward marker as well
+    let reward_marker = account_info_iter.next();
+
+    let legacy_type = legacy_account_type(&account.data.borrow());
+    let v1_type = v1_account_type(&account.data.borrow());
+    let migrated = match (legacy_type, v1_type) {
+        (Some(account_type), _) => migrate_legacy(
+            program_id,
+            payer,
+            account,
+            system_program,
+            reward_marker,
+            account_type,
+        )?,
+        (None, Some(account_type)) => migrate_v1(
+            program_id,
+            payer,
+            account,
+            system_program,
+            reward_marker,
+            account_type,
+        )?,
+        (None, None) => {
+            msg!("Account is not in a legacy or version 1 layout");
+            return Err(StudentIntroError::UnsupportedAccountVersion.into());
+        }
+    };
+
+    resize_account(account, payer, system_program, migrated.len())?;
+    account.data.borrow_mut().copy_from_slice(&migrated);
+    msg!("Account migrated to version {}", CURRENT_VERSION);
+
+    Ok(())
+}
+
+/// Rewrites an account from its string-discriminator layout.
+fn migrate_legacy<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    reward_marker: Option<&AccountInfo<'a>>,
+    account_type: AccountType,
+) -> Result<Vec<u8>, ProgramError> {
+    let (user_pda, _bump_seed) = user_intro_pda(payer.key, program_id);
+    let migrated = match account_type {
+        AccountType::StudentInfo => {
+            // Shrinking the old fixed-size account refunds rent to the payer, so only the
+            // author may migrate their intro
+            require_pda(account, &user_pda)?;
+            let legacy = try_from_slice_unchecked::<LegacyStudentInfo>(&account.data.borrow())
+                .map_err(|_| StudentIntroError::DeserializationFailed)?;
+
+            // Legacy intros were rewarded when they were created
+            mark_intro_rewarded(program_id, payer, reward_marker, system_program)?;
+
+            let now = Clock::get()?.unix_timestamp;
+            StudentInfo {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::StudentInfo,
+                is_initialized: legacy.is_initialized,
+                author: *payer.key,
+                name: legacy.name,
+                msg: legacy.msg,
+                created_at: now,
+                updated_at: now,
+                rating_count: 0,
+                rating_sum: 0,
+                avatar_uri: String::new(),
+                message_chunks: 0,
+            }
+            .try_to_vec()?
+        }
+        AccountType::ReplyCounter => {
+            let (counter, _counter_bump) = reply_counter_pda(&user_pda, program_id);
+            require_pda(account, &counter)?;
+            let legacy = try_from_slice_unchecked::<LegacyReplyCounter>(&account.data.borrow())
+                .map_err(|_| StudentIntroError::DeserializationFailed)?;
+            ReplyCounter {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::ReplyCounter,
+                is_initialized: legacy.is_initialized,
+                counter: legacy.counter.into(),
+                first_index: 0,
+                last_reply_at: 0,
+            }
+            .try_to_vec()?
+        }
+        AccountType::Reply => {
+            // Legacy replies never recorded their author, so they stay unattributed
+            let legacy = try_from_slice_unchecked::<LegacyReply>(&account.data.borrow())
+                .map_err(|_| StudentIntroError::DeserializationFailed)?;
+            let reply_len = Reply::get_account_size(&legacy.reply, None)?;
+            let mut data = Reply {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::Reply,
+                is_initialized: legacy.is_initialized,
+                studentinfo: legacy.studentinfo,
+                replier: Pubkey::default(),
+                parent: None,
+                reply: legacy.reply,
+                likes: 0,
+                reactions: [0; REACTION_KINDS],
+                flags: 0,
+                deleted: false,
+                edits: Vec::new(),
+            }
+            .try_to_vec()?;
+            // Reserve the edit history like replies created by AddReply
+            data.resize(reply_len, 0);
+            data
+        }
+        _ => {
+            msg!("Account is not in a legacy layout");
+            return Err(StudentIntroError::UnsupportedAccountVersion.into());
+        }
+    };
+    Ok(migrated)
+}
+
+/// Rewrites an account from the version 1 layout, which had no magic bytes.
+fn migrate_v1<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    reward_marker: Option<&AccountInfo<'a>>,
+    account_type: AccountType,
+) -> Result<Vec<u8>, ProgramError> {
+    let (user_pda, _bump_seed) = user_intro_pda(payer.key, program_id);
+    let migrated = match account_type {
+        AccountType::StudentInfo => {
+            // Only the author may migrate their intro, since a shrinking account would
+            // refund rent to the payer
+            require_pda(account, &user_pda)?;
+            let v1 = try_from_slice_unchecked::<StudentInfoV1>(&account.data.borrow())
+                .map_err(|_| StudentIntroError::DeserializationFailed)?;
+
+            // The claim moves from the intro to the author's wallet
+            if v1.rewards_claimed {
+                mark_intro_rewarded(program_id, payer, reward_marker, system_program)?;
+            }
+
+            StudentInfo {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::StudentInfo,
+                is_initialized: v1.is_initialized,
+                author: v1.author,
+                name: v1.name,
+                msg: v1.msg,
+                created_at: v1.created_at,
+                updated_at: v1.updated_at,
+                rating_count: v1.rating_count,
+                rating_sum: v1.rating_sum,
+                avatar_uri: v1.avatar_uri,
+                message_chunks: v1.message_chunks,
+            }
+            .try_to_vec()?
+        }
+        AccountType::ReplyCounter => {
+            let (counter, _counter_bump) = reply_counter_pda(&user_pda, program_id);
+            require_pda(account, &counter)?;
+            let v1 = try_from_slice_unchecked::<ReplyCounterV1>(&account.data.borrow())
+                .map_err(|_| StudentIntroError::DeserializationFailed)?;
+            // Version 1 closed the counter with its intro, so every reply it counted
+            // belongs to the current intro
+            ReplyCounter {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::ReplyCounter,
+                is_initialized: v1.is_initialized,
+                counter: v1.counter,
+                first_index: 0,
+                last_reply_at: v1.last_reply_at,
+            }
+            .try_to_vec()?
+        }
+        _ => {
+            // Every other layout only gained the magic bytes ahead of its version
+            let data = account.data.borrow();
+            let mut migrated = ACCOUNT_MAGIC.to_vec();
+            migrated.push(CURRENT_VERSION);
+            migrated.extend_from_slice(&data[1..]);
+            migrated
+        }
+    };
+    Ok(migrated)
+}
+
+/// Creates the author's intro reward marker for an intro rewarded under an older
+/// layout, so `InitUserInput` does not mint for the same wallet again.
+fn mark_intro_rewarded<'a>(
+    program_id: &Pubkey,
+    payer: &AccountInfo<'a>,
+    reward_marker: Option<&AccountInfo<'a>>,
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    let reward_marker = reward_marker.ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (marker, marker_bump) = intro_reward_marker_pda(payer.key, program_id);
+    require_pda(reward_marker, &marker)?;
+    claim_reward(
+        program_id,
+        payer,
+        reward_marker,
+        system_program,
+        &Rent::get()?,
+        &[INTRO_REWARD_SEED, payer.key.as_ref(), &[marker_bump]],
+    )?;
+    Ok(())
+}
+
+pub fn initialize_token_mint(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    decimals: u8,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+
+    // The order of accounts is not arbitrary, the client will send them in this order
+    // Whoever sent in the transaction
+    let initializer = next_account_info(account_info_iter)?;
+    // Token mint PDA - derived on the client
+    let token_mint_pda = next_account_info(account_info_iter)?;
+    // Token mint authorirty (this should be you)
+    let mint_auth_pda = next_account_info(account_info_iter)?;
+    // System program to create a new account
+    let system_program = next_account_info(account_info_iter)?;
+    // Solana Token program address
+    let token_program = next_account_info(account_info_iter)?;
+    // System account to calcuate the rent
+    let sysvar_rent = next_account_info(account_info_iter)?;
+    // Metadata PDA for the mint, owned by the Token Metadata program
+    let metadata_account = next_account_info(account_info_iter)?;
+    // Token Metadata program
+    let metadata_program = next_account_info(account_info_iter)?;
+
+    require_admin(initializer, "initialize the token mint")?;
+
+    // Rewards are written with REWARD_DECIMALS, so a finer mint could not represent them
+    if decimals > REWARD_DECIMALS {
+        msg!("Token decimals cannot exceed {}", REWARD_DECIMALS);
+        return Err(StudentIntroError::InvalidInput.into());
+    }
+
+    let (expected_token_mint_pda, mint_bump) = crate::pda::token_mint_pda(program_id);
+
+    let (expected_mint_auth_pda, auth_bump) = crate::pda::mint_auth_pda(program_id);
+
+    debug_msg!("Token mint: {:?}", expected_token_mint_pda);
+    debug_msg!("Mint authority: {:?}", expected_mint_auth_pda);
+
+    require_key_eq(
+        token_mint_pda,
+        &expected_token_mint_pda,
+        "token mint account",
+    )?;
+
+    if token_mint_pda.data_len() != 0 || *token_mint_pda.owner == TOKEN_PROGRAM_ID {
+        msg!("Token mint already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    require_key_eq(token_program, &TOKEN_PROGRAM_ID, "token program")?;
+    require_key_eq(mint_auth_pda, &expected_mint_auth_pda, "mint auth account")?;
+    require_key_eq(system_program, &SYSTEM_PROGRAM_ID, "system program")?;
+    require_key_eq(sysvar_rent, &RENT_PROGRAM_ID, "rent program")?;
+
+    let (expected_metadata_pda, _metadata_bump) =
+        crate::pda::token_metadata_pda(&expected_token_mint_pda);
+    require_key_eq(metadata_account, &expected_metadata_pda, "metadata account")?;
+
+    require_key_eq(
+        metadata_program,
+        &TOKEN_METADATA_PROGRAM_ID,
+        "metadata program",
+    )?;
+
+    // Calculate the rent
+    let rent = Rent::get()?;
+
+    // Create the token mint PDA
+    create_pda_account(
+        initializer,
+        token_mint_pda,
+        system_program,
+        token_program.key,
+        82, // We know the size of a mint account is 82 (remember it lol)
+        &rent,
+        &[TOKEN_MINT_SEED, &[mint_bump]],
+    )?;
+
+    msg!("Created token mint account");
+
+    // Freeze authority - nobody can freeze unless the deployment opts in
+    let freeze_authority = if cfg!(feature = "freeze-authority") {
+        Some(crate::pda::freeze_auth_pda(program_id).0)
+    } else {
+        None
+    };
+
+    // Initialize the mint account
+    invoke_signed(
         &initialize_mint(
             token_program.key,
             token_mint_pda.key,
             mint_auth_pda.key,
-            Option::None, // Freeze authority - we don't want anyone to be able to freeze!
-            9,            // Number of decimals
+            freeze_authority.as_ref(),
+            decimals,
         )?,
         // Which accounts we're reading from or writing to
         &[
@@ -430,10 +3558,92 @@ pub fn initialize_token_mint(program_id: &Pubkey, accounts: &[AccountInfo]) -> P
             mint_auth_pda.clone(),
         ],
         // The seeds for our token mint PDA
-        &[&[b"token_mint", &[mint_bump]]],
+        &[&[TOKEN_MINT_SEED, &[mint_bump]]],
     )?;
 
     msg!("Initialized token mint");
 
+    // Give the mint a name and symbol so wallets don't show it as unknown
+    invoke_signed(
+        &create_metadata_account_v3(
+            metadata_account.key,
+            token_mint_pda.key,
+            mint_auth_pda.key,
+            initializer.key,
+            initializer.key,
+        ),
+        &[
+            metadata_account.clone(),
+            token_mint_pda.clone(),
+            mint_auth_pda.clone(),
+            initializer.clone(),
+            system_program.clone(),
+            sysvar_rent.clone(),
+            metadata_program.clone(),
+        ],
+        // The mint authority PDA signs for the mint
+        &[&[MINT_AUTH_SEED, &[auth_bump]]],
+    )?;
+
+    msg!("Created token metadata account");
+
+    IntroEvent::TokenMintInitialized(TokenMintInitialized {
+        mint: *token_mint_pda.key,
+        mint_authority: *mint_auth_pda.key,
+    })
+    .emit();
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::entrypoint::{deserialize, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE};
+
+    /// Lays out writable `(key, owner, lamports, data)` accounts like the BPF loader, since
+    /// `realloc` writes the new data length back into that input.
+    fn serialize(accounts: &[(Pubkey, Pubkey, u64, Vec<u8>)]) -> Vec<u64> {
+        let mut input = Vec::new();
+        input.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+        for (key, owner, lamports, data) in accounts {
+            input.extend_from_slice(&[u8::MAX, 0, 1, 0, 0, 0, 0, 0]);
+            input.extend_from_slice(key.as_ref());
+            input.extend_from_slice(owner.as_ref());
+            input.extend_from_slice(&lamports.to_le_bytes());
+            input.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            input.extend_from_slice(data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            let padding = (BPF_ALIGN_OF_U128 - input.len() % BPF_ALIGN_OF_U128) % BPF_ALIGN_OF_U128;
+            input.resize(input.len() + padding, 0);
+            input.extend_from_slice(&0u64.to_le_bytes());
+        }
+        input.extend_from_slice(&0u64.to_le_bytes());
+        input.extend_from_slice(crate::id().as_ref());
+
+        let mut aligned = vec![0u64; input.len().div_ceil(8)];
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                input.as_ptr(),
+                aligned.as_mut_ptr() as *mut u8,
+                input.len(),
+            );
+        }
+        aligned
+    }
+
+    #[test]
+    fn closed_accounts_are_empty_and_system_owned() {
+        let mut input = serialize(&[
+            (Pubkey::new_unique(), crate::id(), 1_000, vec![0xab; 16]),
+            (Pubkey::new_unique(), SYSTEM_PROGRAM_ID, 500, vec![]),
+        ]);
+        let (_program_id, accounts, _data) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+
+        assert_eq!(close_account(&accounts[0], &accounts[1]), Ok(1_000));
+        assert_eq!(accounts[0].lamports(), 0);
+        assert!(accounts[0].data_is_empty());
+        assert_eq!(*accounts[0].owner, SYSTEM_PROGRAM_ID);
+        assert_eq!(accounts[1].lamports(), 1_500);
+    }
+}
@@ -1,11 +1,114 @@
+use crate::error::StudentIntroError;
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     program_pack::{IsInitialized, Sealed},
     pubkey::Pubkey,
+    rent::Rent,
 };
+use std::ops::Range;
 
+pub const MAX_NAME_LEN: usize = 64;
+/// Message and reply limits are measured in characters rather than bytes so multibyte
+/// text gets the same allowance.
+pub const MAX_MESSAGE_CHARS: usize = 280;
+pub const MAX_REPLY_CHARS: usize = 300;
+pub const MAX_AVATAR_URI_LEN: usize = 128;
+/// Each chunk holds up to `MAX_MESSAGE_CHARS` more characters of the intro message.
+pub const MAX_MESSAGE_CHUNKS: u8 = 8;
+/// Upper bound on a serialized `StudentInfo`, checked on both create and update.
+pub const MAX_INTRO_ACCOUNT_LEN: usize = 1000;
+pub const MAX_REPLIES: u64 = 1000;
+/// Each reply in a batch is another account to create, so batches are kept small enough
+/// to fit the transaction's account and compute limits.
+pub const MAX_BATCH_REPLIES: usize = 5;
+pub const MAX_BLOCKED_KEYS: usize = 100;
+/// Every intro and reply is scanned for every term, so both bounds also cap that cost.
+pub const MAX_DENIED_WORDS: usize = 32;
+pub const MAX_DENIED_WORD_LEN: usize = 32;
+pub const RECENT_REPLIES: usize = 10;
+pub const REPLY_EDIT_HISTORY: usize = 3;
+/// Keeps a `ListReplies` page well inside the 1024 byte return data limit.
+pub const MAX_LIST_REPLIES: u8 = 20;
+
+/// One-byte tag stored after the magic bytes and version of every program account. It
+/// replaced the old string discriminators ("studentinfo", "counter", "reply"); accounts
+/// still in the string layout are rewritten by `Migrate`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountType {
+    Uninitialized,
+    StudentInfo,
+    ReplyCounter,
+    Reply,
+    LikeMarker,
+    ReactionMarker,
+    FlagMarker,
+    BlockList,
+    NameIndex,
+    ReplyIndex,
+    Config,
+    RatingMarker,
+    MessageChunk,
+    WordDenylist,
+    RewardMarker,
+}
+
+/// Serialized as a single byte, which also indexes `Reply::reactions`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReactionKind {
+    ThumbsUp,
+    Heart,
+    Laugh,
+    Celebrate,
+}
+
+pub const REACTION_KINDS: usize = 4;
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagReason {
+    Spam,
+    Harassment,
+    OffTopic,
+    Other,
+}
+
+impl TryFrom<u8> for FlagReason {
+    type Error = StudentIntroError;
+
+    fn try_from(reason: u8) -> Result<Self, Self::Error> {
+        match reason {
+            0 => Ok(Self::Spam),
+            1 => Ok(Self::Harassment),
+            2 => Ok(Self::OffTopic),
+            3 => Ok(Self::Other),
+            _ => Err(StudentIntroError::InvalidInput),
+        }
+    }
+}
+
+/// Bytes every program account starts with, ahead of its layout version and type tag.
+pub const ACCOUNT_MAGIC: [u8; 4] = *b"SINT";
+
+/// Layout version written after the magic bytes of every program account. Bumped with
+/// every change to an account layout, and `Migrate` rewrites accounts from each older
+/// version.
+pub const CURRENT_VERSION: u8 = 2;
+
+/// First versioned layout: the version byte came first, with no magic bytes ahead of it.
+pub const V1_VERSION: u8 = 1;
+
+pub trait TypedAccount {
+    const ACCOUNT_TYPE: AccountType;
+
+    fn version(&self) -> u8;
+
+    fn account_type(&self) -> AccountType;
+}
+
+/// Layouts used before accounts carried a version byte, when each one started with a
+/// borsh string discriminator. The `Migrate` instruction rewrites them to the current
+/// layout.
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct StudentInfo {
+pub struct LegacyStudentInfo {
     pub discriminator: String,
     pub is_initialized: bool,
     pub name: String,
@@ -13,20 +116,487 @@ pub struct StudentInfo {
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct ReplyCounter {
+pub struct LegacyReplyCounter {
     pub discriminator: String,
     pub is_initialized: bool,
     pub counter: u8,
 }
 
 #[derive(BorshSerialize, BorshDeserialize)]
-pub struct Reply {
+pub struct LegacyReply {
     pub discriminator: String,
     pub is_initialized: bool,
     pub studentinfo: Pubkey,
     pub reply: String,
 }
 
+/// Version 1 intro, which stored its rounded average rating and whether its creation
+/// reward was claimed.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StudentInfoV1 {
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub author: Pubkey,
+    pub name: String,
+    pub msg: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub rating: u8,
+    pub rating_count: u64,
+    pub rating_sum: u64,
+    pub avatar_uri: String,
+    pub message_chunks: u8,
+    pub rewards_claimed: bool,
+}
+
+/// Version 1 reply counter, closed together with its intro and so without a
+/// `first_index`.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ReplyCounterV1 {
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub counter: u64,
+    pub last_reply_at: i64,
+}
+
+/// Returns the account type of data still in the version 1 layout. Only intros and
+/// reply counters changed their body since; every other type only lacks the magic bytes.
+pub fn v1_account_type(data: &[u8]) -> Option<AccountType> {
+    match <(u8, AccountType)>::deserialize(&mut &data[..]) {
+        Ok((V1_VERSION, account_type)) if account_type != AccountType::Uninitialized => {
+            Some(account_type)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the account type of data still in a legacy string-discriminator layout.
+pub fn legacy_account_type(data: &[u8]) -> Option<AccountType> {
+    let has_discriminator = |discriminator: &str| {
+        let prefix = (discriminator.len() as u32).to_le_bytes();
+        data.len() >= 4 + discriminator.len()
+            && data[..4] == prefix
+            && &data[4..4 + discriminator.len()] == discriminator.as_bytes()
+    };
+    if has_discriminator("studentinfo") {
+        Some(AccountType::StudentInfo)
+    } else if has_discriminator("counter") {
+        Some(AccountType::ReplyCounter)
+    } else if has_discriminator("reply") {
+        Some(AccountType::Reply)
+    } else {
+        None
+    }
+}
+
+/// Reads the version and type tag that follow `ACCOUNT_MAGIC`. Data that was allocated
+/// but never written is all zeros and reads as version 0 and `Uninitialized`. Returns
+/// `None` for data that starts with anything else.
+pub fn account_header(data: &[u8]) -> Option<(u8, AccountType)> {
+    let (magic, version, account_type) =
+        <([u8; 4], u8, AccountType)>::deserialize(&mut &data[..]).ok()?;
+    let zeroed = magic == [0; 4] && version == 0 && account_type == AccountType::Uninitialized;
+    if magic == ACCOUNT_MAGIC || zeroed {
+        Some((version, account_type))
+    } else {
+        None
+    }
+}
+
+/// Classifies data owned by this program from its first bytes, for tooling that reads
+/// accounts without knowing their address. Current accounts start with `ACCOUNT_MAGIC`,
+/// version 1 ones with their version byte and legacy ones with the length of their
+/// string discriminator. Returns `None` for anything else, including accounts that were
+/// allocated but not yet written.
+pub fn identify_account(data: &[u8]) -> Option<AccountType> {
+    if let Some(account_type) = legacy_account_type(data).or_else(|| v1_account_type(data)) {
+        return Some(account_type);
+    }
+    match account_header(data) {
+        Some((_version, account_type)) if account_type != AccountType::Uninitialized => {
+            Some(account_type)
+        }
+        _ => None,
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct StudentInfo {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub author: Pubkey,
+    pub name: String,
+    pub msg: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub rating_count: u64,
+    pub rating_sum: u64,
+    /// Empty when the author has not set an avatar.
+    pub avatar_uri: String,
+    /// Number of `MessageChunk` accounts continuing `msg`.
+    pub message_chunks: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ReplyCounter {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    /// Number of replies created so far; its big-endian bytes seed the next reply PDA.
+    /// Deleting a reply does not decrement it, so a reply index is never reused.
+    /// Widened from `u8` to `u64`, so reply PDAs derived from the old one-byte seed are
+    /// no longer reachable through this program.
+    pub counter: u64,
+    /// Index of the first reply to the intro now at this address. A transferred intro
+    /// leaves its counter behind, so an intro recreated at the same address carries on
+    /// from the old count and replies below this index belong to the one that moved.
+    pub first_index: u64,
+    /// Unix timestamp of the latest reply made through this counter, 0 before the first.
+    pub last_reply_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Reply {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub studentinfo: Pubkey,
+    pub replier: Pubkey,
+    /// Reply this one answers; `None` for top-level replies to the intro.
+    pub parent: Option<Pubkey>,
+    pub reply: String,
+    pub likes: u64,
+    pub reactions: [u64; REACTION_KINDS],
+    pub flags: u16,
+    /// Hidden by its author or the admin. The account stays open so reply indices
+    /// keep deriving the same PDAs. `DeleteReply` also sets it when it leaves the
+    /// account as an emptied, uninitialized tombstone.
+    pub deleted: bool,
+    /// The last `REPLY_EDIT_HISTORY` edits, oldest first. Space for all of them is
+    /// reserved when the reply is created.
+    pub edits: Vec<ReplyEdit>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplyEdit {
+    /// sha256 of the text the edit replaced.
+    pub previous_hash: [u8; 32],
+    pub edited_at: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LikeMarker {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ReactionMarker {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+}
+
+/// Maps a normalized intro name to the intro that holds it, so clients can look an
+/// intro up by name and two intros cannot share one.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct NameIndex {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub intro: Pubkey,
+}
+
+/// The newest `RECENT_REPLIES` top-level replies of an intro, oldest first, so clients
+/// can page from the newest without walking every index. Deleted replies are not
+/// removed; their accounts are simply closed.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ReplyIndex {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub recent: Vec<Pubkey>,
+}
+
+/// Allocated for `MAX_BLOCKED_KEYS` up front, so adding a key never reallocs.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct BlockList {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub blocked: Vec<Pubkey>,
+}
+
+/// Lowercased terms that names, messages and replies may not contain. Allocated for
+/// `MAX_DENIED_WORDS` terms up front, so adding one never reallocs.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct WordDenylist {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub terms: Vec<String>,
+}
+
+/// Admin-tunable limits and rewards, plus the emergency pause switch. Until it is
+/// created the handlers use the compile-time constants.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Config {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub max_name_len: u32,
+    /// In characters, like `MAX_MESSAGE_CHARS`.
+    pub max_message_len: u32,
+    pub reply_reward: u64,
+    pub intro_reward: u64,
+    pub paused: bool,
+    /// Minimum number of seconds between two replies to the same intro.
+    pub min_reply_interval: i64,
+}
+
+/// Records that a wallet rated an intro, so each rater is counted once.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RatingMarker {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub rating: u8,
+}
+
+/// Overflow text for an intro message. Clients append the chunks' text to
+/// `StudentInfo::msg` in index order.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct MessageChunk {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub intro: Pubkey,
+    pub index: u8,
+    pub text: String,
+}
+
+/// Records that a reward was minted. It is never closed, so the same reward cannot be
+/// claimed twice by deleting and recreating what earned it.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct RewardMarker {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct FlagMarker {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub discriminator: AccountType,
+    pub is_initialized: bool,
+    pub reason: FlagReason,
+}
+
+/// Sums account size components, failing instead of wrapping on crafted lengths.
+fn checked_size(parts: &[usize]) -> Result<usize, StudentIntroError> {
+    parts
+        .iter()
+        .try_fold(0usize, |total, part| total.checked_add(*part))
+        .ok_or(StudentIntroError::InvalidDataLength)
+}
+
+/// Decodes account data fetched off-chain. Unlike the processor's loader it also rejects
+/// accounts that were created but never initialized.
+fn from_account_data<T: BorshDeserialize + TypedAccount>(
+    data: &[u8],
+) -> Result<T, StudentIntroError> {
+    if legacy_account_type(data).is_some() || v1_account_type(data).is_some() {
+        return Err(StudentIntroError::UnsupportedAccountVersion);
+    }
+    let (version, account_type) =
+        account_header(data).ok_or(StudentIntroError::IncorrectAccountError)?;
+    if account_type != T::ACCOUNT_TYPE {
+        return Err(StudentIntroError::IncorrectAccountError);
+    }
+    if version != CURRENT_VERSION {
+        return Err(StudentIntroError::UnsupportedAccountVersion);
+    }
+    // Accounts can be longer than their contents, so trailing bytes are allowed
+    T::deserialize(&mut &data[..]).map_err(|_| StudentIntroError::DeserializationFailed)
+}
+
+impl StudentInfo {
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, StudentIntroError> {
+        from_account_data(data)
+    }
+
+    pub fn get_account_size(
+        name: &str,
+        message: &str,
+        avatar_uri: &str,
+    ) -> Result<usize, StudentIntroError> {
+        checked_size(&[
+            4 + 1 + 1 + 1 + 32,
+            4,
+            name.len(),
+            4,
+            message.len(),
+            8 + 8 + 8 + 8,
+            4,
+            avatar_uri.len(),
+            1,
+        ])
+    }
+
+    /// `rating_sum / rating_count` rounded half up, or 0 before the first rating.
+    pub fn average_rating(&self) -> u8 {
+        if self.rating_count == 0 {
+            return 0;
+        }
+        let sum = u128::from(self.rating_sum);
+        let count = u128::from(self.rating_count);
+        // Ratings are at most 5, so the average fits in a u8
+        ((sum * 2 + count) / (count * 2)) as u8
+    }
+}
+
+/// Rent for the accounts `InitUserInput` creates (intro, reply counter, name index and the
+/// reward marker of a wallet's first intro) under the default rent parameters. The
+/// creation fee and the ATA are not included.
+pub fn estimate_creation_rent(name: &str, message: &str) -> Result<u64, StudentIntroError> {
+    estimate_creation_rent_with(&Rent::default(), name, message)
+}
+
+pub fn estimate_creation_rent_with(
+    rent: &Rent,
+    name: &str,
+    message: &str,
+) -> Result<u64, StudentIntroError> {
+    let intro_len = StudentInfo::get_account_size(name, message, "")?;
+    [
+        intro_len,
+        ReplyCounter::SIZE,
+        NameIndex::SIZE,
+        RewardMarker::SIZE,
+    ]
+    .iter()
+    .try_fold(0u64, |total, len| {
+        total.checked_add(rent.minimum_balance(*len))
+    })
+    .ok_or(StudentIntroError::Overflow)
+}
+
+impl ReplyCounter {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + 8 + 8 + 8;
+
+    /// Replies made to the current intro, including deleted ones.
+    pub fn reply_count(&self) -> u64 {
+        self.counter.saturating_sub(self.first_index)
+    }
+
+    /// Reply indices of the current intro, in order.
+    pub fn indices(&self) -> Range<u64> {
+        self.first_index..self.counter
+    }
+
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, StudentIntroError> {
+        from_account_data(data)
+    }
+}
+
+impl Reply {
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, StudentIntroError> {
+        from_account_data(data)
+    }
+
+    pub fn get_account_size(
+        reply: &str,
+        parent: Option<&Pubkey>,
+    ) -> Result<usize, StudentIntroError> {
+        let parent_len = if parent.is_some() { 1 + 32 } else { 1 };
+        checked_size(&[
+            4 + 1 + 1 + 1 + 32 + 32,
+            parent_len,
+            4,
+            reply.len(),
+            8 + 8 * REACTION_KINDS + 2 + 1,
+            4 + (32 + 8) * REPLY_EDIT_HISTORY,
+        ])
+    }
+}
+
+impl LikeMarker {
+    pub const SIZE: usize = 4 + 1 + 1 + 1;
+}
+
+impl ReactionMarker {
+    pub const SIZE: usize = 4 + 1 + 1 + 1;
+}
+
+impl FlagMarker {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + 1;
+}
+
+impl RatingMarker {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + 1;
+}
+
+impl RewardMarker {
+    pub const SIZE: usize = 4 + 1 + 1 + 1;
+}
+
+impl MessageChunk {
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, StudentIntroError> {
+        from_account_data(data)
+    }
+
+    pub fn get_account_size(text: &str) -> Result<usize, StudentIntroError> {
+        checked_size(&[4 + 1 + 1 + 1 + 32 + 1, 4, text.len()])
+    }
+}
+
+impl NameIndex {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + 32;
+}
+
+impl ReplyIndex {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + (4 + 32 * RECENT_REPLIES);
+}
+
+impl BlockList {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + (4 + 32 * MAX_BLOCKED_KEYS);
+}
+
+impl WordDenylist {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + (4 + (4 + MAX_DENIED_WORD_LEN) * MAX_DENIED_WORDS);
+
+    /// The first term found in `text`, ignoring case.
+    pub fn find_term(&self, text: &str) -> Option<&str> {
+        let text = text.to_lowercase();
+        self.terms
+            .iter()
+            .map(String::as_str)
+            .find(|term| text.contains(term))
+    }
+}
+
+impl Config {
+    pub const SIZE: usize = 4 + 1 + 1 + 1 + 4 + 4 + 8 + 8 + 1 + 8;
+}
+
 impl Sealed for StudentInfo {}
 
 impl IsInitialized for StudentInfo {
@@ -46,3 +616,681 @@ impl IsInitialized for Reply {
         self.is_initialized
     }
 }
+
+impl IsInitialized for LikeMarker {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for ReactionMarker {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for FlagMarker {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for RatingMarker {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for RewardMarker {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for MessageChunk {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for NameIndex {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for ReplyIndex {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for BlockList {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for WordDenylist {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for Config {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl TypedAccount for StudentInfo {
+    const ACCOUNT_TYPE: AccountType = AccountType::StudentInfo;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for ReplyCounter {
+    const ACCOUNT_TYPE: AccountType = AccountType::ReplyCounter;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for Reply {
+    const ACCOUNT_TYPE: AccountType = AccountType::Reply;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for LikeMarker {
+    const ACCOUNT_TYPE: AccountType = AccountType::LikeMarker;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for ReactionMarker {
+    const ACCOUNT_TYPE: AccountType = AccountType::ReactionMarker;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for FlagMarker {
+    const ACCOUNT_TYPE: AccountType = AccountType::FlagMarker;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for RatingMarker {
+    const ACCOUNT_TYPE: AccountType = AccountType::RatingMarker;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for RewardMarker {
+    const ACCOUNT_TYPE: AccountType = AccountType::RewardMarker;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for MessageChunk {
+    const ACCOUNT_TYPE: AccountType = AccountType::MessageChunk;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for BlockList {
+    const ACCOUNT_TYPE: AccountType = AccountType::BlockList;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for NameIndex {
+    const ACCOUNT_TYPE: AccountType = AccountType::NameIndex;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for ReplyIndex {
+    const ACCOUNT_TYPE: AccountType = AccountType::ReplyIndex;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for WordDenylist {
+    const ACCOUNT_TYPE: AccountType = AccountType::WordDenylist;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+impl TypedAccount for Config {
+    const ACCOUNT_TYPE: AccountType = AccountType::Config;
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn account_type(&self) -> AccountType {
+        self.discriminator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT_TYPES: [AccountType; 14] = [
+        AccountType::StudentInfo,
+        AccountType::ReplyCounter,
+        AccountType::Reply,
+        AccountType::LikeMarker,
+        AccountType::ReactionMarker,
+        AccountType::FlagMarker,
+        AccountType::BlockList,
+        AccountType::NameIndex,
+        AccountType::ReplyIndex,
+        AccountType::Config,
+        AccountType::RatingMarker,
+        AccountType::MessageChunk,
+        AccountType::WordDenylist,
+        AccountType::RewardMarker,
+    ];
+
+    fn header(account_type: AccountType) -> Vec<u8> {
+        let mut data = ACCOUNT_MAGIC.to_vec();
+        data.push(CURRENT_VERSION);
+        data.extend(account_type.try_to_vec().unwrap());
+        // Body bytes are never read when classifying
+        data.extend([0; 32]);
+        data
+    }
+
+    #[test]
+    fn identify_account_reads_every_type_tag() {
+        for account_type in ACCOUNT_TYPES {
+            assert_eq!(identify_account(&header(account_type)), Some(account_type));
+        }
+    }
+
+    #[test]
+    fn account_types_round_trip_as_one_byte() {
+        assert_eq!(AccountType::Uninitialized.try_to_vec().unwrap(), [0]);
+        for (tag, account_type) in (1..).zip(ACCOUNT_TYPES) {
+            let data = account_type.try_to_vec().unwrap();
+            assert_eq!(data, [tag]);
+            assert_eq!(AccountType::try_from_slice(&data).unwrap(), account_type);
+        }
+        assert!(AccountType::try_from_slice(&[ACCOUNT_TYPES.len() as u8 + 1]).is_err());
+    }
+
+    #[test]
+    fn identify_account_reads_serialized_accounts() {
+        let intro = StudentInfo {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::StudentInfo,
+            is_initialized: true,
+            author: Pubkey::new_unique(),
+            name: "name".to_string(),
+            msg: "message".to_string(),
+            created_at: 1,
+            updated_at: 1,
+            rating_count: 0,
+            rating_sum: 0,
+            avatar_uri: String::new(),
+            message_chunks: 0,
+        };
+        let counter = ReplyCounter {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized: true,
+            counter: 3,
+            first_index: 1,
+            last_reply_at: 0,
+        };
+        let marker = RewardMarker {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::RewardMarker,
+            is_initialized: true,
+        };
+
+        assert_eq!(
+            identify_account(&intro.try_to_vec().unwrap()),
+            Some(AccountType::StudentInfo)
+        );
+        assert_eq!(
+            identify_account(&counter.try_to_vec().unwrap()),
+            Some(AccountType::ReplyCounter)
+        );
+        assert_eq!(
+            identify_account(&marker.try_to_vec().unwrap()),
+            Some(AccountType::RewardMarker)
+        );
+    }
+
+    #[test]
+    fn identify_account_reads_legacy_layouts() {
+        let intro = LegacyStudentInfo {
+            discriminator: "studentinfo".to_string(),
+            is_initialized: true,
+            name: "name".to_string(),
+            msg: "message".to_string(),
+        };
+        let counter = LegacyReplyCounter {
+            discriminator: "counter".to_string(),
+            is_initialized: true,
+            counter: 2,
+        };
+        let reply = LegacyReply {
+            discriminator: "reply".to_string(),
+            is_initialized: true,
+            studentinfo: Pubkey::new_unique(),
+            reply: "reply".to_string(),
+        };
+
+        assert_eq!(
+            identify_account(&intro.try_to_vec().unwrap()),
+            Some(AccountType::StudentInfo)
+        );
+        assert_eq!(
+            identify_account(&counter.try_to_vec().unwrap()),
+            Some(AccountType::ReplyCounter)
+        );
+        assert_eq!(
+            identify_account(&reply.try_to_vec().unwrap()),
+            Some(AccountType::Reply)
+        );
+    }
+
+    #[test]
+    fn identify_account_reads_version_1_layouts() {
+        let counter = ReplyCounterV1 {
+            version: V1_VERSION,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized: true,
+            counter: 4,
+            last_reply_at: 0,
+        };
+        let data = counter.try_to_vec().unwrap();
+
+        assert_eq!(v1_account_type(&data), Some(AccountType::ReplyCounter));
+        assert_eq!(identify_account(&data), Some(AccountType::ReplyCounter));
+        assert_eq!(account_header(&data), None);
+        assert!(matches!(
+            ReplyCounter::try_from_account_data(&data),
+            Err(StudentIntroError::UnsupportedAccountVersion)
+        ));
+    }
+
+    #[test]
+    fn current_accounts_are_not_version_1() {
+        for account_type in ACCOUNT_TYPES {
+            assert_eq!(v1_account_type(&header(account_type)), None);
+        }
+    }
+
+    #[test]
+    fn identify_account_rejects_unknown_data() {
+        // Allocated but never written
+        assert_eq!(identify_account(&[0; 64]), None);
+        assert_eq!(identify_account(&[]), None);
+
+        let mut wrong_magic = header(AccountType::StudentInfo);
+        wrong_magic[0] ^= 0xff;
+        assert_eq!(identify_account(&wrong_magic), None);
+
+        let mut unknown_tag = header(AccountType::StudentInfo);
+        unknown_tag[5] = u8::MAX;
+        assert_eq!(identify_account(&unknown_tag), None);
+    }
+
+    fn size_of<T: BorshSerialize>(account: &T) -> usize {
+        account.try_to_vec().unwrap().len()
+    }
+
+    #[test]
+    fn fixed_sizes_match_borsh() {
+        let (magic, version, is_initialized) = (ACCOUNT_MAGIC, CURRENT_VERSION, true);
+        let counter = ReplyCounter {
+            magic,
+            version,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized,
+            counter: u64::MAX,
+            first_index: u64::MAX,
+            last_reply_at: i64::MAX,
+        };
+        assert_eq!(size_of(&counter), ReplyCounter::SIZE);
+
+        let like = LikeMarker {
+            magic,
+            version,
+            discriminator: AccountType::LikeMarker,
+            is_initialized,
+        };
+        assert_eq!(size_of(&like), LikeMarker::SIZE);
+        let reaction = ReactionMarker {
+            magic,
+            version,
+            discriminator: AccountType::ReactionMarker,
+            is_initialized,
+        };
+        assert_eq!(size_of(&reaction), ReactionMarker::SIZE);
+        let reward = RewardMarker {
+            magic,
+            version,
+            discriminator: AccountType::RewardMarker,
+            is_initialized,
+        };
+        assert_eq!(size_of(&reward), RewardMarker::SIZE);
+        let flag = FlagMarker {
+            magic,
+            version,
+            discriminator: AccountType::FlagMarker,
+            is_initialized,
+            reason: FlagReason::Other,
+        };
+        assert_eq!(size_of(&flag), FlagMarker::SIZE);
+        let rating = RatingMarker {
+            magic,
+            version,
+            discriminator: AccountType::RatingMarker,
+            is_initialized,
+            rating: 5,
+        };
+        assert_eq!(size_of(&rating), RatingMarker::SIZE);
+        let name_index = NameIndex {
+            magic,
+            version,
+            discriminator: AccountType::NameIndex,
+            is_initialized,
+            intro: Pubkey::new_unique(),
+        };
+        assert_eq!(size_of(&name_index), NameIndex::SIZE);
+        let config = Config {
+            magic,
+            version,
+            discriminator: AccountType::Config,
+            is_initialized,
+            max_name_len: u32::MAX,
+            max_message_len: u32::MAX,
+            reply_reward: u64::MAX,
+            intro_reward: u64::MAX,
+            paused: true,
+            min_reply_interval: i64::MAX,
+        };
+        assert_eq!(size_of(&config), Config::SIZE);
+    }
+
+    #[test]
+    fn list_sizes_match_borsh_when_full() {
+        let keys = |count| (0..count).map(|_| Pubkey::new_unique()).collect::<Vec<_>>();
+        let reply_index = ReplyIndex {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::ReplyIndex,
+            is_initialized: true,
+            recent: keys(RECENT_REPLIES),
+        };
+        assert_eq!(size_of(&reply_index), ReplyIndex::SIZE);
+        let block_list = BlockList {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::BlockList,
+            is_initialized: true,
+            blocked: keys(MAX_BLOCKED_KEYS),
+        };
+        assert_eq!(size_of(&block_list), BlockList::SIZE);
+        let denylist = WordDenylist {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::WordDenylist,
+            is_initialized: true,
+            terms: vec!["x".repeat(MAX_DENIED_WORD_LEN); MAX_DENIED_WORDS],
+        };
+        assert_eq!(size_of(&denylist), WordDenylist::SIZE);
+    }
+
+    #[test]
+    fn variable_sizes_match_borsh() {
+        let intro = StudentInfo {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::StudentInfo,
+            is_initialized: true,
+            author: Pubkey::new_unique(),
+            name: "Alice".to_string(),
+            msg: "Hello, I am Alice".to_string(),
+            created_at: 1,
+            updated_at: 2,
+            rating_count: 3,
+            rating_sum: 12,
+            avatar_uri: "https://example.com/alice.png".to_string(),
+            message_chunks: 1,
+        };
+        assert_eq!(
+            size_of(&intro),
+            StudentInfo::get_account_size(&intro.name, &intro.msg, &intro.avatar_uri).unwrap()
+        );
+        // Strings are sized by their UTF-8 bytes, not their characters
+        let intro = StudentInfo {
+            name: "Zoë".to_string(),
+            msg: "こんにちは".to_string(),
+            avatar_uri: String::new(),
+            ..intro
+        };
+        assert_eq!(
+            size_of(&intro),
+            StudentInfo::get_account_size(&intro.name, &intro.msg, &intro.avatar_uri).unwrap()
+        );
+
+        // Space for the whole edit history is reserved up front
+        let edit = ReplyEdit {
+            previous_hash: [7; 32],
+            edited_at: 1,
+        };
+        for parent in [None, Some(Pubkey::new_unique())] {
+            let reply = Reply {
+                magic: ACCOUNT_MAGIC,
+                version: CURRENT_VERSION,
+                discriminator: AccountType::Reply,
+                is_initialized: true,
+                studentinfo: Pubkey::new_unique(),
+                replier: Pubkey::new_unique(),
+                parent,
+                reply: "Welcome, Alice".to_string(),
+                likes: 1,
+                reactions: [1; REACTION_KINDS],
+                flags: 1,
+                deleted: false,
+                edits: vec![edit; REPLY_EDIT_HISTORY],
+            };
+            assert_eq!(
+                size_of(&reply),
+                Reply::get_account_size(&reply.reply, reply.parent.as_ref()).unwrap()
+            );
+        }
+
+        let chunk = MessageChunk {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::MessageChunk,
+            is_initialized: true,
+            intro: Pubkey::new_unique(),
+            index: 0,
+            text: "and more about me".to_string(),
+        };
+        assert_eq!(
+            size_of(&chunk),
+            MessageChunk::get_account_size(&chunk.text).unwrap()
+        );
+    }
+
+    #[test]
+    fn account_data_round_trips_through_the_typed_readers() {
+        let intro = StudentInfo {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::StudentInfo,
+            is_initialized: true,
+            author: Pubkey::new_unique(),
+            name: "Alice".to_string(),
+            msg: "Hello, I am Alice".to_string(),
+            created_at: 1,
+            updated_at: 2,
+            rating_count: 0,
+            rating_sum: 0,
+            avatar_uri: String::new(),
+            message_chunks: 0,
+        };
+        let reply = Reply {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::Reply,
+            is_initialized: true,
+            studentinfo: Pubkey::new_unique(),
+            replier: Pubkey::new_unique(),
+            parent: None,
+            reply: "Welcome, Alice".to_string(),
+            likes: 2,
+            reactions: [0; REACTION_KINDS],
+            flags: 0,
+            deleted: false,
+            edits: Vec::new(),
+        };
+        let counter = ReplyCounter {
+            magic: ACCOUNT_MAGIC,
+            version: CURRENT_VERSION,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized: true,
+            counter: 3,
+            first_index: 1,
+            last_reply_at: 4,
+        };
+        // Accounts are allocated with room to spare
+        let padded = |bytes: Vec<u8>| [bytes, vec![0; 16]].concat();
+        let intro_data = padded(intro.try_to_vec().unwrap());
+        let reply_data = padded(reply.try_to_vec().unwrap());
+        let counter_data = padded(counter.try_to_vec().unwrap());
+
+        let decoded = StudentInfo::try_from_account_data(&intro_data).unwrap();
+        assert_eq!(decoded.try_to_vec().unwrap(), intro.try_to_vec().unwrap());
+        let decoded = Reply::try_from_account_data(&reply_data).unwrap();
+        assert_eq!(decoded.try_to_vec().unwrap(), reply.try_to_vec().unwrap());
+        let decoded = ReplyCounter::try_from_account_data(&counter_data).unwrap();
+        assert_eq!(decoded.try_to_vec().unwrap(), counter.try_to_vec().unwrap());
+
+        assert!(matches!(
+            Reply::try_from_account_data(&intro_data),
+            Err(StudentIntroError::IncorrectAccountError)
+        ));
+        assert!(matches!(
+            StudentInfo::try_from_account_data(&counter_data),
+            Err(StudentIntroError::IncorrectAccountError)
+        ));
+        assert!(matches!(
+            ReplyCounter::try_from_account_data(&[0; 8]),
+            Err(StudentIntroError::IncorrectAccountError)
+        ));
+    }
+
+    #[test]
+    fn sizes_past_usize_fail_instead_of_wrapping() {
+        assert_eq!(
+            checked_size(&[usize::MAX, 1]),
+            Err(StudentIntroError::InvalidDataLength)
+        );
+        assert_eq!(checked_size(&[4, usize::MAX - 8, 4]), Ok(usize::MAX));
+        assert_eq!(checked_size(&[]), Ok(0));
+    }
+
+    #[test]
+    fn account_header_reads_zeroed_data_as_uninitialized() {
+        assert_eq!(
+            account_header(&[0; 16]),
+            Some((0, AccountType::Uninitialized))
+        );
+        assert_eq!(
+            account_header(&header(AccountType::Reply)),
+            Some((CURRENT_VERSION, AccountType::Reply))
+        );
+    }
+}
@@ -0,0 +1,485 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{runtime::Runtime, TOKEN};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use solana_student_intros_program::{
+    metadata::{TOKEN_METADATA_PROGRAM_ID, TOKEN_NAME, TOKEN_SYMBOL, TOKEN_URI},
+    pda::freeze_auth_pda,
+    prelude::*,
+    processor::{
+        ADMIN_PUBKEY, DEFAULT_INTRO_REWARD, EDIT_COOLDOWN_SECONDS, INTRO_CREATION_FEE,
+        MIN_REPLY_INTERVAL_SECONDS, REPLY_REWARD, REWARD_DECIMALS,
+    },
+    state::{MAX_MESSAGE_CHARS, MAX_NAME_LEN},
+};
+use spl_token::state::Mint;
+
+fn block_list(tag: InstructionTag, key: &Pubkey) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(ADMIN_PUBKEY, true),
+        AccountMeta::new(block_list_pda(&id()).0, false),
+    ];
+    if tag == InstructionTag::AddToBlockList {
+        accounts.push(common::system_meta());
+    }
+    accounts.push(common::config_meta());
+    common::instruction(tag, key, accounts)
+}
+
+fn denied_word(tag: InstructionTag, term: &str) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(ADMIN_PUBKEY, true),
+        AccountMeta::new(word_denylist_pda(&id()).0, false),
+    ];
+    if tag == InstructionTag::AddDeniedWord {
+        accounts.push(common::system_meta());
+    }
+    accounts.push(common::config_meta());
+    common::instruction(tag, &term.to_string(), accounts)
+}
+
+fn withdraw(authority: &Pubkey, lamports: u64) -> Instruction {
+    common::instruction(
+        InstructionTag::WithdrawTreasury,
+        &lamports,
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(treasury_pda(&id()).0, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn set_freeze_authority(admin: &Pubkey, new_authority: Option<Pubkey>) -> Instruction {
+    common::instruction(
+        InstructionTag::SetFreezeAuthority,
+        &new_authority,
+        vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new(common::token_mint(), false),
+            AccountMeta::new_readonly(freeze_auth_pda(&id()).0, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn set_config(
+    max_name_len: u32,
+    max_message_len: u32,
+    reply_reward: u64,
+    intro_reward: u64,
+    min_reply_interval: i64,
+) -> Instruction {
+    common::instruction(
+        InstructionTag::SetConfig,
+        &(
+            max_name_len,
+            max_message_len,
+            reply_reward,
+            intro_reward,
+            min_reply_interval,
+        ),
+        vec![
+            AccountMeta::new(ADMIN_PUBKEY, true),
+            AccountMeta::new(config_pda(&id()).0, false),
+            common::system_meta(),
+        ],
+    )
+}
+
+fn set_paused(paused: bool) -> Instruction {
+    common::instruction(
+        InstructionTag::SetPaused,
+        &paused,
+        vec![
+            AccountMeta::new_readonly(ADMIN_PUBKEY, true),
+            AccountMeta::new(config_pda(&id()).0, false),
+        ],
+    )
+}
+
+fn mint(runtime: &Runtime) -> Mint {
+    Mint::unpack(&runtime.account(&common::token_mint()).unwrap().data).unwrap()
+}
+
+#[test]
+fn mint_is_initialized_once() {
+    let mut runtime = Runtime::with_mint();
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::initialize_mint(
+            &id(),
+            &ADMIN_PUBKEY,
+            REWARD_DECIMALS
+        )),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+}
+
+#[test]
+fn only_the_admin_initializes_the_mint() {
+    let mut runtime = Runtime::new();
+    let user = Pubkey::new_unique();
+    runtime.airdrop(&user, common::STARTING_LAMPORTS);
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::initialize_mint(
+            &id(),
+            &user,
+            REWARD_DECIMALS
+        )),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert!(runtime.account(&common::token_mint()).is_none());
+}
+
+#[test]
+fn mint_takes_up_to_nine_decimals() {
+    let mut runtime = Runtime::new();
+    runtime.airdrop(&ADMIN_PUBKEY, common::STARTING_LAMPORTS);
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::initialize_mint(
+            &id(),
+            &ADMIN_PUBKEY,
+            REWARD_DECIMALS + 1
+        )),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    runtime
+        .process(&IntroInstruction::initialize_mint(&id(), &ADMIN_PUBKEY, 6))
+        .unwrap();
+    assert_eq!(mint(&runtime).decimals, 6);
+
+    // Rewards keep their value in whole tokens
+    let alice = runtime.create_user();
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    assert_eq!(runtime.token_balance(&alice), DEFAULT_INTRO_REWARD / 1_000);
+}
+
+#[test]
+fn mint_gets_a_metadata_account() {
+    let runtime = Runtime::with_mint();
+
+    let metadata = runtime
+        .account(&token_metadata_pda(&common::token_mint()).0)
+        .unwrap();
+    assert_eq!(metadata.owner, TOKEN_METADATA_PROGRAM_ID);
+    let (name, symbol, uri) =
+        <(String, String, String)>::deserialize(&mut &metadata.data[..]).unwrap();
+    assert_eq!(name, TOKEN_NAME);
+    assert_eq!(symbol, TOKEN_SYMBOL);
+    assert_eq!(uri, TOKEN_URI);
+}
+
+#[test]
+fn blocked_keys_cannot_post_until_unblocked() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let dave = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    runtime
+        .process(&block_list(InstructionTag::AddToBlockList, &dave))
+        .unwrap();
+    assert_eq!(
+        runtime.get::<BlockList>(&block_list_pda(&id()).0).blocked,
+        vec![dave]
+    );
+    let create = IntroInstruction::init_user_input(
+        &id(),
+        &dave,
+        "Dave".to_string(),
+        "Hello, I am Dave".to_string(),
+        None,
+        None,
+    );
+    let reply = IntroInstruction::add_reply(&id(), &dave, &intro.intro, 0, "Hi".to_string());
+    for instruction in [&create, &reply] {
+        assert_eq!(
+            runtime.process(instruction),
+            Err(StudentIntroError::Blocked.into())
+        );
+    }
+    assert!(runtime.account(&user_intro_pda(&dave, &id()).0).is_none());
+
+    runtime
+        .process(&block_list(InstructionTag::RemoveFromBlockList, &dave))
+        .unwrap();
+    runtime.process(&create).unwrap();
+    runtime.process(&reply).unwrap();
+}
+
+#[test]
+fn denied_words_are_rejected_until_removed() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let dave = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&dave, "Dave", "Hello, I am Dave");
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS.max(MIN_REPLY_INTERVAL_SECONDS));
+
+    runtime
+        .process(&denied_word(InstructionTag::AddDeniedWord, "Spam"))
+        .unwrap();
+    assert_eq!(
+        runtime
+            .get::<WordDenylist>(&word_denylist_pda(&id()).0)
+            .terms,
+        vec!["spam".to_string()]
+    );
+    let update = IntroInstruction::update_student_intro(
+        &id(),
+        &alice,
+        "Alice",
+        "Alice".to_string(),
+        "No SPAM please".to_string(),
+        None,
+    );
+    let reply =
+        IntroInstruction::add_reply(&id(), &dave, &intro.intro, 0, "Hi, spammer".to_string());
+    for instruction in [&update, &reply] {
+        assert_eq!(
+            runtime.process(instruction),
+            Err(StudentIntroError::InvalidInput.into())
+        );
+    }
+    assert_eq!(
+        runtime.get::<StudentInfo>(&intro.intro).msg,
+        "Hello, I am Alice"
+    );
+
+    runtime
+        .process(&denied_word(InstructionTag::RemoveDeniedWord, "spam"))
+        .unwrap();
+    runtime.process(&update).unwrap();
+    runtime.process(&reply).unwrap();
+}
+
+#[test]
+fn only_the_admin_manages_the_block_list() {
+    let mut runtime = Runtime::with_mint();
+    let dave = runtime.create_user();
+
+    let mut instruction = block_list(InstructionTag::AddToBlockList, &dave);
+    instruction.accounts[0].pubkey = dave;
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert!(runtime.account(&block_list_pda(&id()).0).is_none());
+}
+
+#[test]
+fn intro_fees_collect_for_the_admin_to_withdraw() {
+    let mut runtime = Runtime::with_mint();
+    let treasury = treasury_pda(&id()).0;
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    assert_eq!(runtime.lamports(&treasury), INTRO_CREATION_FEE);
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    assert_eq!(runtime.lamports(&treasury), 2 * INTRO_CREATION_FEE);
+
+    assert_eq!(
+        runtime.process(&withdraw(&alice, INTRO_CREATION_FEE)),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(runtime.lamports(&treasury), 2 * INTRO_CREATION_FEE);
+
+    let admin_lamports = runtime.lamports(&ADMIN_PUBKEY);
+    runtime
+        .process(&withdraw(&ADMIN_PUBKEY, INTRO_CREATION_FEE))
+        .unwrap();
+    assert_eq!(runtime.lamports(&treasury), INTRO_CREATION_FEE);
+    assert_eq!(
+        runtime.lamports(&ADMIN_PUBKEY),
+        admin_lamports + INTRO_CREATION_FEE
+    );
+}
+
+#[test]
+fn withdrawals_keep_the_treasury_rent_exempt() {
+    let mut runtime = Runtime::with_mint();
+    let treasury = treasury_pda(&id()).0;
+    let alice = runtime.create_user();
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+
+    for lamports in [INTRO_CREATION_FEE - 1, INTRO_CREATION_FEE + 1] {
+        assert_eq!(
+            runtime.process(&withdraw(&ADMIN_PUBKEY, lamports)),
+            Err(ProgramError::InsufficientFunds)
+        );
+    }
+    assert_eq!(runtime.lamports(&treasury), INTRO_CREATION_FEE);
+
+    // Emptying the treasury entirely is allowed
+    runtime
+        .process(&withdraw(&ADMIN_PUBKEY, INTRO_CREATION_FEE))
+        .unwrap();
+    assert_eq!(runtime.lamports(&treasury), 0);
+}
+
+#[test]
+fn freeze_authority_follows_the_feature() {
+    let mut runtime = Runtime::with_mint();
+    let expected = if cfg!(feature = "freeze-authority") {
+        Some(freeze_auth_pda(&id()).0)
+    } else {
+        None
+    };
+    assert_eq!(mint(&runtime).freeze_authority, expected.into());
+
+    let dave = runtime.create_user();
+    assert_eq!(
+        runtime.process(&set_freeze_authority(&dave, Some(dave))),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(mint(&runtime).freeze_authority, expected.into());
+}
+
+#[cfg(feature = "freeze-authority")]
+#[test]
+fn admin_hands_over_the_freeze_authority() {
+    let mut runtime = Runtime::with_mint();
+    let moderator = Pubkey::new_unique();
+
+    runtime
+        .process(&set_freeze_authority(&ADMIN_PUBKEY, Some(moderator)))
+        .unwrap();
+
+    assert_eq!(mint(&runtime).freeze_authority, Some(moderator).into());
+}
+
+#[test]
+fn config_limits_replace_the_defaults() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    runtime
+        .process(&set_config(8, 20, 2 * TOKEN, 3 * TOKEN, 1))
+        .unwrap();
+    let create = |author: &Pubkey, name: &str, message: &str| {
+        IntroInstruction::init_user_input(
+            &id(),
+            author,
+            name.to_string(),
+            message.to_string(),
+            None,
+            None,
+        )
+    };
+
+    assert_eq!(
+        runtime.process(&create(&alice, "Alexandra", "Hello")),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    assert_eq!(
+        runtime.process(&create(&alice, "Alice", "Hello, I am Alice!!!!")),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    runtime
+        .process(&create(&alice, "Alice", "Hello, I am Alice!!!"))
+        .unwrap();
+    assert_eq!(runtime.token_balance(&alice), 3 * TOKEN);
+
+    // A one second interval replaces the default one
+    runtime
+        .process(&create(&bob, "Bob", "Hello, I am Bob"))
+        .unwrap();
+    let intro = common::IntroPdas::new(&alice, "Alice");
+    for index in 0..2 {
+        runtime.advance_clock(1);
+        runtime
+            .process(&IntroInstruction::add_reply(
+                &id(),
+                &bob,
+                &intro.intro,
+                index,
+                "Welcome".to_string(),
+            ))
+            .unwrap();
+    }
+    // Each reply burns the configured reward before minting it
+    assert_eq!(runtime.token_balance(&bob), 3 * TOKEN);
+}
+
+#[test]
+fn config_rejects_zero_limits() {
+    let mut runtime = Runtime::with_mint();
+    for (max_name_len, max_message_len, min_reply_interval) in
+        [(0, 20, 1), (8, 0, 1), (8, 20, 0), (8, 20, -1)]
+    {
+        assert_eq!(
+            runtime.process(&set_config(
+                max_name_len,
+                max_message_len,
+                REPLY_REWARD,
+                DEFAULT_INTRO_REWARD,
+                min_reply_interval,
+            )),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+    assert!(runtime.account(&config_pda(&id()).0).is_none());
+}
+
+#[test]
+fn pausing_stops_writes_until_unpaused() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    runtime
+        .process(&set_config(
+            MAX_NAME_LEN as u32,
+            MAX_MESSAGE_CHARS as u32,
+            REPLY_REWARD,
+            DEFAULT_INTRO_REWARD,
+            MIN_REPLY_INTERVAL_SECONDS,
+        ))
+        .unwrap();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let carol = runtime.create_user();
+    let create = IntroInstruction::init_user_input(
+        &id(),
+        &carol,
+        "Carol".to_string(),
+        "Hello, I am Carol".to_string(),
+        None,
+        None,
+    );
+    let reply = IntroInstruction::add_reply(&id(), &bob, &intro.intro, 0, "Welcome".to_string());
+    let stats = common::instruction(
+        InstructionTag::GetStats,
+        &(),
+        vec![
+            AccountMeta::new_readonly(intro.intro, false),
+            AccountMeta::new_readonly(intro.reply_counter, false),
+        ],
+    );
+
+    runtime.process(&set_paused(true)).unwrap();
+    for instruction in [&create, &reply] {
+        assert_eq!(
+            runtime.process(instruction),
+            Err(StudentIntroError::ProgramPaused.into())
+        );
+    }
+    runtime.process(&stats).unwrap();
+
+    runtime.process(&set_paused(false)).unwrap();
+    runtime.process(&create).unwrap();
+    runtime.process(&reply).unwrap();
+}
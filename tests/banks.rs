@@ -0,0 +1,340 @@
+mod common;
+#[path = "../examples/cpi_add_reply.rs"]
+mod cpi_add_reply;
+
+use common::{
+    banks::{
+        self, add_intro_with_reply, add_token_account, add_wallet, program_test, MESSAGE, NAME,
+    },
+    counter_state, intro_state, program_account, IntroPdas, STARTING_LAMPORTS, TOKEN,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use solana_program_test::{processor, tokio};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use solana_student_intros_program::{
+    prelude::*,
+    processor::{EDIT_COOLDOWN_SECONDS, MIN_REPLY_INTERVAL_SECONDS},
+};
+use spl_token::state::Mint;
+
+fn program_error(error: StudentIntroError) -> TransactionError {
+    TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+}
+
+#[tokio::test]
+async fn author_and_replier_edit_their_accounts() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    add_intro_with_reply(&mut program_test, &alice, &bob);
+    let mut context = program_test.start_with_context().await;
+    banks::airdrop(&mut context, &alice.pubkey(), STARTING_LAMPORTS).await;
+    let pdas = IntroPdas::new(&alice.pubkey(), NAME);
+
+    // Same length as the original, so the account keeps its size
+    let message = "Hello, I am Alie!";
+    banks::process(
+        &mut context,
+        &[IntroInstruction::update_student_intro(
+            &id(),
+            &alice.pubkey(),
+            NAME,
+            NAME.to_string(),
+            message.to_string(),
+            None,
+        )],
+        &[&alice],
+    )
+    .await
+    .unwrap();
+    let intro: StudentInfo = banks::get(&mut context, &pdas.intro).await;
+    assert_eq!(intro.author, alice.pubkey());
+    assert_eq!(intro.name, NAME);
+    assert_eq!(intro.msg, message);
+    assert!(intro.updated_at > 0);
+    let alice_lamports = banks::account(&mut context, &alice.pubkey())
+        .await
+        .unwrap()
+        .lamports;
+    assert_eq!(alice_lamports, 2 * STARTING_LAMPORTS);
+
+    banks::process(
+        &mut context,
+        &[common::instruction(
+            InstructionTag::UpdateReply,
+            &"Hi there Alice".to_string(),
+            vec![
+                AccountMeta::new(bob.pubkey(), true),
+                AccountMeta::new(pdas.reply(0), false),
+                common::denylist_meta(),
+                common::config_meta(),
+            ],
+        )],
+        &[&bob],
+    )
+    .await
+    .unwrap();
+    banks::process(
+        &mut context,
+        &[common::instruction(
+            InstructionTag::SoftDeleteReply,
+            &true,
+            vec![
+                AccountMeta::new_readonly(bob.pubkey(), true),
+                AccountMeta::new(pdas.reply(0), false),
+                common::config_meta(),
+            ],
+        )],
+        &[&bob],
+    )
+    .await
+    .unwrap();
+    let reply: Reply = banks::get(&mut context, &pdas.reply(0)).await;
+    assert_eq!(reply.replier, bob.pubkey());
+    assert_eq!(reply.reply, "Hi there Alice");
+    assert_eq!(reply.edits.len(), 1);
+    assert!(reply.deleted);
+
+    // Editing mints and burns nothing
+    assert_eq!(
+        banks::token_balance(&mut context, &alice.pubkey()).await,
+        10 * TOKEN
+    );
+    assert_eq!(
+        banks::token_balance(&mut context, &bob.pubkey()).await,
+        TOKEN
+    );
+    let mint = banks::account(&mut context, &common::token_mint())
+        .await
+        .unwrap();
+    assert_eq!(Mint::unpack(&mint.data).unwrap().supply, 11 * TOKEN);
+}
+
+#[tokio::test]
+async fn author_cannot_reply_to_own_intro() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    add_intro_with_reply(&mut program_test, &alice, &bob);
+    let mut context = program_test.start_with_context().await;
+    let pdas = IntroPdas::new(&alice.pubkey(), NAME);
+
+    let error = banks::process(
+        &mut context,
+        &[IntroInstruction::add_reply(
+            &id(),
+            &alice.pubkey(),
+            &pdas.intro,
+            1,
+            "Welcome me".to_string(),
+        )],
+        &[&alice],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, program_error(StudentIntroError::InvalidInput));
+    let counter: ReplyCounter = banks::get(&mut context, &pdas.reply_counter).await;
+    assert_eq!(counter.counter, 1);
+    assert!(banks::account(&mut context, &pdas.reply(1)).await.is_none());
+    assert_eq!(
+        banks::token_balance(&mut context, &alice.pubkey()).await,
+        10 * TOKEN
+    );
+}
+
+#[tokio::test]
+async fn update_moves_updated_at_past_created_at() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+    let mut context = program_test.start_with_context().await;
+    let created_at = banks::unix_timestamp(&mut context).await;
+    context.set_account(
+        &pdas.intro,
+        &program_account(
+            &intro_state(&alice.pubkey(), NAME, MESSAGE, created_at),
+            StudentInfo::get_account_size(NAME, MESSAGE, "").unwrap(),
+        )
+        .into(),
+    );
+    banks::advance_clock(&mut context, EDIT_COOLDOWN_SECONDS).await;
+
+    banks::process(
+        &mut context,
+        &[IntroInstruction::update_student_intro(
+            &id(),
+            &alice.pubkey(),
+            NAME,
+            NAME.to_string(),
+            "Hello, I am Alie!".to_string(),
+            None,
+        )],
+        &[&alice],
+    )
+    .await
+    .unwrap();
+
+    let intro: StudentInfo = banks::get(&mut context, &pdas.intro).await;
+    assert_eq!(intro.created_at, created_at);
+    assert_eq!(intro.updated_at, created_at + EDIT_COOLDOWN_SECONDS);
+    assert!(intro.updated_at > intro.created_at);
+}
+
+#[tokio::test]
+async fn trace_logs_follow_the_debug_logs_feature() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+    let mut context = program_test.start_with_context().await;
+
+    // A zero tip fails after its trace line but before the transfer CPI. The program runs
+    // natively here, unmetered, so the saving shows up as log lines rather than units
+    let logs = banks::failure_logs(
+        &mut context,
+        &[common::instruction(
+            InstructionTag::TipAuthor,
+            &0u64,
+            vec![
+                AccountMeta::new(bob.pubkey(), true),
+                AccountMeta::new(alice.pubkey(), false),
+                AccountMeta::new_readonly(pdas.intro, false),
+                common::system_meta(),
+                common::config_meta(),
+            ],
+        )],
+        &[&bob],
+    )
+    .await;
+
+    let logged = |line: &str| {
+        logs.iter()
+            .any(|log| log == &format!("Program log: {}", line))
+    };
+    assert!(logged("Tipping intro author..."));
+    assert!(logged("Tip must be greater than zero"));
+    assert_eq!(logged("Lamports: 0"), cfg!(feature = "debug-logs"));
+}
+
+#[tokio::test]
+async fn updates_wait_out_the_cooldown() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+    let mut context = program_test.start_with_context().await;
+    // Messages keep the original length, so no update needs to resize the intro, and
+    // differ so the retried update is a new transaction
+    let update = |message: &str| {
+        IntroInstruction::update_student_intro(
+            &id(),
+            &alice.pubkey(),
+            NAME,
+            NAME.to_string(),
+            message.to_string(),
+            None,
+        )
+    };
+
+    banks::process(&mut context, &[update("Hello, I am Alie!")], &[&alice])
+        .await
+        .unwrap();
+    banks::advance_clock(&mut context, EDIT_COOLDOWN_SECONDS - 1).await;
+    let error = banks::process(&mut context, &[update("Hello, it's Alice")], &[&alice])
+        .await
+        .unwrap_err();
+    assert_eq!(error, program_error(StudentIntroError::CooldownActive));
+    let intro: StudentInfo = banks::get(&mut context, &pdas.intro).await;
+    assert_eq!(intro.msg, "Hello, I am Alie!");
+
+    banks::advance_clock(&mut context, 1).await;
+    banks::process(&mut context, &[update("Hello, Alice here")], &[&alice])
+        .await
+        .unwrap();
+    let intro: StudentInfo = banks::get(&mut context, &pdas.intro).await;
+    assert_eq!(intro.msg, "Hello, Alice here");
+}
+
+#[tokio::test]
+async fn replies_wait_out_the_reply_interval() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let carol = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+    // Carol holds no tokens, so once the interval has passed her reply fails at the
+    // burn's balance check instead of reaching the CPIs
+    add_wallet(&mut program_test, &carol.pubkey(), STARTING_LAMPORTS);
+    add_token_account(&mut program_test, &carol.pubkey(), 0);
+    let mut context = program_test.start_with_context().await;
+    let mut counter = counter_state(1);
+    counter.last_reply_at = banks::unix_timestamp(&mut context).await;
+    context.set_account(
+        &pdas.reply_counter,
+        &program_account(&counter, ReplyCounter::SIZE).into(),
+    );
+    let reply = |text: &str| {
+        IntroInstruction::add_reply(&id(), &carol.pubkey(), &pdas.intro, 1, text.to_string())
+    };
+
+    banks::advance_clock(&mut context, MIN_REPLY_INTERVAL_SECONDS - 1).await;
+    let error = banks::process(&mut context, &[reply("Welcome, Alice!")], &[&carol])
+        .await
+        .unwrap_err();
+    assert_eq!(error, program_error(StudentIntroError::CooldownActive));
+
+    banks::advance_clock(&mut context, 1).await;
+    let error = banks::process(&mut context, &[reply("Welcome, Alice!!")], &[&carol])
+        .await
+        .unwrap_err();
+    assert_eq!(error, program_error(StudentIntroError::InsufficientTokens));
+    assert!(banks::account(&mut context, &pdas.reply(1)).await.is_none());
+}
+
+#[tokio::test]
+async fn example_program_reaches_add_reply_through_cpi() {
+    let alice = Keypair::new();
+    let bob = Keypair::new();
+    let mut program_test = program_test(11 * TOKEN);
+    let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+    let caller = Pubkey::new_unique();
+    program_test.add_program(
+        "cpi_add_reply",
+        caller,
+        processor!(cpi_add_reply::process_instruction),
+    );
+    // The PDA holds no tokens, so add_reply stops at the burn's balance check, before
+    // any CPI of its own
+    let replier = Pubkey::find_program_address(&[cpi_add_reply::REPLIER_SEED], &caller).0;
+    add_wallet(&mut program_test, &replier, STARTING_LAMPORTS);
+    add_token_account(&mut program_test, &replier, 0);
+    let mut context = program_test.start_with_context().await;
+    banks::advance_clock(&mut context, MIN_REPLY_INTERVAL_SECONDS).await;
+
+    let text = "Hello from a program";
+    let mut accounts =
+        IntroInstruction::add_reply(&id(), &replier, &pdas.intro, 1, text.to_string()).accounts;
+    accounts[0].is_signer = false;
+    accounts.push(AccountMeta::new_readonly(id(), false));
+    let error = banks::process(
+        &mut context,
+        &[Instruction::new_with_bytes(
+            caller,
+            text.as_bytes(),
+            accounts,
+        )],
+        &[],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(error, program_error(StudentIntroError::InsufficientTokens));
+    assert!(banks::account(&mut context, &pdas.reply(1)).await.is_none());
+}
@@ -0,0 +1,188 @@
+//! solana-program-test harness. The program runs natively, so accounts are injected in
+//! the state the creating instructions would leave them in; see `runtime` for why those
+//! instructions cannot run here. A CPI from a native program also leaves program-test
+//! 1.10 holding a stale reference into its instruction trace, so only paths that return
+//! before their first CPI, or whose CPI fails before changing any account, are driven
+//! through it.
+
+use super::{
+    counter_state, intro_state, mint_account, program_account, reply_state, token_account,
+    token_account_data, IntroPdas, STARTING_LAMPORTS, TOKEN,
+};
+use solana_program::{
+    clock::Clock, instruction::Instruction, pubkey::Pubkey, system_instruction, system_program,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use solana_student_intros_program::{prelude::*, processor::REWARD_DECIMALS};
+use spl_token::state::Account as TokenAccount;
+
+pub const NAME: &str = "Alice";
+pub const MESSAGE: &str = "Hello, I am Alice";
+pub const REPLY: &str = "Welcome, Alice";
+
+/// The program with its reward mint already initialized and `supply` minted.
+pub fn program_test(supply: u64) -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "solana_student_intros_program",
+        id(),
+        processor!(process_instruction),
+    );
+    program_test.add_account(
+        token_mint_pda(&id()).0,
+        mint_account(REWARD_DECIMALS, supply),
+    );
+    program_test
+}
+
+/// Injects `owner`'s associated token account holding `amount` reward tokens.
+pub fn add_token_account(program_test: &mut ProgramTest, owner: &Pubkey, amount: u64) {
+    program_test.add_account(token_account(owner), token_account_data(owner, amount));
+}
+
+/// Injects a system account with `lamports`, so it can pay for its own transactions.
+pub fn add_wallet(program_test: &mut ProgramTest, wallet: &Pubkey, lamports: u64) {
+    program_test.add_account(*wallet, Account::new(lamports, 0, &system_program::ID));
+}
+
+/// Injects `author`'s intro, created long enough ago to be editable, with one reply from
+/// `replier` and the reward tokens both would hold by then.
+pub fn add_intro_with_reply(
+    program_test: &mut ProgramTest,
+    author: &Keypair,
+    replier: &Keypair,
+) -> IntroPdas {
+    let pdas = IntroPdas::new(&author.pubkey(), NAME);
+    add_wallet(program_test, &author.pubkey(), STARTING_LAMPORTS);
+    add_wallet(program_test, &replier.pubkey(), STARTING_LAMPORTS);
+    add_token_account(program_test, &author.pubkey(), 10 * TOKEN);
+    add_token_account(program_test, &replier.pubkey(), TOKEN);
+    program_test.add_account(
+        pdas.intro,
+        program_account(
+            &intro_state(&author.pubkey(), NAME, MESSAGE, 0),
+            StudentInfo::get_account_size(NAME, MESSAGE, "").unwrap(),
+        ),
+    );
+    program_test.add_account(
+        pdas.reply_counter,
+        program_account(&counter_state(1), ReplyCounter::SIZE),
+    );
+    program_test.add_account(
+        pdas.reply(0),
+        program_account(
+            &reply_state(&pdas.intro, &replier.pubkey(), REPLY),
+            Reply::get_account_size(REPLY, None).unwrap(),
+        ),
+    );
+    pdas
+}
+
+/// Funds `recipient` from the context's payer.
+pub async fn airdrop(context: &mut ProgramTestContext, recipient: &Pubkey, lamports: u64) {
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(
+            &context.payer.pubkey(),
+            recipient,
+            lamports,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+}
+
+pub async fn unix_timestamp(context: &mut ProgramTestContext) -> i64 {
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp
+}
+
+pub async fn advance_clock(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+/// Sends `instructions` in one transaction paid by the context's payer and signed by
+/// `signers` as well.
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|error| match error {
+            BanksClientError::TransactionError(error)
+            | BanksClientError::SimulationError { err: error, .. } => error,
+            error => panic!("transport error: {}", error),
+        })
+}
+
+/// Runs `instructions` through preflight simulation, expecting them to fail, and returns
+/// the program logs captured along the way.
+pub async fn failure_logs(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<String> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    match context
+        .banks_client
+        .process_transaction_with_preflight(transaction)
+        .await
+    {
+        Err(BanksClientError::SimulationError { logs, .. }) => logs,
+        result => panic!("expected a failed simulation, got {:?}", result),
+    }
+}
+
+pub async fn account(context: &mut ProgramTestContext, address: &Pubkey) -> Option<Account> {
+    context.banks_client.get_account(*address).await.unwrap()
+}
+
+pub async fn get<T: borsh::BorshDeserialize>(
+    context: &mut ProgramTestContext,
+    address: &Pubkey,
+) -> T {
+    match account(context, address).await {
+        Some(account) => super::state(&account),
+        None => panic!("account {} does not exist", address),
+    }
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, owner: &Pubkey) -> u64 {
+    match account(context, &token_account(owner)).await {
+        Some(account) => TokenAccount::unpack(&account.data).unwrap().amount,
+        None => 0,
+    }
+}
@@ -0,0 +1,297 @@
+//! Fixtures shared by the integration tests. `banks` drives the program through
+//! solana-program-test, `runtime` through the in-process runtime that can also run the
+//! instructions creating and closing accounts.
+#![allow(dead_code)]
+
+pub mod banks;
+pub mod runtime;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use runtime::Runtime;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_sdk::account::Account;
+use solana_student_intros_program::{
+    prelude::*,
+    processor::{ADMIN_PUBKEY, MIN_REPLY_INTERVAL_SECONDS, REWARD_DECIMALS},
+    state::CURRENT_VERSION,
+};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+/// Reward tokens are written with 9 decimals, so one whole token.
+pub const TOKEN: u64 = 1_000_000_000;
+/// Comfortably covers the creation fee and rent of everything a test creates.
+pub const STARTING_LAMPORTS: u64 = 10_000_000_000;
+
+/// Every PDA around one author's intro, derived through the `pda` module.
+pub struct IntroPdas {
+    pub author: Pubkey,
+    pub intro: Pubkey,
+    pub reply_counter: Pubkey,
+    pub reply_index: Pubkey,
+    pub name_index: Pubkey,
+    pub reward_marker: Pubkey,
+    pub token_account: Pubkey,
+}
+
+impl IntroPdas {
+    pub fn new(author: &Pubkey, name: &str) -> Self {
+        let program_id = id();
+        let (intro, _) = user_intro_pda(author, &program_id);
+        Self {
+            author: *author,
+            intro,
+            reply_counter: reply_counter_pda(&intro, &program_id).0,
+            reply_index: reply_index_pda(&intro, &program_id).0,
+            name_index: name_index_pda(name, &program_id).0,
+            reward_marker: intro_reward_marker_pda(author, &program_id).0,
+            token_account: token_account(author),
+        }
+    }
+
+    pub fn reply(&self, index: u64) -> Pubkey {
+        reply_pda(&self.intro, index, &id()).0
+    }
+
+    pub fn chunk(&self, index: u8) -> Pubkey {
+        message_chunk_pda(&self.intro, index, &id()).0
+    }
+}
+
+pub fn token_mint() -> Pubkey {
+    token_mint_pda(&id()).0
+}
+
+pub fn token_account(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &token_mint())
+}
+
+pub fn data_account(data: Vec<u8>, owner: &Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: *owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A program account holding `state`, padded with zeroes to `len` bytes.
+pub fn program_account<T: BorshSerialize>(state: &T, len: usize) -> Account {
+    let mut data = state.try_to_vec().unwrap();
+    assert!(data.len() <= len, "state does not fit {} bytes", len);
+    data.resize(len, 0);
+    data_account(data, &id())
+}
+
+/// The reward mint as `InitializeMint` leaves it, with `supply` already minted.
+pub fn mint_account(decimals: u8, supply: u64) -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::Some(mint_auth_pda(&id()).0),
+        supply,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    data_account(data, &spl_token::ID)
+}
+
+pub fn token_account_data(owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount {
+        mint: token_mint(),
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }
+    .pack_into_slice(&mut data);
+    data_account(data, &spl_token::ID)
+}
+
+pub fn intro_state(author: &Pubkey, name: &str, message: &str, created_at: i64) -> StudentInfo {
+    StudentInfo {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::StudentInfo,
+        is_initialized: true,
+        author: *author,
+        name: name.to_string(),
+        msg: message.to_string(),
+        created_at,
+        updated_at: created_at,
+        rating_count: 0,
+        rating_sum: 0,
+        avatar_uri: String::new(),
+        message_chunks: 0,
+    }
+}
+
+pub fn counter_state(counter: u64) -> ReplyCounter {
+    ReplyCounter {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::ReplyCounter,
+        is_initialized: true,
+        counter,
+        first_index: 0,
+        last_reply_at: 0,
+    }
+}
+
+pub fn reply_state(intro: &Pubkey, replier: &Pubkey, reply: &str) -> Reply {
+    Reply {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::Reply,
+        is_initialized: true,
+        studentinfo: *intro,
+        replier: *replier,
+        parent: None,
+        reply: reply.to_string(),
+        likes: 0,
+        reactions: [0; 4],
+        flags: 0,
+        deleted: false,
+        edits: Vec::new(),
+    }
+}
+
+pub fn config_state(paused: bool) -> Config {
+    Config {
+        magic: ACCOUNT_MAGIC,
+        version: CURRENT_VERSION,
+        discriminator: AccountType::Config,
+        is_initialized: true,
+        max_name_len: 64,
+        max_message_len: 280,
+        reply_reward: TOKEN,
+        intro_reward: 10 * TOKEN,
+        paused,
+        min_reply_interval: MIN_REPLY_INTERVAL_SECONDS,
+    }
+}
+
+/// Builds an instruction the builders do not cover from its tag and borsh payload.
+pub fn instruction<T: BorshSerialize>(
+    tag: InstructionTag,
+    payload: &T,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut data = vec![tag as u8];
+    payload.serialize(&mut data).unwrap();
+    Instruction::new_with_bytes(id(), &data, accounts)
+}
+
+pub fn config_meta() -> AccountMeta {
+    AccountMeta::new_readonly(config_pda(&id()).0, false)
+}
+
+pub fn denylist_meta() -> AccountMeta {
+    AccountMeta::new_readonly(word_denylist_pda(&id()).0, false)
+}
+
+pub fn system_meta() -> AccountMeta {
+    AccountMeta::new_readonly(system_program::ID, false)
+}
+
+pub fn state<T: BorshDeserialize>(account: &Account) -> T {
+    T::deserialize(&mut &account.data[..]).unwrap()
+}
+
+impl Runtime {
+    /// A runtime whose reward mint was created by the admin through `InitializeMint`.
+    pub fn with_mint() -> Self {
+        let mut runtime = Self::new();
+        runtime.airdrop(&ADMIN_PUBKEY, STARTING_LAMPORTS);
+        runtime
+            .process(&IntroInstruction::initialize_mint(
+                &id(),
+                &ADMIN_PUBKEY,
+                REWARD_DECIMALS,
+            ))
+            .unwrap();
+        runtime
+    }
+
+    /// A funded wallet with an associated token account for the reward mint.
+    pub fn create_user(&mut self) -> Pubkey {
+        let user = Pubkey::new_unique();
+        self.airdrop(&user, STARTING_LAMPORTS);
+        self.process(
+            &spl_associated_token_account::instruction::create_associated_token_account(
+                &user,
+                &user,
+                &token_mint(),
+            ),
+        )
+        .unwrap();
+        user
+    }
+
+    pub fn create_intro(&mut self, author: &Pubkey, name: &str, message: &str) -> IntroPdas {
+        self.process(&IntroInstruction::init_user_input(
+            &id(),
+            author,
+            name.to_string(),
+            message.to_string(),
+            None,
+            None,
+        ))
+        .unwrap();
+        IntroPdas::new(author, name)
+    }
+
+    /// Replies at the intro's next index once the reply interval has passed, returning
+    /// the reply PDA.
+    pub fn add_reply(&mut self, replier: &Pubkey, intro: &IntroPdas, reply: &str) -> Pubkey {
+        self.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+        let index = self.get::<ReplyCounter>(&intro.reply_counter).counter;
+        self.process(&IntroInstruction::add_reply(
+            &id(),
+            replier,
+            &intro.intro,
+            index,
+            reply.to_string(),
+        ))
+        .unwrap();
+        intro.reply(index)
+    }
+
+    pub fn get<T: BorshDeserialize>(&self, address: &Pubkey) -> T {
+        match self.account(address) {
+            Some(account) => state(account),
+            None => panic!("account {} does not exist", address),
+        }
+    }
+
+    /// Rewrites an existing account's state in place, keeping its size and lamports.
+    pub fn set_state<T: BorshSerialize>(&mut self, address: &Pubkey, state: &T) {
+        let mut account = self.account(address).unwrap().clone();
+        state.serialize(&mut &mut account.data[..]).unwrap();
+        self.set_account(*address, account);
+    }
+
+    pub fn token_balance(&self, owner: &Pubkey) -> u64 {
+        match self.account(&token_account(owner)) {
+            Some(account) => TokenAccount::unpack(&account.data).unwrap().amount,
+            None => 0,
+        }
+    }
+
+    pub fn intro_events(&self) -> Vec<IntroEvent> {
+        self.events()
+            .iter()
+            .map(|data| IntroEvent::try_from_slice(data).unwrap())
+            .collect()
+    }
+}
@@ -0,0 +1,511 @@
+//! In-process stand-in for the runtime, for the flows program-test cannot run natively.
+//!
+//! solana-program-test 1.10 runs a native program against plain `Vec` copies of its
+//! accounts and its CPI hook refuses to resize account data, so every instruction that
+//! creates or closes an account fails there. This runtime serializes the accounts the way
+//! the BPF loader does and hands the buffer to the entrypoint's `deserialize`, so
+//! `realloc` and `assign` behave as on chain. CPIs are routed to an emulated system
+//! program, the real token and associated token account processors, or programs added
+//! with `add_program`; PDA signatures are derived from the calling program's seeds and a
+//! CPI cannot gain a signer or writable privilege its caller lacks.
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::{
+        deserialize, ProcessInstruction, ProgramResult, BPF_ALIGN_OF_U128,
+        MAX_PERMITTED_DATA_INCREASE, SUCCESS,
+    },
+    instruction::Instruction,
+    program::invoke_signed,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::{self, SystemError, SystemInstruction},
+    system_program, sysvar,
+};
+use solana_sdk::account::{create_account_for_test, Account};
+use solana_student_intros_program::{metadata::TOKEN_METADATA_PROGRAM_ID, prelude::*};
+use std::{cell::RefCell, collections::HashMap, sync::Once};
+
+/// Unix timestamp every runtime starts at.
+pub const GENESIS_TIMESTAMP: i64 = 1_650_000_000;
+
+#[derive(Default)]
+struct Context {
+    programs: HashMap<Pubkey, ProcessInstruction>,
+    unix_timestamp: i64,
+    callers: Vec<Pubkey>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        CONTEXT.with(|context| context.borrow_mut().logs.push(message.to_string()));
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        CONTEXT.with(|context| context.borrow_mut().events.push(fields.concat()));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            unix_timestamp: CONTEXT.with(|context| context.borrow().unix_timestamp),
+            ..Clock::default()
+        };
+        unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write(var_addr as *mut Rent, Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        CONTEXT.with(|context| context.borrow().return_data.clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        CONTEXT.with(|context| {
+            let mut context = context.borrow_mut();
+            let program_id = *context
+                .callers
+                .last()
+                .expect("return data outside a program");
+            context.return_data = Some((program_id, data.to_vec()));
+        });
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        CONTEXT.with(|context| context.borrow().callers.len() as u64)
+    }
+}
+
+/// Accepts `CreateMetadataAccountV3` once the mint authority has signed and creates the
+/// metadata account holding the instruction's payload as is, rather than the real
+/// program's layout.
+fn process_metadata_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let account = |index: usize| {
+        accounts
+            .get(index)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+    let (metadata, mint, mint_authority, payer, system_program) = (
+        account(0)?,
+        account(1)?,
+        account(2)?,
+        account(3)?,
+        account(5)?,
+    );
+    if !mint_authority.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"metadata", program_id.as_ref(), mint.key.as_ref()],
+        program_id,
+    );
+    if *metadata.key != expected {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let payload = instruction_data.get(1..).unwrap_or_default();
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            metadata.key,
+            Rent::default().minimum_balance(payload.len()),
+            payload.len() as u64,
+            program_id,
+        ),
+        &[payer.clone(), metadata.clone(), system_program.clone()],
+        &[&[b"metadata", program_id.as_ref(), mint.key.as_ref(), &[bump]]],
+    )?;
+    metadata.data.borrow_mut().copy_from_slice(payload);
+    Ok(())
+}
+
+fn process_token_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    spl_token::processor::Processor::process(program_id, accounts, instruction_data)
+}
+
+/// The subset of the system program the student intro program and the associated token
+/// account program use, with the same account checks.
+fn process_system_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let instruction = limited_deserialize::<SystemInstruction>(instruction_data, 1232)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    let account = |index: usize| {
+        accounts
+            .get(index)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+    match instruction {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let (from, to) = (account(0)?, account(1)?);
+            if to.lamports() != 0 {
+                return Err(ProgramError::Custom(
+                    SystemError::AccountAlreadyInUse as u32,
+                ));
+            }
+            transfer(from, to, lamports)?;
+            allocate(to, space)?;
+            to.assign(&owner);
+        }
+        SystemInstruction::Transfer { lamports } => {
+            let (from, to) = (account(0)?, account(1)?);
+            if !from.data_is_empty() || *from.owner != system_program::ID {
+                return Err(ProgramError::InvalidArgument);
+            }
+            transfer(from, to, lamports)?;
+        }
+        SystemInstruction::Allocate { space } => allocate(account(0)?, space)?,
+        SystemInstruction::Assign { owner } => {
+            let account = account(0)?;
+            if !account.is_signer || *account.owner != system_program::ID {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            account.assign(&owner);
+        }
+        _ => return Err(ProgramError::InvalidInstructionData),
+    }
+    Ok(())
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if from.lamports() < lamports {
+        return Err(ProgramError::Custom(
+            SystemError::ResultWithNegativeLamports as u32,
+        ));
+    }
+    **from.lamports.borrow_mut() -= lamports;
+    **to.lamports.borrow_mut() += lamports;
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !account.data_is_empty() || *account.owner != system_program::ID {
+        return Err(ProgramError::Custom(
+            SystemError::AccountAlreadyInUse as u32,
+        ));
+    }
+    account.realloc(space as usize, true)
+}
+
+fn invoke(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let caller = CONTEXT.with(|context| {
+        *context
+            .borrow()
+            .callers
+            .last()
+            .expect("CPI outside a program")
+    });
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut accounts = Vec::with_capacity(instruction.accounts.len());
+    for meta in &instruction.accounts {
+        let info = match account_infos.iter().find(|info| *info.key == meta.pubkey) {
+            Some(info) => info,
+            None => panic!(
+                "CPI to {} is missing account {}",
+                instruction.program_id, meta.pubkey
+            ),
+        };
+        if meta.is_signer && !info.is_signer && !pda_signers.contains(&meta.pubkey) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if meta.is_writable && !info.is_writable {
+            return Err(ProgramError::InvalidArgument);
+        }
+        accounts.push(AccountInfo {
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+            ..info.clone()
+        });
+    }
+    execute(&instruction.program_id, &accounts, &instruction.data)
+}
+
+fn execute(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let process = CONTEXT
+        .with(|context| context.borrow().programs.get(program_id).copied())
+        .ok_or(ProgramError::IncorrectProgramId)?;
+    CONTEXT.with(|context| context.borrow_mut().callers.push(*program_id));
+    let result = process(program_id, accounts, instruction_data);
+    CONTEXT.with(|context| context.borrow_mut().callers.pop());
+    result
+}
+
+pub struct Runtime {
+    accounts: HashMap<Pubkey, Account>,
+    programs: HashMap<Pubkey, ProcessInstruction>,
+    unix_timestamp: i64,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+impl Runtime {
+    /// Starts with the student intro, system, token, associated token account and token
+    /// metadata programs and the rent sysvar.
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        let mut runtime = Self {
+            accounts: HashMap::new(),
+            programs: HashMap::new(),
+            unix_timestamp: GENESIS_TIMESTAMP,
+            return_data: None,
+            logs: Vec::new(),
+            events: Vec::new(),
+        };
+        runtime.add_program(id(), process_instruction);
+        runtime.add_program(system_program::ID, process_system_instruction);
+        runtime.add_program(spl_token::ID, process_token_instruction);
+        runtime.add_program(
+            spl_associated_token_account::ID,
+            spl_associated_token_account::processor::process_instruction,
+        );
+        runtime.add_program(TOKEN_METADATA_PROGRAM_ID, process_metadata_instruction);
+        runtime.set_account(sysvar::rent::ID, create_account_for_test(&Rent::default()));
+        runtime
+    }
+
+    pub fn add_program(&mut self, program_id: Pubkey, process: ProcessInstruction) {
+        self.programs.insert(program_id, process);
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    pub fn remove_account(&mut self, address: &Pubkey) {
+        self.accounts.remove(address);
+    }
+
+    /// `None` once an account is closed, like an account the runtime has purged.
+    pub fn account(&self, address: &Pubkey) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.account(address).map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*address)
+            .or_insert_with(|| Account::new(0, 0, &system_program::ID))
+            .lamports += lamports;
+    }
+
+    pub fn unix_timestamp(&self) -> i64 {
+        self.unix_timestamp
+    }
+
+    pub fn advance_clock(&mut self, seconds: i64) {
+        self.unix_timestamp += seconds;
+    }
+
+    /// Return data left by the last processed instruction, from whichever program set it.
+    pub fn return_data(&self) -> Option<&(Pubkey, Vec<u8>)> {
+        self.return_data.as_ref()
+    }
+
+    /// Logs of the last processed instruction, including those of programs it invoked.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    /// `sol_log_data` payloads of the last processed instruction.
+    pub fn events(&self) -> &[Vec<u8>] {
+        &self.events
+    }
+
+    /// Runs `instruction` as the only instruction of a transaction signed by every
+    /// account its metas mark as signers. Account changes are only kept if it succeeds.
+    pub fn process(&mut self, instruction: &Instruction) -> ProgramResult {
+        CONTEXT.with(|context| {
+            *context.borrow_mut() = Context {
+                programs: self.programs.clone(),
+                unix_timestamp: self.unix_timestamp,
+                ..Context::default()
+            }
+        });
+
+        let mut input = self.serialize(instruction);
+        let result = {
+            let (program_id, account_infos, instruction_data) =
+                unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            let result = execute(program_id, &account_infos, instruction_data);
+            if result.is_ok() {
+                self.commit(instruction, &account_infos);
+            }
+            result
+        };
+
+        CONTEXT.with(|context| {
+            let context = context.replace(Context::default());
+            self.return_data = context.return_data;
+            self.logs = context.logs;
+            self.events = context.events;
+        });
+        result
+    }
+
+    /// Lays out the accounts, instruction data and program id like the BPF loader, in a
+    /// `u64` buffer so every field `deserialize` reads is aligned.
+    fn serialize(&self, instruction: &Instruction) -> Vec<u64> {
+        let mut input = Vec::new();
+        input.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+        for (position, meta) in instruction.accounts.iter().enumerate() {
+            let first = instruction
+                .accounts
+                .iter()
+                .position(|other| other.pubkey == meta.pubkey)
+                .unwrap();
+            if first < position {
+                input.push(first as u8);
+                input.extend_from_slice(&[0; 7]);
+                continue;
+            }
+            let metas = instruction
+                .accounts
+                .iter()
+                .filter(|other| other.pubkey == meta.pubkey);
+            let is_signer = metas.clone().any(|other| other.is_signer);
+            let is_writable = metas.clone().any(|other| other.is_writable);
+            let account = self
+                .accounts
+                .get(&meta.pubkey)
+                .cloned()
+                .unwrap_or_else(|| Account::new(0, 0, &system_program::ID));
+
+            input.push(u8::MAX);
+            input.push(is_signer.into());
+            input.push(is_writable.into());
+            input.push(account.executable.into());
+            // Filled in with the original data length by deserialize
+            input.extend_from_slice(&[0; 4]);
+            input.extend_from_slice(meta.pubkey.as_ref());
+            input.extend_from_slice(account.owner.as_ref());
+            input.extend_from_slice(&account.lamports.to_le_bytes());
+            input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            input.extend_from_slice(&account.data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            let padding = (BPF_ALIGN_OF_U128 - input.len() % BPF_ALIGN_OF_U128) % BPF_ALIGN_OF_U128;
+            input.resize(input.len() + padding, 0);
+            input.extend_from_slice(&account.rent_epoch.to_le_bytes());
+        }
+        input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&instruction.data);
+        input.extend_from_slice(instruction.program_id.as_ref());
+
+        let mut aligned = vec![0u64; input.len().div_ceil(8)];
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                input.as_ptr(),
+                aligned.as_mut_ptr() as *mut u8,
+                input.len(),
+            );
+        }
+        aligned
+    }
+
+    /// Writes the accounts back, dropping those left without lamports. Like the runtime
+    /// it rejects an instruction that changed read-only accounts or created lamports.
+    fn commit(&mut self, instruction: &Instruction, account_infos: &[AccountInfo]) {
+        let mut seen = Vec::new();
+        let (mut before, mut after) = (0u128, 0u128);
+        for info in account_infos {
+            if seen.contains(info.key) {
+                continue;
+            }
+            seen.push(*info.key);
+            let previous = self
+                .accounts
+                .get(info.key)
+                .cloned()
+                .unwrap_or_else(|| Account::new(0, 0, &system_program::ID));
+            let account = Account {
+                lamports: info.lamports(),
+                data: info.data.borrow().to_vec(),
+                owner: *info.owner,
+                executable: previous.executable,
+                rent_epoch: previous.rent_epoch,
+            };
+            before += u128::from(previous.lamports);
+            after += u128::from(account.lamports);
+            if !info.is_writable {
+                assert_eq!(
+                    (previous.lamports, &previous.data, previous.owner),
+                    (account.lamports, &account.data, account.owner),
+                    "{} changed read-only account {}",
+                    instruction.program_id,
+                    info.key
+                );
+                continue;
+            }
+            if account.lamports == 0 {
+                self.accounts.remove(info.key);
+            } else {
+                self.accounts.insert(*info.key, account);
+            }
+        }
+        assert_eq!(before, after, "instruction did not balance lamports");
+    }
+}
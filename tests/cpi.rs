@@ -0,0 +1,74 @@
+mod common;
+#[path = "../examples/cpi_add_reply.rs"]
+mod cpi_add_reply;
+
+use common::{runtime::Runtime, IntroPdas, TOKEN};
+use cpi_add_reply::REPLIER_SEED;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_student_intros_program::{prelude::*, processor::MIN_REPLY_INTERVAL_SECONDS};
+
+/// Alice's intro, and the example program deployed with a funded replier PDA holding a
+/// reward token for the reply burn.
+fn setup() -> (Runtime, Pubkey, Pubkey, IntroPdas) {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    let caller = Pubkey::new_unique();
+    runtime.add_program(caller, cpi_add_reply::process_instruction);
+    let replier = Pubkey::find_program_address(&[REPLIER_SEED], &caller).0;
+    runtime.airdrop(&replier, common::STARTING_LAMPORTS);
+    runtime.set_account(
+        common::token_account(&replier),
+        common::token_account_data(&replier, TOKEN),
+    );
+    (runtime, caller, replier, intro)
+}
+
+/// The example's instruction: `add_reply`'s accounts with the PDA replier left for the
+/// caller to sign, then the student intro program.
+fn reply_through(caller: &Pubkey, replier: &Pubkey, intro: &IntroPdas, text: &str) -> Instruction {
+    let mut accounts =
+        IntroInstruction::add_reply(&id(), replier, &intro.intro, 0, text.to_string()).accounts;
+    accounts[0].is_signer = false;
+    accounts.push(AccountMeta::new_readonly(id(), false));
+    Instruction::new_with_bytes(*caller, text.as_bytes(), accounts)
+}
+
+#[test]
+fn example_program_replies_as_its_pda() {
+    let (mut runtime, caller, replier, intro) = setup();
+
+    runtime
+        .process(&reply_through(
+            &caller,
+            &replier,
+            &intro,
+            "Hello from a program",
+        ))
+        .unwrap();
+
+    let reply: Reply = runtime.get(&intro.reply(0));
+    assert_eq!(reply.studentinfo, intro.intro);
+    assert_eq!(reply.replier, replier);
+    assert_eq!(reply.reply, "Hello from a program");
+    assert_eq!(runtime.get::<ReplyCounter>(&intro.reply_counter).counter, 1);
+}
+
+#[test]
+fn example_program_needs_the_intro_program_last() {
+    let (mut runtime, caller, replier, intro) = setup();
+
+    let mut instruction = reply_through(&caller, &replier, &intro, "Hello from a program");
+    instruction.accounts.pop();
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    assert!(runtime.account(&intro.reply(0)).is_none());
+}
@@ -0,0 +1,754 @@
+//! Drives every handler with one bad input and checks the exact error it is rejected
+//! with. Each case fails before the handler's first CPI, see `common::banks`.
+mod common;
+
+use common::{
+    banks::{self, add_intro_with_reply, add_wallet, program_test, MESSAGE, NAME},
+    config_meta, data_account, denylist_meta, program_account, system_meta, IntroPdas,
+    STARTING_LAMPORTS,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use solana_program_test::{tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+use solana_student_intros_program::{prelude::*, processor::ADMIN_PUBKEY};
+
+/// Alice's intro with Bob's reply at index 0, and Carol's intro and a reply at index 1
+/// that were allocated but never written.
+struct Fixture {
+    alice: Keypair,
+    bob: Keypair,
+    carol: Keypair,
+    pdas: IntroPdas,
+    carol_pdas: IntroPdas,
+}
+
+impl Fixture {
+    async fn start(mut program_test: ProgramTest) -> (ProgramTestContext, Self) {
+        let alice = Keypair::new();
+        let bob = Keypair::new();
+        let carol = Keypair::new();
+        let pdas = add_intro_with_reply(&mut program_test, &alice, &bob);
+        let carol_pdas = IntroPdas::new(&carol.pubkey(), "Carol");
+        add_wallet(&mut program_test, &carol.pubkey(), STARTING_LAMPORTS);
+        program_test.add_account(
+            carol_pdas.intro,
+            data_account(
+                vec![0; StudentInfo::get_account_size(NAME, MESSAGE, "").unwrap()],
+                &id(),
+            ),
+        );
+        program_test.add_account(pdas.reply(1), data_account(vec![0; 128], &id()));
+        let fixture = Self {
+            alice,
+            bob,
+            carol,
+            pdas,
+            carol_pdas,
+        };
+        (program_test.start_with_context().await, fixture)
+    }
+}
+
+/// Sends each case in a transaction of its own and checks it failed with the error given.
+async fn assert_rejects(
+    context: &mut ProgramTestContext,
+    cases: Vec<(&str, Instruction, Vec<&Keypair>, ProgramError)>,
+) {
+    for (name, instruction, signers, expected) in cases {
+        let result = banks::process(context, &[instruction], &signers).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::from(u64::from(expected.clone()))
+            )),
+            "{} should fail with {:?}",
+            name,
+            expected
+        );
+    }
+}
+
+fn custom(error: StudentIntroError) -> ProgramError {
+    error.into()
+}
+
+fn unsigned(mut instruction: Instruction, index: usize) -> Instruction {
+    instruction.accounts[index].is_signer = false;
+    instruction
+}
+
+fn replace_account(mut instruction: Instruction, index: usize, key: Pubkey) -> Instruction {
+    instruction.accounts[index].pubkey = key;
+    instruction
+}
+
+#[tokio::test]
+async fn intro_handlers_reject_bad_input() {
+    let (mut context, fixture) = Fixture::start(program_test(0)).await;
+    let dave = Keypair::new();
+    let (alice, bob, carol) = (
+        fixture.alice.pubkey(),
+        fixture.bob.pubkey(),
+        fixture.carol.pubkey(),
+    );
+    let intro = fixture.pdas.intro;
+    let init = |initializer: &Pubkey| {
+        IntroInstruction::init_user_input(
+            &id(),
+            initializer,
+            "Dave".to_string(),
+            "Hello, I am Dave".to_string(),
+            None,
+            None,
+        )
+    };
+    let update = |initializer: &Pubkey| {
+        IntroInstruction::update_student_intro(
+            &id(),
+            initializer,
+            NAME,
+            NAME.to_string(),
+            "Hello again".to_string(),
+            None,
+        )
+    };
+    let tip = |author: &Pubkey, lamports: u64| {
+        common::instruction(
+            InstructionTag::TipAuthor,
+            &lamports,
+            vec![
+                AccountMeta::new(bob, true),
+                AccountMeta::new(*author, false),
+                AccountMeta::new_readonly(intro, false),
+                system_meta(),
+                config_meta(),
+            ],
+        )
+    };
+    let rate = |user_account: &Pubkey, rating: u8| {
+        common::instruction(
+            InstructionTag::RateIntro,
+            &rating,
+            vec![
+                AccountMeta::new(bob, true),
+                AccountMeta::new(*user_account, false),
+                AccountMeta::new(rating_marker_pda(user_account, &bob, &id()).0, false),
+                system_meta(),
+                config_meta(),
+            ],
+        )
+    };
+    let transfer = IntroInstruction::transfer_intro_ownership(&id(), &alice, &bob, NAME, 0);
+    let cases = vec![
+        (
+            "InitUserInput without the initializer's signature",
+            unsigned(init(&dave.pubkey()), 0),
+            vec![],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "InitUserInput with the wrong token program",
+            replace_account(init(&dave.pubkey()), 7, Pubkey::new_unique()),
+            vec![&dave],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "InitUserInput into another author's intro PDA",
+            replace_account(init(&dave.pubkey()), 1, intro),
+            vec![&dave],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "UpdateStudentIntro of another author's intro",
+            replace_account(update(&bob), 1, intro),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "UpdateStudentIntro of an uninitialized intro",
+            update(&carol),
+            vec![&fixture.carol],
+            custom(StudentIntroError::UninitializedAccount),
+        ),
+        (
+            "InitOrUpdate of another author's intro",
+            replace_account(
+                IntroInstruction::init_or_update(
+                    &id(),
+                    &bob,
+                    Some(NAME),
+                    NAME.to_string(),
+                    "Hello again".to_string(),
+                    None,
+                    None,
+                ),
+                1,
+                intro,
+            ),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "AppendMessageChunk with empty text",
+            IntroInstruction::append_message_chunk(&id(), &alice, 0, String::new()),
+            vec![&fixture.alice],
+            custom(StudentIntroError::InvalidInput),
+        ),
+        (
+            "CloseStudentIntro with the wrong reply counter",
+            replace_account(
+                IntroInstruction::close_student_intro(&id(), &alice, NAME, 0),
+                2,
+                Pubkey::new_unique(),
+            ),
+            vec![&fixture.alice],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "TransferIntroOwnership to a key the accounts do not name",
+            common::instruction(
+                InstructionTag::TransferIntroOwnership,
+                &carol,
+                transfer.accounts.clone(),
+            ),
+            vec![&fixture.alice, &fixture.bob],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "TipAuthor of zero lamports",
+            tip(&alice, 0),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidInput),
+        ),
+        (
+            "TipAuthor to someone other than the intro's author",
+            tip(&carol, 1),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "RateIntro outside 1..=5",
+            rate(&intro, 6),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidInput),
+        ),
+        (
+            "RateIntro of an uninitialized intro",
+            rate(&fixture.carol_pdas.intro, 5),
+            vec![&fixture.bob],
+            custom(StudentIntroError::UninitializedAccount),
+        ),
+        (
+            "GetStats of an account the program does not own",
+            common::instruction(
+                InstructionTag::GetStats,
+                &(),
+                vec![
+                    AccountMeta::new_readonly(alice, false),
+                    AccountMeta::new_readonly(fixture.pdas.reply_counter, false),
+                ],
+            ),
+            vec![],
+            ProgramError::IllegalOwner,
+        ),
+        (
+            "Migrate of a token account",
+            common::instruction(
+                InstructionTag::Migrate,
+                &(),
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(fixture.pdas.token_account, false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::IllegalOwner,
+        ),
+    ];
+    assert_rejects(&mut context, cases).await;
+}
+
+#[tokio::test]
+async fn reply_handlers_reject_bad_input() {
+    let (mut context, fixture) = Fixture::start(program_test(0)).await;
+    let (alice, bob, carol) = (
+        fixture.alice.pubkey(),
+        fixture.bob.pubkey(),
+        fixture.carol.pubkey(),
+    );
+    let intro = fixture.pdas.intro;
+    let reply = fixture.pdas.reply(0);
+    let unwritten_reply = fixture.pdas.reply(1);
+    let add_reply = |user_account: &Pubkey| {
+        IntroInstruction::add_reply(&id(), &bob, user_account, 1, "Nice intro".to_string())
+    };
+    let delete = |authority: &Pubkey, index: u64| {
+        common::instruction(
+            InstructionTag::DeleteReply,
+            &index,
+            vec![
+                AccountMeta::new(*authority, true),
+                AccountMeta::new_readonly(intro, false),
+                AccountMeta::new(fixture.pdas.reply_counter, false),
+                AccountMeta::new(reply, false),
+                config_meta(),
+            ],
+        )
+    };
+    let burn = |token_program: &Pubkey, amount: u64| {
+        common::instruction(
+            InstructionTag::BurnTokens,
+            &amount,
+            vec![
+                AccountMeta::new(bob, true),
+                AccountMeta::new(common::token_account(&bob), false),
+                AccountMeta::new(common::token_mint(), false),
+                AccountMeta::new_readonly(*token_program, false),
+                config_meta(),
+            ],
+        )
+    };
+    let cases = vec![
+        (
+            "AddReply without the replier's signature",
+            unsigned(add_reply(&intro), 0),
+            vec![],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "AddReply with the wrong token program",
+            replace_account(add_reply(&intro), 8, Pubkey::new_unique()),
+            vec![&fixture.bob],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "AddReply to an uninitialized intro",
+            add_reply(&fixture.carol_pdas.intro),
+            vec![&fixture.bob],
+            custom(StudentIntroError::UninitializedAccount),
+        ),
+        (
+            "AddRepliesBatch without replies",
+            IntroInstruction::add_replies_batch(&id(), &bob, &intro, 1, Vec::new()),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidInput),
+        ),
+        (
+            "AddNestedReply under a parent the accounts do not name",
+            common::instruction(
+                InstructionTag::AddNestedReply,
+                &("Thanks".to_string(), Pubkey::new_unique()),
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new_readonly(intro, false),
+                    AccountMeta::new_readonly(reply, false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    system_meta(),
+                    AccountMeta::new_readonly(block_list_pda(&id()).0, false),
+                    denylist_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "DeleteReply at another index than the reply's",
+            delete(&bob, 5),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidPDA),
+        ),
+        (
+            "DeleteReply by neither the replier nor the author",
+            delete(&carol, 0),
+            vec![&fixture.carol],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "UpdateReply by someone other than the replier",
+            common::instruction(
+                InstructionTag::UpdateReply,
+                &"Welcome, me".to_string(),
+                vec![
+                    AccountMeta::new(alice, true),
+                    AccountMeta::new(reply, false),
+                    denylist_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.alice],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "SoftDeleteReply by neither the replier nor the admin",
+            common::instruction(
+                InstructionTag::SoftDeleteReply,
+                &true,
+                vec![
+                    AccountMeta::new_readonly(carol, true),
+                    AccountMeta::new(reply, false),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.carol],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "LikeReply of an uninitialized reply",
+            common::instruction(
+                InstructionTag::LikeReply,
+                &(),
+                vec![
+                    AccountMeta::new(alice, true),
+                    AccountMeta::new(unwritten_reply, false),
+                    AccountMeta::new(like_marker_pda(&unwritten_reply, &alice, &id()).0, false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.alice],
+            custom(StudentIntroError::UninitializedAccount),
+        ),
+        (
+            "AddReaction with the wrong system program",
+            common::instruction(
+                InstructionTag::AddReaction,
+                &ReactionKind::Heart,
+                vec![
+                    AccountMeta::new(alice, true),
+                    AccountMeta::new(reply, false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.alice],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "FlagReply with an unknown reason",
+            common::instruction(
+                InstructionTag::FlagReply,
+                &9u8,
+                vec![
+                    AccountMeta::new(alice, true),
+                    AccountMeta::new(reply, false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.alice],
+            custom(StudentIntroError::InvalidInput),
+        ),
+        (
+            "CloseOrphanReply refunding someone other than the replier",
+            IntroInstruction::close_orphan_reply(&id(), &carol, &intro, 0),
+            vec![],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "BurnTokens with the wrong token program",
+            burn(&Pubkey::new_unique(), 1),
+            vec![&fixture.bob],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "BurnTokens of nothing",
+            burn(&spl_token::ID, 0),
+            vec![&fixture.bob],
+            custom(StudentIntroError::InvalidInput),
+        ),
+    ];
+    assert_rejects(&mut context, cases).await;
+}
+
+#[tokio::test]
+async fn admin_handlers_reject_everyone_else() {
+    let (mut context, fixture) = Fixture::start(program_test(0)).await;
+    let (bob, carol) = (fixture.bob.pubkey(), fixture.carol.pubkey());
+    let block_list = block_list_pda(&id()).0;
+    let denylist = word_denylist_pda(&id()).0;
+    let config = config_pda(&id()).0;
+    let treasury = treasury_pda(&id()).0;
+    let cases = vec![
+        (
+            "InitializeMint without the admin's signature",
+            unsigned(
+                IntroInstruction::initialize_mint(&id(), &ADMIN_PUBKEY, 9),
+                0,
+            ),
+            vec![],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "AddToBlockList by someone other than the admin",
+            common::instruction(
+                InstructionTag::AddToBlockList,
+                &carol,
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(block_list, false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "RemoveFromBlockList by someone other than the admin",
+            common::instruction(
+                InstructionTag::RemoveFromBlockList,
+                &carol,
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(block_list, false),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "AddDeniedWord by someone other than the admin",
+            common::instruction(
+                InstructionTag::AddDeniedWord,
+                &"spam".to_string(),
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(denylist, false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "RemoveDeniedWord by someone other than the admin",
+            common::instruction(
+                InstructionTag::RemoveDeniedWord,
+                &"spam".to_string(),
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(denylist, false),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "SetConfig by someone other than the admin",
+            common::instruction(
+                InstructionTag::SetConfig,
+                &(64u32, 280u32, 1u64, 10u64, 60i64),
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(config, false),
+                    system_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "SetPaused by someone other than the admin",
+            common::instruction(
+                InstructionTag::SetPaused,
+                &true,
+                vec![AccountMeta::new(bob, true), AccountMeta::new(config, false)],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "WithdrawTreasury by someone other than the admin",
+            common::instruction(
+                InstructionTag::WithdrawTreasury,
+                &1u64,
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(treasury, false),
+                    system_meta(),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "WithdrawTreasury to another destination than the instruction's",
+            common::instruction(
+                InstructionTag::WithdrawTreasury,
+                &(1u64, bob),
+                vec![
+                    AccountMeta::new_readonly(ADMIN_PUBKEY, false),
+                    AccountMeta::new(treasury, false),
+                    system_meta(),
+                    AccountMeta::new(carol, false),
+                    config_meta(),
+                ],
+            ),
+            vec![],
+            custom(StudentIntroError::IncorrectAccountError),
+        ),
+        (
+            "SetFreezeAuthority by someone other than the admin",
+            common::instruction(
+                InstructionTag::SetFreezeAuthority,
+                &Option::<Pubkey>::None,
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(common::token_mint(), false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(spl_token::ID, false),
+                    config_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::MissingRequiredSignature,
+        ),
+        (
+            "ListReplies past the page limit",
+            common::instruction(
+                InstructionTag::ListReplies,
+                &(0u64, 21u8),
+                vec![
+                    AccountMeta::new_readonly(fixture.pdas.intro, false),
+                    AccountMeta::new_readonly(fixture.pdas.reply_counter, false),
+                ],
+            ),
+            vec![],
+            custom(StudentIntroError::InvalidInput),
+        ),
+    ];
+    assert_rejects(&mut context, cases).await;
+}
+
+#[tokio::test]
+async fn dispatch_rejects_malformed_instructions() {
+    let (mut context, fixture) = Fixture::start(program_test(0)).await;
+    let bob = fixture.bob.pubkey();
+    let cases = vec![
+        (
+            "An empty instruction",
+            Instruction::new_with_bytes(id(), &[], vec![config_meta()]),
+            vec![],
+            ProgramError::InvalidInstructionData,
+        ),
+        (
+            "An unknown tag",
+            Instruction::new_with_bytes(id(), &[u8::MAX], vec![config_meta()]),
+            vec![],
+            custom(StudentIntroError::InvalidInstruction),
+        ),
+        (
+            "A truncated payload",
+            Instruction::new_with_bytes(
+                id(),
+                &[InstructionTag::TipAuthor as u8, 1],
+                vec![config_meta()],
+            ),
+            vec![],
+            ProgramError::InvalidInstructionData,
+        ),
+        (
+            "TipAuthor without the config account",
+            common::instruction(
+                InstructionTag::TipAuthor,
+                &1u64,
+                vec![
+                    AccountMeta::new(bob, true),
+                    AccountMeta::new(fixture.alice.pubkey(), false),
+                    AccountMeta::new_readonly(fixture.pdas.intro, false),
+                    system_meta(),
+                ],
+            ),
+            vec![&fixture.bob],
+            ProgramError::NotEnoughAccountKeys,
+        ),
+    ];
+    assert_rejects(&mut context, cases).await;
+}
+
+#[tokio::test]
+async fn paused_program_rejects_writes() {
+    let mut program_test = program_test(0);
+    program_test.add_account(
+        config_pda(&id()).0,
+        program_account(&common::config_state(true), Config::SIZE),
+    );
+    let (mut context, fixture) = Fixture::start(program_test).await;
+    let bob = fixture.bob.pubkey();
+
+    let cases = vec![(
+        "AddReply while paused",
+        IntroInstruction::add_reply(&id(), &bob, &fixture.pdas.intro, 1, "Hi".to_string()),
+        vec![&fixture.bob],
+        custom(StudentIntroError::ProgramPaused),
+    )];
+    assert_rejects(&mut context, cases).await;
+
+    // Reads stay available
+    banks::process(
+        &mut context,
+        &[common::instruction(
+            InstructionTag::GetStats,
+            &(),
+            vec![
+                AccountMeta::new_readonly(fixture.pdas.intro, false),
+                AccountMeta::new_readonly(fixture.pdas.reply_counter, false),
+            ],
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn blocked_key_cannot_reply() {
+    let dave = Keypair::new();
+    let mut program_test = program_test(0);
+    program_test.add_account(
+        block_list_pda(&id()).0,
+        program_account(
+            &BlockList {
+                magic: ACCOUNT_MAGIC,
+                version: solana_student_intros_program::state::CURRENT_VERSION,
+                discriminator: AccountType::BlockList,
+                is_initialized: true,
+                blocked: vec![dave.pubkey()],
+            },
+            BlockList::SIZE,
+        ),
+    );
+    let (mut context, fixture) = Fixture::start(program_test).await;
+
+    let cases = vec![(
+        "AddReply by a blocked key",
+        IntroInstruction::add_reply(
+            &id(),
+            &dave.pubkey(),
+            &fixture.pdas.intro,
+            1,
+            "Hi".to_string(),
+        ),
+        vec![&dave],
+        custom(StudentIntroError::Blocked),
+    )];
+    assert_rejects(&mut context, cases).await;
+}
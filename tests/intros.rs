@@ -0,0 +1,812 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{runtime::Runtime, IntroPdas};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_student_intros_program::{
+    events::{StudentIntroCreated, StudentIntroUpdated},
+    prelude::*,
+    processor::{EDIT_COOLDOWN_SECONDS, INTRO_CREATION_FEE},
+    state::{
+        estimate_creation_rent, MAX_AVATAR_URI_LEN, MAX_INTRO_ACCOUNT_LEN, MAX_MESSAGE_CHARS,
+        MAX_NAME_LEN,
+    },
+};
+
+/// Alice's intro, past the edit cooldown.
+fn setup() -> (Runtime, Pubkey, IntroPdas) {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+    (runtime, alice, intro)
+}
+
+fn create(author: &Pubkey, name: &str, message: &str) -> Instruction {
+    IntroInstruction::init_user_input(
+        &id(),
+        author,
+        name.to_string(),
+        message.to_string(),
+        None,
+        None,
+    )
+}
+
+fn update(author: &Pubkey, previous_name: &str, name: &str, message: &str) -> Instruction {
+    IntroInstruction::update_student_intro(
+        &id(),
+        author,
+        previous_name,
+        name.to_string(),
+        message.to_string(),
+        None,
+    )
+}
+
+fn rate(rater: &Pubkey, intro: &IntroPdas, rating: u8) -> Instruction {
+    common::instruction(
+        InstructionTag::RateIntro,
+        &rating,
+        vec![
+            AccountMeta::new(*rater, true),
+            AccountMeta::new(intro.intro, false),
+            AccountMeta::new(rating_marker_pda(&intro.intro, rater, &id()).0, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+/// A message under the character limit that makes `name`'s intro account `len` bytes.
+fn message_for_account_len(name: &str, len: usize) -> String {
+    let bytes = len - StudentInfo::get_account_size(name, "", "").unwrap();
+    let message = "😀".repeat(bytes / 4) + &"a".repeat(bytes % 4);
+    assert!(message.chars().count() <= MAX_MESSAGE_CHARS);
+    message
+}
+
+#[test]
+fn update_changes_name_and_message() {
+    let (mut runtime, alice, intro) = setup();
+
+    runtime
+        .process(&update(&alice, "Alice", "Alicia", "Hi, I go by Alicia now"))
+        .unwrap();
+
+    let state: StudentInfo = runtime.get(&intro.intro);
+    assert_eq!(state.name, "Alicia");
+    assert_eq!(state.msg, "Hi, I go by Alicia now");
+    let name_index: NameIndex = runtime.get(&name_index_pda("Alicia", &id()).0);
+    assert_eq!(name_index.intro, intro.intro);
+    assert!(runtime.account(&intro.name_index).is_none());
+}
+
+#[test]
+fn only_the_signing_author_updates() {
+    let (mut runtime, alice, intro) = setup();
+
+    let mut unsigned = update(&alice, "Alice", "Alice", "Hello, I am not Alice");
+    unsigned.accounts[0].is_signer = false;
+    assert_eq!(
+        runtime.process(&unsigned),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(
+        runtime.get::<StudentInfo>(&intro.intro).msg,
+        "Hello, I am Alice"
+    );
+
+    runtime
+        .process(&update(&alice, "Alice", "Alice", "Hello, it is Alice"))
+        .unwrap();
+    assert_eq!(
+        runtime.get::<StudentInfo>(&intro.intro).msg,
+        "Hello, it is Alice"
+    );
+}
+
+#[test]
+fn garbage_intro_data_fails_to_deserialize() {
+    let (mut runtime, alice, intro) = setup();
+    let mut account = runtime.account(&intro.intro).unwrap().clone();
+    // Keeps the header, so the body is what fails to parse
+    account.data[7..].fill(0xff);
+    runtime.set_account(intro.intro, account);
+
+    assert_eq!(
+        runtime.process(&update(&alice, "Alice", "Alice", "Hello, it is Alice")),
+        Err(StudentIntroError::DeserializationFailed.into())
+    );
+}
+
+#[test]
+fn system_owned_intro_is_rejected_before_it_is_read() {
+    let (mut runtime, alice, intro) = setup();
+    let mut account = runtime.account(&intro.intro).unwrap().clone();
+    account.owner = system_program::ID;
+    account.data.fill(0xff);
+    runtime.set_account(intro.intro, account);
+
+    assert_eq!(
+        runtime.process(&update(&alice, "Alice", "Alice", "Hello, it is Alice")),
+        Err(ProgramError::IllegalOwner)
+    );
+}
+
+#[test]
+fn intro_pays_rent_for_its_own_size_only() {
+    let (runtime, _alice, intro) = setup();
+
+    let len = StudentInfo::get_account_size("Alice", "Hello, I am Alice", "").unwrap();
+    let account = runtime.account(&intro.intro).unwrap();
+    assert_eq!(account.data.len(), len);
+    let rent = Rent::default();
+    assert_eq!(account.lamports, rent.minimum_balance(len));
+    // Intros used to be allocated 1000 bytes whatever their length
+    assert!(account.lamports < rent.minimum_balance(1000));
+}
+
+#[test]
+fn intro_accounts_are_created_rent_exempt() {
+    let (runtime, alice, intro) = setup();
+
+    let rent = Rent::default();
+    for address in [
+        intro.intro,
+        intro.reply_counter,
+        intro.name_index,
+        intro_reward_marker_pda(&alice, &id()).0,
+    ] {
+        let account = runtime.account(&address).unwrap();
+        assert!(rent.is_exempt(account.lamports, account.data.len()));
+    }
+}
+
+#[test]
+fn creation_costs_the_estimated_rent_plus_the_fee() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let alice_lamports = runtime.lamports(&alice);
+
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+
+    let estimate = estimate_creation_rent("Alice", "Hello, I am Alice").unwrap();
+    assert_eq!(
+        alice_lamports - runtime.lamports(&alice),
+        estimate + INTRO_CREATION_FEE
+    );
+}
+
+#[test]
+fn underfunded_initializer_is_rejected_before_any_account_is_created() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let required =
+        estimate_creation_rent("Alice", "Hello, I am Alice").unwrap() + INTRO_CREATION_FEE;
+    let mut wallet = runtime.account(&alice).unwrap().clone();
+    wallet.lamports = required - 1;
+    runtime.set_account(alice, wallet);
+
+    assert_eq!(
+        runtime.process(&create(&alice, "Alice", "Hello, I am Alice")),
+        Err(ProgramError::InsufficientFunds)
+    );
+    let pdas = IntroPdas::new(&alice, "Alice");
+    assert!(runtime.account(&pdas.intro).is_none());
+    assert!(runtime.account(&pdas.reply_counter).is_none());
+    assert_eq!(runtime.lamports(&treasury_pda(&id()).0), 0);
+    assert_eq!(runtime.lamports(&alice), required - 1);
+
+    runtime.airdrop(&alice, 1);
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    assert_eq!(runtime.lamports(&alice), 0);
+}
+
+#[test]
+fn update_resizes_the_intro_to_fit() {
+    let (mut runtime, alice, intro) = setup();
+    let rent = Rent::default();
+    let resize_to = |runtime: &mut Runtime, message: &str| {
+        let alice_lamports = runtime.lamports(&alice);
+        let intro_lamports = runtime.lamports(&intro.intro);
+        runtime
+            .process(&update(&alice, "Alice", "Alice", message))
+            .unwrap();
+        runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+        let len = StudentInfo::get_account_size("Alice", message, "").unwrap();
+        assert_eq!(runtime.account(&intro.intro).unwrap().data.len(), len);
+        assert_eq!(runtime.lamports(&intro.intro), rent.minimum_balance(len));
+        // Whatever the intro gains or gives back comes from or goes to its author
+        assert_eq!(
+            runtime.lamports(&alice) + runtime.lamports(&intro.intro),
+            alice_lamports + intro_lamports
+        );
+        assert_eq!(runtime.get::<StudentInfo>(&intro.intro).msg, message);
+    };
+
+    resize_to(&mut runtime, "Hello, I am Alice and I study chemistry");
+    resize_to(&mut runtime, "Hi");
+    // Same length, so nothing moves
+    resize_to(&mut runtime, "Yo");
+}
+
+#[test]
+fn growing_update_needs_the_author_to_cover_the_rent() {
+    let (mut runtime, alice, intro) = setup();
+    let message = "Hello, I am Alice and I study chemistry";
+    let shortfall = Rent::default()
+        .minimum_balance(StudentInfo::get_account_size("Alice", message, "").unwrap())
+        - runtime.lamports(&intro.intro);
+    let mut wallet = runtime.account(&alice).unwrap().clone();
+    wallet.lamports = shortfall - 1;
+    runtime.set_account(alice, wallet);
+    let len = runtime.account(&intro.intro).unwrap().data.len();
+
+    assert_eq!(
+        runtime.process(&update(&alice, "Alice", "Alice", message)),
+        Err(ProgramError::InsufficientFunds)
+    );
+    assert_eq!(runtime.account(&intro.intro).unwrap().data.len(), len);
+    assert_eq!(runtime.lamports(&alice), shortfall - 1);
+
+    runtime.airdrop(&alice, 1);
+    runtime
+        .process(&update(&alice, "Alice", "Alice", message))
+        .unwrap();
+    assert_eq!(runtime.lamports(&alice), 0);
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).msg, message);
+}
+
+#[test]
+fn intro_needs_a_name_and_message() {
+    let (mut runtime, _alice, _intro) = setup();
+    let carol = runtime.create_user();
+    let long_name = "C".repeat(MAX_NAME_LEN + 1);
+
+    for (name, message) in [
+        ("", "Hello, I am Carol"),
+        ("  ", "Hello, I am Carol"),
+        ("Carol", ""),
+        (long_name.as_str(), "Hello, I am Carol"),
+    ] {
+        assert_eq!(
+            runtime.process(&create(&carol, name, message)),
+            Err(StudentIntroError::InvalidInput.into()),
+            "{:?} / {:?}",
+            name,
+            message
+        );
+    }
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+
+    runtime
+        .process(&create(&carol, &"C".repeat(MAX_NAME_LEN), "Hello"))
+        .unwrap();
+}
+
+#[test]
+fn ratings_average_rounds_half_up() {
+    let (mut runtime, _alice, intro) = setup();
+
+    let rate_as_new_user = |runtime: &mut Runtime, rating: u8| {
+        let rater = runtime.create_user();
+        runtime.process(&rate(&rater, &intro, rating)).unwrap();
+        runtime.get::<StudentInfo>(&intro.intro)
+    };
+
+    let state = rate_as_new_user(&mut runtime, 5);
+    assert_eq!(state.average_rating(), 5);
+    // 9 / 2 = 4.5 rounds up
+    let state = rate_as_new_user(&mut runtime, 4);
+    assert_eq!((state.rating_sum, state.rating_count), (9, 2));
+    assert_eq!(state.average_rating(), 5);
+    // 10 / 3 = 3.33 rounds down
+    let state = rate_as_new_user(&mut runtime, 1);
+    assert_eq!((state.rating_sum, state.rating_count), (10, 3));
+    assert_eq!(state.average_rating(), 3);
+    assert!(runtime.intro_events().iter().any(|event| matches!(
+        event,
+        IntroEvent::IntroRated(rated) if rated.average == 3 && rated.rating_count == 3
+    )));
+
+    let rater = runtime.create_user();
+    for rating in [0, 6] {
+        assert_eq!(
+            runtime.process(&rate(&rater, &intro, rating)),
+            Err(StudentIntroError::InvalidInput.into())
+        );
+    }
+}
+
+#[test]
+fn each_account_rates_an_intro_once() {
+    let (mut runtime, _alice, intro) = setup();
+    let bob = runtime.create_user();
+
+    runtime.process(&rate(&bob, &intro, 4)).unwrap();
+    assert!(runtime
+        .account(&rating_marker_pda(&intro.intro, &bob, &id()).0)
+        .is_some());
+
+    // Ratings cannot be changed either, so a second one never skews the sum
+    for rating in [4, 1] {
+        assert_eq!(
+            runtime.process(&rate(&bob, &intro, rating)),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+    }
+    let state: StudentInfo = runtime.get(&intro.intro);
+    assert_eq!((state.rating_sum, state.rating_count), (4, 1));
+}
+
+#[test]
+fn create_and_update_log_their_events() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let created_at = runtime.unix_timestamp();
+
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    assert!(runtime
+        .intro_events()
+        .contains(&IntroEvent::StudentIntroCreated(StudentIntroCreated {
+            author: alice,
+            intro: intro.intro,
+            name: "Alice".to_string(),
+            created_at,
+        })));
+
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+    runtime
+        .process(&update(&alice, "Alice", "Alice", "Hello, it is Alice"))
+        .unwrap();
+    assert_eq!(
+        runtime.intro_events(),
+        vec![IntroEvent::StudentIntroUpdated(StudentIntroUpdated {
+            author: alice,
+            intro: intro.intro,
+            updated_at: created_at + EDIT_COOLDOWN_SECONDS,
+        })]
+    );
+}
+
+#[test]
+fn author_closes_their_intro() {
+    let (mut runtime, alice, intro) = setup();
+    let refund = runtime.lamports(&intro.intro)
+        + runtime.lamports(&intro.reply_counter)
+        + runtime.lamports(&intro.name_index);
+    let alice_lamports = runtime.lamports(&alice);
+
+    runtime
+        .process(&IntroInstruction::close_student_intro(
+            &id(),
+            &alice,
+            "Alice",
+            0,
+        ))
+        .unwrap();
+
+    assert!(runtime.account(&intro.intro).is_none());
+    assert!(runtime.account(&intro.name_index).is_none());
+    assert!(runtime.account(&intro.reply_counter).is_none());
+    assert_eq!(runtime.lamports(&alice), alice_lamports + refund);
+}
+
+#[test]
+fn intros_with_replies_stay_open() {
+    let (mut runtime, alice, intro) = setup();
+    let bob = runtime.create_user();
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let close = IntroInstruction::close_student_intro(&id(), &alice, "Alice", 0);
+
+    assert_eq!(
+        runtime.process(&close),
+        Err(StudentIntroError::RepliesRemaining.into())
+    );
+    // Deleted replies keep their index taken, so they count too
+    runtime
+        .process(&common::instruction(
+            InstructionTag::DeleteReply,
+            &0u64,
+            vec![
+                AccountMeta::new(bob, true),
+                AccountMeta::new_readonly(intro.intro, false),
+                AccountMeta::new_readonly(intro.reply_counter, false),
+                AccountMeta::new(reply, false),
+                common::config_meta(),
+            ],
+        ))
+        .unwrap();
+    assert_eq!(
+        runtime.process(&close),
+        Err(StudentIntroError::RepliesRemaining.into())
+    );
+    assert!(runtime.account(&intro.intro).is_some());
+    assert!(runtime.account(&intro.reply_counter).is_some());
+}
+
+#[test]
+fn only_the_author_closes_an_intro() {
+    let (mut runtime, _alice, intro) = setup();
+    let bob = runtime.create_user();
+
+    let mut instruction = IntroInstruction::close_student_intro(&id(), &bob, "Alice", 0);
+    instruction.accounts[1].pubkey = intro.intro;
+    instruction.accounts[2].pubkey = intro.reply_counter;
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(StudentIntroError::InvalidPDA.into())
+    );
+    assert!(runtime.account(&intro.intro).is_some());
+}
+
+#[test]
+fn transfer_moves_the_intro_to_the_new_owner() {
+    let (mut runtime, alice, intro) = setup();
+    let carol = runtime.create_user();
+
+    runtime
+        .process(&IntroInstruction::transfer_intro_ownership(
+            &id(),
+            &alice,
+            &carol,
+            "Alice",
+            0,
+        ))
+        .unwrap();
+
+    let moved = IntroPdas::new(&carol, "Alice");
+    let state: StudentInfo = runtime.get(&moved.intro);
+    assert_eq!(state.author, carol);
+    assert_eq!(state.msg, "Hello, I am Alice");
+    assert!(runtime.account(&intro.intro).is_none());
+    assert_eq!(
+        runtime.get::<NameIndex>(&intro.name_index).intro,
+        moved.intro
+    );
+    assert!(
+        runtime
+            .get::<ReplyCounter>(&moved.reply_counter)
+            .is_initialized
+    );
+}
+
+#[test]
+fn transfer_needs_the_new_owner_to_sign() {
+    let (mut runtime, alice, intro) = setup();
+    let carol = runtime.create_user();
+
+    let mut instruction =
+        IntroInstruction::transfer_intro_ownership(&id(), &alice, &carol, "Alice", 0);
+    instruction.accounts[1].is_signer = false;
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).author, alice);
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+}
+
+#[test]
+fn intro_stores_the_creating_signer_as_author() {
+    let (mut runtime, alice, intro) = setup();
+    let bob = runtime.create_user();
+
+    runtime
+        .process(&create(&bob, "Bob", "Hello, I am Bob"))
+        .unwrap();
+
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).author, alice);
+    let bob_intro = IntroPdas::new(&bob, "Bob");
+    assert_eq!(runtime.get::<StudentInfo>(&bob_intro.intro).author, bob);
+}
+
+#[test]
+fn stats_summarize_the_intro_without_writing() {
+    let (mut runtime, _alice, intro) = setup();
+    let bob = runtime.create_user();
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    for (rater, rating) in [(bob, 4), (runtime.create_user(), 5)] {
+        runtime.process(&rate(&rater, &intro, rating)).unwrap();
+    }
+    let before = runtime.account(&intro.intro).unwrap().clone();
+
+    runtime
+        .process(&common::instruction(
+            InstructionTag::GetStats,
+            &(),
+            vec![
+                AccountMeta::new_readonly(intro.intro, false),
+                AccountMeta::new_readonly(intro.reply_counter, false),
+            ],
+        ))
+        .unwrap();
+
+    let (program_id, data) = runtime.return_data().unwrap();
+    assert_eq!(*program_id, id());
+    let state: StudentInfo = runtime.get(&intro.intro);
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    assert_eq!(
+        IntroStats::try_from_slice(data).unwrap(),
+        IntroStats {
+            reply_count: counter.reply_count(),
+            created_at: state.created_at,
+            rating: state.average_rating(),
+            rating_count: state.rating_count,
+        }
+    );
+    assert_eq!(counter.reply_count(), 1);
+    assert_eq!(state.rating_count, 2);
+    assert_eq!(runtime.account(&intro.intro), Some(&before));
+}
+
+#[test]
+fn intro_needs_the_authors_token_account() {
+    let (mut runtime, _alice, _intro) = setup();
+    let carol = Pubkey::new_unique();
+    runtime.airdrop(&carol, common::STARTING_LAMPORTS);
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", "Hello, I am Carol")),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+    assert_eq!(runtime.lamports(&carol), common::STARTING_LAMPORTS);
+}
+
+#[test]
+fn wrong_token_program_is_rejected_before_any_account_is_created() {
+    let (mut runtime, _alice, _intro) = setup();
+    let carol = runtime.create_user();
+    let carol_lamports = runtime.lamports(&carol);
+    let pdas = IntroPdas::new(&carol, "Carol");
+
+    let mut instruction = create(&carol, "Carol", "Hello, I am Carol");
+    instruction.accounts[7] = AccountMeta::new_readonly(Pubkey::new_unique(), false);
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+    assert!(runtime.account(&pdas.intro).is_none());
+    assert!(runtime.account(&pdas.reply_counter).is_none());
+    assert!(runtime.account(&pdas.name_index).is_none());
+    assert_eq!(runtime.lamports(&carol), carol_lamports);
+}
+
+#[test]
+fn pre_funded_intro_address_is_topped_up_in_place() {
+    let (mut runtime, _alice, _intro) = setup();
+    let carol = runtime.create_user();
+    let pdas = IntroPdas::new(&carol, "Carol");
+    let len = StudentInfo::get_account_size("Carol", "Hello, I am Carol", "").unwrap();
+    let rent = Rent::default().minimum_balance(len);
+    // Someone sent lamports to the address before Carol created her intro
+    runtime.airdrop(&pdas.intro, 1_000);
+    let carol_lamports = runtime.lamports(&carol);
+
+    runtime
+        .process(&create(&carol, "Carol", "Hello, I am Carol"))
+        .unwrap();
+
+    let account = runtime.account(&pdas.intro).unwrap();
+    assert_eq!(account.owner, id());
+    assert_eq!(account.data.len(), len);
+    assert_eq!(account.lamports, rent);
+    assert_eq!(runtime.get::<StudentInfo>(&pdas.intro).author, carol);
+
+    // Only the rest of the intro's rent came out of Carol's wallet
+    let other_accounts: u64 = [ReplyCounter::SIZE, NameIndex::SIZE, RewardMarker::SIZE]
+        .iter()
+        .map(|len| Rent::default().minimum_balance(*len))
+        .sum();
+    assert_eq!(
+        carol_lamports - runtime.lamports(&carol),
+        INTRO_CREATION_FEE + other_accounts + rent - 1_000
+    );
+}
+
+#[test]
+fn names_are_unique_ignoring_case() {
+    let (mut runtime, alice, intro) = setup();
+    let bob = runtime.create_user();
+
+    // Found by name without knowing the author
+    let index: NameIndex = runtime.get(&name_index_pda("alice", &id()).0);
+    assert_eq!(index.intro, intro.intro);
+    assert_eq!(runtime.get::<StudentInfo>(&index.intro).author, alice);
+
+    assert_eq!(
+        runtime.process(&create(&bob, "ALICE", "Hello, I am also Alice")),
+        Err(StudentIntroError::NameTaken.into())
+    );
+    assert!(runtime.account(&user_intro_pda(&bob, &id()).0).is_none());
+
+    // Renaming frees the old name for someone else
+    runtime
+        .process(&update(&alice, "Alice", "Alicia", "Hello, I am Alicia"))
+        .unwrap();
+    runtime
+        .process(&create(&bob, "ALICE", "Hello, I am also Alice"))
+        .unwrap();
+    let index: NameIndex = runtime.get(&name_index_pda("Alice", &id()).0);
+    assert_eq!(index.intro, user_intro_pda(&bob, &id()).0);
+}
+
+#[test]
+fn message_length_is_counted_in_characters() {
+    let (mut runtime, alice, intro) = setup();
+    let carol = runtime.create_user();
+    // Three bytes each, so the longest message is still well under the account limit
+    let longest = "日".repeat(MAX_MESSAGE_CHARS);
+    let too_long = "日".repeat(MAX_MESSAGE_CHARS + 1);
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", &too_long)),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    runtime.process(&create(&carol, "Carol", &longest)).unwrap();
+    assert_eq!(
+        runtime
+            .get::<StudentInfo>(&user_intro_pda(&carol, &id()).0)
+            .msg,
+        longest
+    );
+
+    assert_eq!(
+        runtime.process(&update(&alice, "Alice", "Alice", &too_long)),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    runtime
+        .process(&update(&alice, "Alice", "Alice", &longest))
+        .unwrap();
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).msg, longest);
+}
+
+#[test]
+fn intro_accounts_fill_exactly_the_length_limit() {
+    let (mut runtime, alice, intro) = setup();
+    let carol = runtime.create_user();
+    let at_limit = |name: &str| message_for_account_len(name, MAX_INTRO_ACCOUNT_LEN);
+    let over_limit = |name: &str| message_for_account_len(name, MAX_INTRO_ACCOUNT_LEN + 1);
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", &over_limit("Carol"))),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    runtime
+        .process(&create(&carol, "Carol", &at_limit("Carol")))
+        .unwrap();
+    let carol_intro = user_intro_pda(&carol, &id()).0;
+    assert_eq!(
+        runtime.account(&carol_intro).unwrap().data.len(),
+        MAX_INTRO_ACCOUNT_LEN
+    );
+
+    assert_eq!(
+        runtime.process(&update(&alice, "Alice", "Alice", &over_limit("Alice"))),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    runtime
+        .process(&update(&alice, "Alice", "Alice", &at_limit("Alice")))
+        .unwrap();
+    assert_eq!(
+        runtime.account(&intro.intro).unwrap().data.len(),
+        MAX_INTRO_ACCOUNT_LEN
+    );
+}
+
+#[test]
+fn tampered_type_tags_are_rejected() {
+    let (mut runtime, alice, intro) = setup();
+    let original = runtime.account(&intro.intro).unwrap().clone();
+
+    // The type tag follows the 4-byte magic and the version byte
+    for (offset, byte) in [(5, AccountType::Reply as u8), (5, 0xff), (0, 0)] {
+        let mut tampered = original.clone();
+        tampered.data[offset] = byte;
+        runtime.set_account(intro.intro, tampered);
+        assert_eq!(
+            runtime.process(&update(&alice, "Alice", "Alice", "Hello, I am Alie!")),
+            Err(StudentIntroError::IncorrectAccountError.into()),
+            "byte {} set to {}",
+            offset,
+            byte
+        );
+    }
+}
+
+#[test]
+fn avatar_is_set_kept_and_cleared() {
+    let (mut runtime, alice, intro) = setup();
+    let carol = runtime.create_user();
+    let avatar = "https://example.com/carol.png";
+    let create_with_avatar = |avatar: &str| {
+        IntroInstruction::init_user_input(
+            &id(),
+            &carol,
+            "Carol".to_string(),
+            "Hello, I am Carol".to_string(),
+            None,
+            Some(avatar.to_string()),
+        )
+    };
+    let set_avatar = |avatar: Option<&str>| {
+        IntroInstruction::update_student_intro(
+            &id(),
+            &alice,
+            "Alice",
+            "Alice".to_string(),
+            "Hello, I am Alice".to_string(),
+            avatar.map(String::from),
+        )
+    };
+
+    for invalid in [
+        "https://example.com/a b.png".to_string(),
+        "a".repeat(MAX_AVATAR_URI_LEN + 1),
+    ] {
+        assert_eq!(
+            runtime.process(&create_with_avatar(&invalid)),
+            Err(StudentIntroError::InvalidInput.into())
+        );
+    }
+    runtime.process(&create_with_avatar(avatar)).unwrap();
+    let carol_intro = user_intro_pda(&carol, &id()).0;
+    assert_eq!(runtime.get::<StudentInfo>(&carol_intro).avatar_uri, avatar);
+    assert_eq!(
+        runtime.account(&carol_intro).unwrap().data.len(),
+        StudentInfo::get_account_size("Carol", "Hello, I am Carol", avatar).unwrap()
+    );
+
+    runtime.process(&set_avatar(Some(avatar))).unwrap();
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).avatar_uri, avatar);
+    // Leaving it out keeps the current one
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+    runtime.process(&set_avatar(None)).unwrap();
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).avatar_uri, avatar);
+
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+    runtime.process(&set_avatar(Some(""))).unwrap();
+    assert_eq!(runtime.get::<StudentInfo>(&intro.intro).avatar_uri, "");
+    assert_eq!(
+        runtime.account(&intro.intro).unwrap().data.len(),
+        StudentInfo::get_account_size("Alice", "Hello, I am Alice", "").unwrap()
+    );
+}
+
+#[test]
+fn message_chunks_reassemble_in_order() {
+    let (mut runtime, alice, intro) = setup();
+
+    for (index, text) in [(0, " I study chemistry"), (1, " and play chess.")] {
+        runtime
+            .process(&IntroInstruction::append_message_chunk(
+                &id(),
+                &alice,
+                index,
+                text.to_string(),
+            ))
+            .unwrap();
+    }
+
+    let state: StudentInfo = runtime.get(&intro.intro);
+    assert_eq!(state.message_chunks, 2);
+    let message = (0..state.message_chunks).fold(state.msg, |message, index| {
+        let chunk: MessageChunk = runtime.get(&intro.chunk(index));
+        assert_eq!((chunk.intro, chunk.index), (intro.intro, index));
+        message + &chunk.text
+    });
+    assert_eq!(
+        message,
+        "Hello, I am Alice I study chemistry and play chess."
+    );
+}
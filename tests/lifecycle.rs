@@ -0,0 +1,242 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{runtime::Runtime, IntroPdas, TOKEN};
+use solana_program::{program_pack::Pack, rent::Rent};
+use solana_student_intros_program::{
+    instruction::CREATED_ACCOUNTS_VERSION,
+    prelude::*,
+    processor::{
+        ADMIN_PUBKEY, EDIT_COOLDOWN_SECONDS, INTRO_CREATION_FEE, MIN_REPLY_INTERVAL_SECONDS,
+    },
+};
+use spl_token::state::Mint;
+
+#[test]
+fn initialize_mint_as_admin() {
+    let runtime = Runtime::with_mint();
+
+    let mint = Mint::unpack(&runtime.account(&common::token_mint()).unwrap().data).unwrap();
+    assert_eq!(mint.mint_authority, Some(mint_auth_pda(&id()).0).into());
+    assert_eq!(mint.decimals, 9);
+    assert_eq!(mint.supply, 0);
+    assert!(runtime.lamports(&ADMIN_PUBKEY) < common::STARTING_LAMPORTS);
+}
+
+#[test]
+fn intro_and_replies_mint_rewards() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+
+    let created_at = runtime.unix_timestamp();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    let state: StudentInfo = runtime.get(&intro.intro);
+    assert_eq!(state.author, alice);
+    assert_eq!(state.name, "Alice");
+    assert_eq!(state.msg, "Hello, I am Alice");
+    assert_eq!(state.created_at, created_at);
+    assert_eq!(
+        runtime.account(&intro.intro).unwrap().data.len(),
+        StudentInfo::get_account_size("Alice", "Hello, I am Alice", "").unwrap()
+    );
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    assert!(counter.is_initialized);
+    assert_eq!(counter.counter, 0);
+    let name_index: NameIndex = runtime.get(&intro.name_index);
+    assert_eq!(name_index.intro, intro.intro);
+    assert_eq!(runtime.lamports(&treasury_pda(&id()).0), INTRO_CREATION_FEE);
+    assert_eq!(runtime.token_balance(&alice), 10 * TOKEN);
+    assert!(runtime
+        .intro_events()
+        .iter()
+        .any(|event| matches!(event, IntroEvent::StudentIntroCreated(created) if created.intro == intro.intro)));
+
+    // Bob needs tokens of his own to pay the reply burn
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    let first = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let second = runtime.add_reply(&bob, &intro, "See you in class");
+
+    assert_eq!(first, intro.reply(0));
+    assert_eq!(second, intro.reply(1));
+    for (address, text) in [(first, "Welcome, Alice"), (second, "See you in class")] {
+        let reply: Reply = runtime.get(&address);
+        assert_eq!(reply.studentinfo, intro.intro);
+        assert_eq!(reply.replier, bob);
+        assert_eq!(reply.reply, text);
+        assert_eq!(reply.likes, 0);
+        assert!(!reply.deleted);
+        assert!(
+            runtime.lamports(&address)
+                >= Rent::default().minimum_balance(runtime.account(&address).unwrap().data.len())
+        );
+    }
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    assert_eq!(counter.counter, 2);
+    assert_eq!(counter.last_reply_at, runtime.unix_timestamp());
+    let index: ReplyIndex = runtime.get(&intro.reply_index);
+    assert_eq!(index.recent, vec![first, second]);
+
+    // Each reply burns a token and mints one back
+    let bob_balance = 10 * TOKEN;
+    assert_eq!(runtime.token_balance(&alice), 10 * TOKEN);
+    assert_eq!(runtime.token_balance(&bob), bob_balance);
+    let mint = Mint::unpack(&runtime.account(&common::token_mint()).unwrap().data).unwrap();
+    assert_eq!(mint.supply, 10 * TOKEN + bob_balance);
+}
+
+#[test]
+fn pdas_match_the_builders() {
+    let author = solana_program::pubkey::Pubkey::new_unique();
+    let pdas = IntroPdas::new(&author, "Alice");
+    let instruction = IntroInstruction::init_user_input(
+        &id(),
+        &author,
+        "Alice".to_string(),
+        "Hello".to_string(),
+        None,
+        None,
+    );
+    let keys: Vec<_> = instruction
+        .accounts
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    for address in [
+        pdas.intro,
+        pdas.reply_counter,
+        pdas.name_index,
+        pdas.reward_marker,
+        pdas.token_account,
+    ] {
+        assert!(keys.contains(&address));
+    }
+}
+
+#[test]
+fn accounts_are_created_at_the_pda_helpers() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    let intro = user_intro_pda(&alice, &id()).0;
+    let pdas = IntroPdas::new(&alice, "Alice");
+    runtime.add_reply(&bob, &pdas, "Welcome, Alice");
+    let reply = reply_pda(&intro, 0, &id()).0;
+
+    for address in [
+        intro,
+        reply_counter_pda(&intro, &id()).0,
+        name_index_pda("Alice", &id()).0,
+        intro_reward_marker_pda(&alice, &id()).0,
+        reply,
+        reply_index_pda(&intro, &id()).0,
+        reply_reward_marker_pda(&reply, &bob, &id()).0,
+    ] {
+        let account = runtime.account(&address).unwrap();
+        assert_eq!(account.owner, id(), "{}", address);
+    }
+    assert_eq!(
+        runtime.account(&token_mint_pda(&id()).0).unwrap().owner,
+        spl_token::ID
+    );
+    // The treasury only ever holds lamports
+    assert_eq!(
+        runtime.lamports(&treasury_pda(&id()).0),
+        2 * INTRO_CREATION_FEE
+    );
+}
+
+#[test]
+fn processor_accepts_every_builder() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    let carol = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+
+    let instructions = [
+        IntroInstruction::update_student_intro(
+            &id(),
+            &alice,
+            "Alice",
+            "Alice".to_string(),
+            "Hello, it is Alice".to_string(),
+            None,
+        ),
+        IntroInstruction::init_or_update(
+            &id(),
+            &bob,
+            Some("Bob"),
+            "Bob".to_string(),
+            "Hello, it is Bob".to_string(),
+            None,
+            None,
+        ),
+        IntroInstruction::add_reply(&id(), &bob, &intro.intro, 0, "Welcome".to_string()),
+    ];
+    for instruction in &instructions {
+        runtime.process(instruction).unwrap();
+    }
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let instructions = [
+        IntroInstruction::add_replies_batch(
+            &id(),
+            &bob,
+            &intro.intro,
+            1,
+            vec!["See you".to_string(), "In class".to_string()],
+        ),
+        IntroInstruction::append_message_chunk(&id(), &alice, 0, " and more".to_string()),
+        IntroInstruction::transfer_intro_ownership(&id(), &alice, &carol, "Alice", 1),
+        IntroInstruction::close_student_intro(&id(), &carol, "Alice", 1),
+        IntroInstruction::close_orphan_reply(&id(), &bob, &intro.intro, 0),
+    ];
+    for instruction in &instructions {
+        runtime.process(instruction).unwrap();
+    }
+    assert!(runtime.account(&intro.reply(0)).is_none());
+}
+
+#[test]
+fn created_addresses_are_returned() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    let created = created_accounts(&runtime);
+    assert_eq!(created.version, CREATED_ACCOUNTS_VERSION);
+    assert_eq!(
+        created.addresses,
+        vec![intro.intro, intro.reply_counter, intro.name_index]
+    );
+
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    assert_eq!(created_accounts(&runtime).addresses, vec![reply]);
+
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    runtime
+        .process(&IntroInstruction::add_replies_batch(
+            &id(),
+            &bob,
+            &intro.intro,
+            1,
+            vec!["See you".to_string(), "In class".to_string()],
+        ))
+        .unwrap();
+    assert_eq!(
+        created_accounts(&runtime).addresses,
+        vec![intro.reply(1), intro.reply(2)]
+    );
+}
+
+fn created_accounts(runtime: &Runtime) -> CreatedAccounts {
+    let (program_id, data) = runtime.return_data().unwrap();
+    assert_eq!(*program_id, id());
+    CreatedAccounts::try_from_slice(data).unwrap()
+}
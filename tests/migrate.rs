@@ -0,0 +1,197 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{data_account, runtime::Runtime, IntroPdas};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_student_intros_program::{
+    prelude::*,
+    state::{
+        LegacyReplyCounter, LegacyStudentInfo, ReplyCounterV1, StudentInfoV1, CURRENT_VERSION,
+        V1_VERSION,
+    },
+};
+
+fn migrate(payer: &Pubkey, account: &Pubkey, reward_marker: Option<&Pubkey>) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*account, false),
+        common::system_meta(),
+    ];
+    if let Some(reward_marker) = reward_marker {
+        accounts.push(AccountMeta::new(*reward_marker, false));
+    }
+    accounts.push(common::config_meta());
+    common::instruction(InstructionTag::Migrate, &(), accounts)
+}
+
+/// Writes `state` into a program account of `len` bytes at `address`.
+fn set_legacy<T: BorshSerialize>(runtime: &mut Runtime, address: Pubkey, state: &T, len: usize) {
+    let mut data = state.try_to_vec().unwrap();
+    data.resize(len, 0);
+    runtime.set_account(address, data_account(data, &id()));
+}
+
+#[test]
+fn legacy_accounts_migrate_to_type_tags() {
+    let mut runtime = Runtime::new();
+    let alice = Pubkey::new_unique();
+    runtime.airdrop(&alice, common::STARTING_LAMPORTS);
+    let pdas = IntroPdas::new(&alice, "Alice");
+    set_legacy(
+        &mut runtime,
+        pdas.intro,
+        &LegacyStudentInfo {
+            discriminator: "studentinfo".to_string(),
+            is_initialized: true,
+            name: "Alice".to_string(),
+            msg: "Hello, I am Alice".to_string(),
+        },
+        1000,
+    );
+    set_legacy(
+        &mut runtime,
+        pdas.reply_counter,
+        &LegacyReplyCounter {
+            discriminator: "counter".to_string(),
+            is_initialized: true,
+            counter: 3,
+        },
+        1000,
+    );
+
+    runtime
+        .process(&migrate(&alice, &pdas.intro, Some(&pdas.reward_marker)))
+        .unwrap();
+    runtime
+        .process(&migrate(&alice, &pdas.reply_counter, None))
+        .unwrap();
+
+    assert_eq!(
+        account_header(&runtime.account(&pdas.intro).unwrap().data),
+        Some((CURRENT_VERSION, AccountType::StudentInfo))
+    );
+    let intro: StudentInfo = runtime.get(&pdas.intro);
+    assert_eq!(intro.author, alice);
+    assert_eq!(intro.name, "Alice");
+    assert_eq!(intro.msg, "Hello, I am Alice");
+    assert_eq!(
+        runtime.account(&pdas.intro).unwrap().data.len(),
+        StudentInfo::get_account_size("Alice", "Hello, I am Alice", "").unwrap()
+    );
+    // Legacy intros were rewarded when they were created
+    assert!(runtime.account(&pdas.reward_marker).is_some());
+    let counter: ReplyCounter = runtime.get(&pdas.reply_counter);
+    assert_eq!(counter.discriminator, AccountType::ReplyCounter);
+    assert_eq!(counter.counter, 3);
+}
+
+#[test]
+fn version_1_accounts_migrate_to_version_2() {
+    let mut runtime = Runtime::new();
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    runtime.airdrop(&alice, common::STARTING_LAMPORTS);
+    runtime.airdrop(&bob, common::STARTING_LAMPORTS);
+    let pdas = IntroPdas::new(&alice, "Alice");
+    let len = StudentInfo::get_account_size("Alice", "Hello, I am Alice", "").unwrap();
+    set_legacy(
+        &mut runtime,
+        pdas.intro,
+        &StudentInfoV1 {
+            version: V1_VERSION,
+            discriminator: AccountType::StudentInfo,
+            is_initialized: true,
+            author: alice,
+            name: "Alice".to_string(),
+            msg: "Hello, I am Alice".to_string(),
+            created_at: 100,
+            updated_at: 200,
+            rating: 4,
+            rating_count: 2,
+            rating_sum: 7,
+            avatar_uri: String::new(),
+            message_chunks: 0,
+            rewards_claimed: true,
+        },
+        len,
+    );
+    set_legacy(
+        &mut runtime,
+        pdas.reply_counter,
+        &ReplyCounterV1 {
+            version: V1_VERSION,
+            discriminator: AccountType::ReplyCounter,
+            is_initialized: true,
+            counter: 300,
+            last_reply_at: 250,
+        },
+        ReplyCounter::SIZE,
+    );
+    // Replies only gained the magic bytes, so a version 1 reply is a current one without them
+    let reply = common::reply_state(&pdas.intro, &bob, "Welcome, Alice");
+    let mut data = reply.try_to_vec().unwrap()[ACCOUNT_MAGIC.len()..].to_vec();
+    data[0] = V1_VERSION;
+    data.resize(Reply::get_account_size("Welcome, Alice", None).unwrap(), 0);
+    runtime.set_account(pdas.reply(0), data_account(data, &id()));
+
+    runtime
+        .process(&migrate(&alice, &pdas.intro, Some(&pdas.reward_marker)))
+        .unwrap();
+    runtime
+        .process(&migrate(&alice, &pdas.reply_counter, None))
+        .unwrap();
+    runtime
+        .process(&migrate(&bob, &pdas.reply(0), None))
+        .unwrap();
+
+    let intro: StudentInfo = runtime.get(&pdas.intro);
+    assert_eq!(intro.version, CURRENT_VERSION);
+    assert_eq!((intro.created_at, intro.updated_at), (100, 200));
+    assert_eq!((intro.rating_sum, intro.rating_count), (7, 2));
+    // The claimed reward moves to the author's marker
+    assert!(runtime.account(&pdas.reward_marker).is_some());
+    let counter: ReplyCounter = runtime.get(&pdas.reply_counter);
+    assert_eq!(counter.version, CURRENT_VERSION);
+    assert_eq!((counter.counter, counter.first_index), (300, 0));
+    assert_eq!(counter.last_reply_at, 250);
+    let migrated: Reply = runtime.get(&pdas.reply(0));
+    assert_eq!(migrated.magic, ACCOUNT_MAGIC);
+    assert_eq!(migrated.version, CURRENT_VERSION);
+    assert_eq!(migrated.replier, bob);
+    assert_eq!(migrated.reply, "Welcome, Alice");
+}
+
+#[test]
+fn current_intros_are_left_as_they_are() {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    runtime
+        .process(&IntroInstruction::init_user_input(
+            &id(),
+            &alice,
+            "Alice".to_string(),
+            "Hello, I am Alice".to_string(),
+            None,
+            Some("https://example.com/alice.png".to_string()),
+        ))
+        .unwrap();
+    let pdas = IntroPdas::new(&alice, "Alice");
+    let before = runtime.account(&pdas.intro).unwrap().clone();
+
+    runtime
+        .process(&migrate(
+            &alice,
+            &pdas.intro,
+            Some(&intro_reward_marker_pda(&alice, &id()).0),
+        ))
+        .unwrap();
+
+    assert_eq!(runtime.account(&pdas.intro), Some(&before));
+    assert_eq!(
+        runtime.get::<StudentInfo>(&pdas.intro).avatar_uri,
+        "https://example.com/alice.png"
+    );
+}
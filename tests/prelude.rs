@@ -0,0 +1,91 @@
+//! Names every item of the prelude, so dropping or renaming one fails to compile.
+
+use solana_program::pubkey::Pubkey;
+use solana_student_intros_program::prelude::{
+    account_header, block_list_pda, check_id, config_pda, flag_marker_pda, id, identify_account,
+    intro_reward_marker_pda, like_marker_pda, message_chunk_pda, mint_auth_pda, name_index_pda,
+    process_instruction, rating_marker_pda, reaction_marker_pda, reply_counter_pda,
+    reply_index_pda, reply_pda, reply_pdas_for, reply_reward_marker_pda, token_metadata_pda,
+    token_mint_pda, treasury_pda, user_intro_pda, word_denylist_pda, AccountType, BlockList,
+    Config, CreatedAccounts, FlagMarker, FlagReason, InstructionTag, IntroEvent, IntroInstruction,
+    IntroStats, LikeMarker, MessageChunk, NameIndex, RatingMarker, ReactionKind, ReactionMarker,
+    Reply, ReplyCounter, ReplyEdit, ReplyIndex, ReplyPage, RewardMarker, StudentInfo,
+    StudentIntroError, TypedAccount, WordDenylist, ACCOUNT_MAGIC, ID,
+};
+
+#[allow(dead_code)]
+struct Types(
+    AccountType,
+    BlockList,
+    Config,
+    CreatedAccounts,
+    FlagMarker,
+    FlagReason,
+    InstructionTag,
+    IntroEvent,
+    IntroStats,
+    LikeMarker,
+    MessageChunk,
+    NameIndex,
+    RatingMarker,
+    ReactionKind,
+    ReactionMarker,
+    Reply,
+    ReplyCounter,
+    ReplyEdit,
+    ReplyIndex,
+    ReplyPage,
+    RewardMarker,
+    StudentInfo,
+    StudentIntroError,
+    WordDenylist,
+);
+
+fn is_typed_account<T: TypedAccount>() {}
+
+#[test]
+fn prelude_reaches_the_supported_surface() {
+    let program_id = id();
+    assert!(check_id(&program_id));
+    assert_eq!(program_id, ID);
+    let user = Pubkey::new_unique();
+    let (intro, _bump) = user_intro_pda(&user, &program_id);
+    let (reply, _bump) = reply_pda(&intro, 0, &program_id);
+    let (mint, _bump) = token_mint_pda(&program_id);
+
+    for address in [
+        block_list_pda(&program_id).0,
+        config_pda(&program_id).0,
+        flag_marker_pda(&reply, &user, &program_id).0,
+        intro_reward_marker_pda(&user, &program_id).0,
+        like_marker_pda(&reply, &user, &program_id).0,
+        message_chunk_pda(&intro, 0, &program_id).0,
+        mint_auth_pda(&program_id).0,
+        name_index_pda("Alice", &program_id).0,
+        rating_marker_pda(&intro, &user, &program_id).0,
+        reaction_marker_pda(&reply, &user, ReactionKind::Heart, &program_id).0,
+        reply_counter_pda(&intro, &program_id).0,
+        reply_index_pda(&intro, &program_id).0,
+        reply_reward_marker_pda(&reply, &user, &program_id).0,
+        token_metadata_pda(&mint).0,
+        treasury_pda(&program_id).0,
+        word_denylist_pda(&program_id).0,
+    ] {
+        assert_ne!(address, intro);
+    }
+    assert_eq!(reply_pdas_for(&intro, 0..1, &program_id), vec![reply]);
+
+    let instruction = IntroInstruction::init_user_input(
+        &program_id,
+        &user,
+        "Alice".to_string(),
+        "Hello, I am Alice".to_string(),
+        None,
+        None,
+    );
+    assert_eq!(instruction.accounts[1].pubkey, intro);
+    assert!(account_header(&[]).is_none());
+    assert!(identify_account(&ACCOUNT_MAGIC).is_none());
+    is_typed_account::<StudentInfo>();
+    let _process = process_instruction;
+}
@@ -0,0 +1,951 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{runtime::Runtime, IntroPdas};
+use solana_program::{
+    entrypoint::ProgramResult,
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::IsInitialized,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_program,
+};
+use solana_student_intros_program::{
+    prelude::*,
+    processor::{ADMIN_PUBKEY, MIN_REPLY_INTERVAL_SECONDS, REPLY_BURN_AMOUNT, REPLY_REWARD},
+    state::{
+        MAX_BATCH_REPLIES, MAX_LIST_REPLIES, MAX_REPLIES, MAX_REPLY_CHARS, RECENT_REPLIES,
+        REPLY_EDIT_HISTORY,
+    },
+};
+
+/// Alice's intro, and Bob with an intro of his own so he holds tokens for the reply burn.
+fn setup() -> (Runtime, Pubkey, Pubkey, IntroPdas) {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    (runtime, alice, bob, intro)
+}
+
+/// Checks `address` holds an emptied tombstone at rent-exempt size and returns its rent.
+fn assert_tombstone(runtime: &Runtime, address: &Pubkey) -> u64 {
+    let account = runtime.account(address).unwrap();
+    let reply: Reply = common::state(account);
+    assert!(reply.deleted && !reply.is_initialized());
+    assert!(reply.reply.is_empty() && reply.edits.is_empty());
+    assert_eq!(
+        account.data.len(),
+        Reply::get_account_size("", reply.parent.as_ref()).unwrap()
+    );
+    assert_eq!(
+        account.lamports,
+        Rent::default().minimum_balance(account.data.len())
+    );
+    account.lamports
+}
+
+/// `replier` is only passed when the intro's author deletes someone else's reply, to
+/// receive its rent.
+fn delete_reply(
+    authority: &Pubkey,
+    intro: &IntroPdas,
+    index: u64,
+    replier: Option<&Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new_readonly(intro.intro, false),
+        AccountMeta::new_readonly(intro.reply_counter, false),
+        AccountMeta::new(intro.reply(index), false),
+    ];
+    if let Some(replier) = replier {
+        accounts.push(AccountMeta::new(*replier, false));
+    }
+    accounts.push(common::config_meta());
+    common::instruction(InstructionTag::DeleteReply, &index, accounts)
+}
+
+fn update_reply(replier: &Pubkey, reply: &Pubkey, text: &str) -> Instruction {
+    common::instruction(
+        InstructionTag::UpdateReply,
+        &text.to_string(),
+        vec![
+            AccountMeta::new(*replier, true),
+            AccountMeta::new(*reply, false),
+            common::denylist_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn soft_delete(authority: &Pubkey, reply: &Pubkey, deleted: bool) -> Instruction {
+    common::instruction(
+        InstructionTag::SoftDeleteReply,
+        &deleted,
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*reply, false),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn like_reply(liker: &Pubkey, reply: &Pubkey) -> Instruction {
+    common::instruction(
+        InstructionTag::LikeReply,
+        &(),
+        vec![
+            AccountMeta::new(*liker, true),
+            AccountMeta::new(*reply, false),
+            AccountMeta::new(like_marker_pda(reply, liker, &id()).0, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn react(reactor: &Pubkey, reply: &Pubkey, kind: ReactionKind) -> Instruction {
+    common::instruction(
+        InstructionTag::AddReaction,
+        &kind,
+        vec![
+            AccountMeta::new(*reactor, true),
+            AccountMeta::new(*reply, false),
+            AccountMeta::new(reaction_marker_pda(reply, reactor, kind, &id()).0, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn flag(reporter: &Pubkey, reply: &Pubkey, reason: u8) -> Instruction {
+    common::instruction(
+        InstructionTag::FlagReply,
+        &reason,
+        vec![
+            AccountMeta::new(*reporter, true),
+            AccountMeta::new(*reply, false),
+            AccountMeta::new(flag_marker_pda(reply, reporter, &id()).0, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+/// Replies to `parent` at its next child index.
+fn add_nested_reply(
+    runtime: &mut Runtime,
+    replier: &Pubkey,
+    intro: &Pubkey,
+    parent: &Pubkey,
+    text: &str,
+) -> ProgramResult {
+    let child_counter = reply_counter_pda(parent, &id()).0;
+    let index = runtime
+        .account(&child_counter)
+        .map_or(0, |_| runtime.get::<ReplyCounter>(&child_counter).counter);
+    runtime.process(&common::instruction(
+        InstructionTag::AddNestedReply,
+        &(text.to_string(), *parent),
+        vec![
+            AccountMeta::new(*replier, true),
+            AccountMeta::new_readonly(*intro, false),
+            AccountMeta::new_readonly(*parent, false),
+            AccountMeta::new(child_counter, false),
+            AccountMeta::new(reply_pda(parent, index, &id()).0, false),
+            common::system_meta(),
+            AccountMeta::new_readonly(block_list_pda(&id()).0, false),
+            common::denylist_meta(),
+            common::config_meta(),
+        ],
+    ))
+}
+
+#[test]
+fn replies_past_index_255_get_their_own_accounts() {
+    let (mut runtime, _alice, bob, intro) = setup();
+
+    for index in 0..258u64 {
+        let reply = runtime.add_reply(&bob, &intro, &format!("Reply {}", index));
+        assert_eq!(reply, intro.reply(index));
+    }
+
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    assert_eq!(counter.counter, 258);
+    for index in [0, 255, 256, 257] {
+        let reply: Reply = runtime.get(&intro.reply(index));
+        assert_eq!(reply.reply, format!("Reply {}", index));
+    }
+}
+
+#[test]
+fn replies_wait_out_the_reply_interval() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    runtime.create_intro(&carol, "Carol", "Hello, I am Carol");
+    runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let reply = IntroInstruction::add_reply(&id(), &carol, &intro.intro, 1, "Hi Alice".to_string());
+
+    // The interval is per intro, so it holds back other repliers too
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS - 1);
+    assert_eq!(
+        runtime.process(&reply),
+        Err(StudentIntroError::CooldownActive.into())
+    );
+    assert!(runtime.account(&intro.reply(1)).is_none());
+
+    runtime.advance_clock(1);
+    runtime.process(&reply).unwrap();
+    assert_eq!(runtime.get::<Reply>(&intro.reply(1)).replier, carol);
+}
+
+#[test]
+fn replier_must_sign() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    let mut instruction =
+        IntroInstruction::add_reply(&id(), &bob, &intro.intro, 0, "Welcome".to_string());
+    instruction.accounts[0].is_signer = false;
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert!(runtime.account(&intro.reply(0)).is_none());
+}
+
+#[test]
+fn reply_counter_must_be_owned_by_the_program() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let mut counter = runtime.account(&intro.reply_counter).unwrap().clone();
+    counter.owner = system_program::ID;
+    runtime.set_account(intro.reply_counter, counter);
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_reply(
+            &id(),
+            &bob,
+            &intro.intro,
+            0,
+            "Welcome".to_string()
+        )),
+        Err(ProgramError::IllegalOwner)
+    );
+}
+
+#[test]
+fn reply_needs_the_system_program() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    let mut instruction =
+        IntroInstruction::add_reply(&id(), &bob, &intro.intro, 0, "Welcome".to_string());
+    instruction.accounts[4].pubkey = spl_token::ID;
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+}
+
+#[test]
+fn replier_deletes_their_reply() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let first = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let second = runtime.add_reply(&bob, &intro, "See you in class");
+    let rent = runtime.lamports(&first);
+    let bob_lamports = runtime.lamports(&bob);
+
+    runtime
+        .process(&delete_reply(&bob, &intro, 0, None))
+        .unwrap();
+
+    let left = assert_tombstone(&runtime, &first);
+    assert_eq!(runtime.lamports(&bob), bob_lamports + rent - left);
+    // The index stays taken, so the earlier reply's markers are never inherited
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    assert_eq!(counter.counter, 2);
+    let reply: Reply = runtime.get(&second);
+    assert_eq!(reply.reply, "See you in class");
+}
+
+#[test]
+fn intro_author_deletes_replies_to_the_replier() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let rent = runtime.lamports(&reply);
+    let (alice_lamports, bob_lamports) = (runtime.lamports(&alice), runtime.lamports(&bob));
+
+    assert_eq!(
+        runtime.process(&delete_reply(&alice, &intro, 0, Some(&carol))),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+    runtime
+        .process(&delete_reply(&alice, &intro, 0, Some(&bob)))
+        .unwrap();
+
+    let left = assert_tombstone(&runtime, &reply);
+    assert_eq!(runtime.lamports(&bob), bob_lamports + rent - left);
+    assert_eq!(runtime.lamports(&alice), alice_lamports);
+    assert_eq!(
+        runtime.process(&delete_reply(&alice, &intro, 0, Some(&bob))),
+        Err(StudentIntroError::UninitializedAccount.into())
+    );
+}
+
+#[test]
+fn only_the_replier_or_intro_author_deletes_a_reply() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    for replier in [None, Some(&bob)] {
+        assert_eq!(
+            runtime.process(&delete_reply(&carol, &intro, 0, replier)),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+    }
+    assert!(runtime.account(&reply).is_some());
+}
+
+#[test]
+fn replier_edits_within_the_original_length() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let len = runtime.account(&reply).unwrap().data.len();
+
+    runtime
+        .process(&update_reply(&bob, &reply, "Hi there Alice"))
+        .unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).reply, "Hi there Alice");
+    runtime.process(&update_reply(&bob, &reply, "Hi")).unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).reply, "Hi");
+    assert_eq!(runtime.account(&reply).unwrap().data.len(), len);
+
+    assert_eq!(
+        runtime.process(&update_reply(&bob, &reply, "Welcome, Alice!")),
+        Err(StudentIntroError::InvalidDataLength.into())
+    );
+    assert_eq!(runtime.get::<Reply>(&reply).reply, "Hi");
+}
+
+#[test]
+fn edit_history_keeps_the_latest_edits() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let texts = [
+        "Welcome, Alice",
+        "Hi there Alice",
+        "Hey Alice",
+        "Hi Alice",
+        "Hi",
+    ];
+
+    let mut edited_at = Vec::new();
+    for text in &texts[1..] {
+        runtime.advance_clock(60);
+        runtime.process(&update_reply(&bob, &reply, text)).unwrap();
+        edited_at.push(runtime.unix_timestamp());
+    }
+
+    // Four edits, so the first one has rolled out of the ring
+    let state: Reply = runtime.get(&reply);
+    assert_eq!(state.reply, "Hi");
+    assert_eq!(state.edits.len(), REPLY_EDIT_HISTORY);
+    for (edit, (previous, edited_at)) in state
+        .edits
+        .iter()
+        .zip(texts[1..4].iter().zip(&edited_at[1..]))
+    {
+        assert_eq!(edit.previous_hash, hash(previous.as_bytes()).to_bytes());
+        assert_eq!(edit.edited_at, *edited_at);
+    }
+}
+
+#[test]
+fn only_the_replier_edits_a_reply() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    assert_eq!(
+        runtime.process(&update_reply(&alice, &reply, "Hi there Alice")),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    let mut unsigned = update_reply(&bob, &reply, "Hi there Alice");
+    unsigned.accounts[0].is_signer = false;
+    assert_eq!(
+        runtime.process(&unsigned),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert_eq!(runtime.get::<Reply>(&reply).reply, "Welcome, Alice");
+}
+
+#[test]
+fn reply_records_the_signing_replier() {
+    let (mut runtime, alice, bob, intro) = setup();
+
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    let state: Reply = runtime.get(&reply);
+    assert_eq!(state.replier, bob);
+    assert_ne!(state.replier, alice);
+    assert!(runtime
+        .intro_events()
+        .iter()
+        .any(|event| matches!(event, IntroEvent::ReplyAdded(added) if added.replier == bob)));
+}
+
+#[test]
+fn reply_length_is_counted_in_characters() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let reply =
+        |text: &str| IntroInstruction::add_reply(&id(), &bob, &intro.intro, 0, text.to_string());
+
+    for text in [
+        String::new(),
+        " ".repeat(3),
+        "é".repeat(MAX_REPLY_CHARS + 1),
+    ] {
+        assert_eq!(
+            runtime.process(&reply(&text)),
+            Err(StudentIntroError::InvalidInput.into())
+        );
+    }
+
+    let longest = "é".repeat(MAX_REPLY_CHARS);
+    runtime.process(&reply(&longest)).unwrap();
+    assert_eq!(runtime.get::<Reply>(&intro.reply(0)).reply, longest);
+    assert_eq!(
+        runtime.account(&intro.reply(0)).unwrap().data.len(),
+        Reply::get_account_size(&longest, None).unwrap()
+    );
+}
+
+#[test]
+fn each_account_likes_a_reply_once() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    runtime.process(&like_reply(&alice, &reply)).unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).likes, 1);
+    assert_eq!(
+        runtime.process(&like_reply(&alice, &reply)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    runtime.process(&like_reply(&carol, &reply)).unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).likes, 2);
+
+    let mut state: Reply = runtime.get(&reply);
+    state.likes = u64::MAX;
+    runtime.set_state(&reply, &state);
+    let dave = runtime.create_user();
+    assert_eq!(
+        runtime.process(&like_reply(&dave, &reply)),
+        Err(StudentIntroError::Overflow.into())
+    );
+    assert_eq!(runtime.get::<Reply>(&reply).likes, u64::MAX);
+}
+
+#[test]
+fn author_cannot_reply_to_own_intro() {
+    let (mut runtime, alice, _bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    for instruction in [
+        IntroInstruction::add_reply(&id(), &alice, &intro.intro, 0, "Hi me".to_string()),
+        IntroInstruction::add_replies_batch(
+            &id(),
+            &alice,
+            &intro.intro,
+            0,
+            vec!["Hi me".to_string(), "Hi again".to_string()],
+        ),
+    ] {
+        assert_eq!(
+            runtime.process(&instruction),
+            Err(StudentIntroError::InvalidInput.into())
+        );
+    }
+    assert_eq!(runtime.get::<ReplyCounter>(&intro.reply_counter).counter, 0);
+    assert_eq!(runtime.token_balance(&alice), 10 * common::TOKEN);
+}
+
+#[test]
+fn reply_counter_does_not_wrap() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let mut counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    counter.counter = u64::MAX;
+    counter.first_index = u64::MAX - 1;
+    runtime.set_state(&intro.reply_counter, &counter);
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_reply(
+            &id(),
+            &bob,
+            &intro.intro,
+            u64::MAX,
+            "Welcome".to_string()
+        )),
+        Err(StudentIntroError::Overflow.into())
+    );
+    assert_eq!(
+        runtime.get::<ReplyCounter>(&intro.reply_counter).counter,
+        u64::MAX
+    );
+}
+
+#[test]
+fn intro_takes_at_most_max_replies() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    // Skips ahead rather than sending every reply before the last one
+    let mut counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    counter.counter = MAX_REPLIES - 1;
+    runtime.set_state(&intro.reply_counter, &counter);
+
+    let last = runtime.add_reply(&bob, &intro, "Last one");
+    assert_eq!(last, intro.reply(MAX_REPLIES - 1));
+
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_reply(
+            &id(),
+            &bob,
+            &intro.intro,
+            MAX_REPLIES,
+            "One too many".to_string()
+        )),
+        Err(StudentIntroError::ReplyLimitReached.into())
+    );
+    assert!(runtime.account(&intro.reply(MAX_REPLIES)).is_none());
+}
+
+#[test]
+fn nested_replies_link_to_their_parent() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let top = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    add_nested_reply(&mut runtime, &alice, &intro.intro, &top, "Thanks, Bob").unwrap();
+    let child = reply_pda(&top, 0, &id()).0;
+    add_nested_reply(&mut runtime, &bob, &intro.intro, &child, "Any time").unwrap();
+    let grandchild = reply_pda(&child, 0, &id()).0;
+
+    let state: Reply = runtime.get(&child);
+    assert_eq!(state.parent, Some(top));
+    assert_eq!(state.studentinfo, intro.intro);
+    assert_eq!(state.reply, "Thanks, Bob");
+    let state: Reply = runtime.get(&grandchild);
+    assert_eq!(state.parent, Some(child));
+    assert_eq!(state.replier, bob);
+    assert_eq!(
+        runtime
+            .get::<ReplyCounter>(&reply_counter_pda(&top, &id()).0)
+            .counter,
+        1
+    );
+    // Nested replies have their own counters, so the intro's stays at the top level
+    assert_eq!(runtime.get::<ReplyCounter>(&intro.reply_counter).counter, 1);
+}
+
+#[test]
+fn nested_reply_parent_must_belong_to_the_intro() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let top = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let bob_intro = IntroPdas::new(&bob, "Bob");
+
+    assert_eq!(
+        add_nested_reply(&mut runtime, &alice, &bob_intro.intro, &top, "Thanks, Bob"),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+    assert!(runtime.account(&reply_pda(&top, 0, &id()).0).is_none());
+}
+
+#[test]
+fn reactions_are_counted_per_kind_once_per_account() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    for kind in [ReactionKind::Heart, ReactionKind::Laugh] {
+        runtime.process(&react(&alice, &reply, kind)).unwrap();
+    }
+    runtime
+        .process(&react(&bob, &reply, ReactionKind::Heart))
+        .unwrap();
+    assert_eq!(
+        runtime.process(&react(&alice, &reply, ReactionKind::Heart)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+
+    let state: Reply = runtime.get(&reply);
+    assert_eq!(state.reactions[ReactionKind::ThumbsUp as usize], 0);
+    assert_eq!(state.reactions[ReactionKind::Heart as usize], 2);
+    assert_eq!(state.reactions[ReactionKind::Laugh as usize], 1);
+    assert_eq!(state.reactions[ReactionKind::Celebrate as usize], 0);
+}
+
+#[test]
+fn replies_need_an_initialized_intro_owned_by_the_program() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let len = runtime.account(&intro.intro).unwrap().data.len();
+    let reply_to =
+        |intro: &Pubkey| IntroInstruction::add_reply(&id(), &bob, intro, 0, "Welcome".to_string());
+
+    let stranger = Pubkey::new_unique();
+    runtime.set_account(
+        stranger,
+        common::data_account(vec![0; len], &system_program::ID),
+    );
+    assert_eq!(
+        runtime.process(&reply_to(&stranger)),
+        Err(ProgramError::IllegalOwner)
+    );
+
+    let empty = Pubkey::new_unique();
+    runtime.set_account(empty, common::data_account(vec![0; len], &id()));
+    assert_eq!(
+        runtime.process(&reply_to(&empty)),
+        Err(StudentIntroError::UninitializedAccount.into())
+    );
+    assert!(runtime.account(&reply_pda(&empty, 0, &id()).0).is_none());
+}
+
+#[test]
+fn each_account_flags_a_reply_once_for_a_known_reason() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    runtime
+        .process(&flag(&alice, &reply, FlagReason::Spam as u8))
+        .unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).flags, 1);
+    assert_eq!(
+        runtime.process(&flag(&alice, &reply, FlagReason::OffTopic as u8)),
+        Err(ProgramError::AccountAlreadyInitialized)
+    );
+    assert_eq!(
+        runtime.process(&flag(&carol, &reply, FlagReason::Other as u8 + 1)),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    assert!(runtime
+        .account(&flag_marker_pda(&reply, &carol, &id()).0)
+        .is_none());
+
+    runtime
+        .process(&flag(&carol, &reply, FlagReason::Harassment as u8))
+        .unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).flags, 2);
+}
+
+#[test]
+fn batch_creates_sequential_replies() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let first = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let balance = runtime.token_balance(&bob);
+    let replies = ["See you", "In class", "Tomorrow"].map(String::from);
+
+    runtime
+        .process(&IntroInstruction::add_replies_batch(
+            &id(),
+            &bob,
+            &intro.intro,
+            1,
+            replies.to_vec(),
+        ))
+        .unwrap();
+
+    assert_eq!(runtime.get::<ReplyCounter>(&intro.reply_counter).counter, 4);
+    assert_eq!(runtime.get::<Reply>(&first).reply, "Welcome, Alice");
+    for (index, text) in (1..).zip(&replies) {
+        let reply: Reply = runtime.get(&intro.reply(index));
+        assert_eq!(&reply.reply, text);
+        assert_eq!(reply.replier, bob);
+    }
+    assert_eq!(
+        runtime.token_balance(&bob),
+        balance + 3 * (REPLY_REWARD - REPLY_BURN_AMOUNT)
+    );
+}
+
+#[test]
+fn batch_takes_at_most_max_batch_replies() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_replies_batch(
+            &id(),
+            &bob,
+            &intro.intro,
+            0,
+            vec!["Hi".to_string(); MAX_BATCH_REPLIES + 1],
+        )),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    assert_eq!(runtime.get::<ReplyCounter>(&intro.reply_counter).counter, 0);
+    assert!(runtime.account(&intro.reply(0)).is_none());
+}
+
+#[test]
+fn replier_and_admin_toggle_soft_delete() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let len = runtime.account(&reply).unwrap().data.len();
+
+    runtime.process(&soft_delete(&bob, &reply, true)).unwrap();
+    assert!(runtime.get::<Reply>(&reply).deleted);
+    runtime
+        .process(&soft_delete(&ADMIN_PUBKEY, &reply, false))
+        .unwrap();
+    let state: Reply = runtime.get(&reply);
+    assert!(!state.deleted);
+    assert_eq!(state.reply, "Welcome, Alice");
+    assert_eq!(runtime.account(&reply).unwrap().data.len(), len);
+}
+
+#[test]
+fn soft_deleted_replies_are_not_edited() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    runtime.process(&soft_delete(&bob, &reply, true)).unwrap();
+
+    assert_eq!(
+        runtime.process(&update_reply(&bob, &reply, "Welcome back")),
+        Err(StudentIntroError::ReplyDeleted.into())
+    );
+    let state: Reply = runtime.get(&reply);
+    assert_eq!(state.reply, "Welcome, Alice");
+    assert!(state.edits.is_empty());
+
+    runtime.process(&soft_delete(&bob, &reply, false)).unwrap();
+    runtime
+        .process(&update_reply(&bob, &reply, "Welcome back"))
+        .unwrap();
+    assert_eq!(runtime.get::<Reply>(&reply).edits.len(), 1);
+}
+
+#[test]
+fn only_the_replier_or_admin_soft_deletes() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    // Not even the intro's author
+    assert_eq!(
+        runtime.process(&soft_delete(&alice, &reply, true)),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    let mut unsigned = soft_delete(&bob, &reply, true);
+    unsigned.accounts[0].is_signer = false;
+    assert_eq!(
+        runtime.process(&unsigned),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert!(!runtime.get::<Reply>(&reply).deleted);
+}
+
+#[test]
+fn reply_handlers_reject_other_account_types() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let intro_data = runtime.account(&intro.intro).unwrap().data.clone();
+    let counter_data = runtime.account(&intro.reply_counter).unwrap().data.clone();
+
+    for instruction in [
+        like_reply(&alice, &intro.intro),
+        soft_delete(&bob, &intro.reply_counter, true),
+        flag(&alice, &intro.intro, FlagReason::Spam as u8),
+        update_reply(&bob, &intro.reply_counter, "Hi"),
+        react(&alice, &intro.intro, ReactionKind::Heart),
+    ] {
+        assert_eq!(
+            runtime.process(&instruction),
+            Err(StudentIntroError::IncorrectAccountError.into())
+        );
+    }
+    assert_eq!(runtime.account(&intro.intro).unwrap().data, intro_data);
+    assert_eq!(
+        runtime.account(&intro.reply_counter).unwrap().data,
+        counter_data
+    );
+
+    // And a reply where an intro belongs
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_reply(
+            &id(),
+            &alice,
+            &reply,
+            0,
+            "Thanks".to_string()
+        )),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+}
+
+#[test]
+fn reply_index_keeps_the_newest_replies_in_order() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let total = RECENT_REPLIES as u64 + 2;
+
+    let replies: Vec<_> = (0..total)
+        .map(|index| runtime.add_reply(&bob, &intro, &format!("Reply {}", index)))
+        .collect();
+
+    let index: ReplyIndex = runtime.get(&intro.reply_index);
+    assert_eq!(index.recent, replies[2..]);
+    assert_eq!(index.recent.first(), Some(&intro.reply(2)));
+    assert_eq!(index.recent.last(), Some(&intro.reply(total - 1)));
+}
+
+#[test]
+fn orphaned_reply_rent_goes_back_to_the_replier() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    let close_orphan = IntroInstruction::close_orphan_reply(&id(), &bob, &intro.intro, 0);
+
+    assert_eq!(
+        runtime.process(&close_orphan),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+
+    // Replies are seeded by the intro address, so moving the intro orphans them
+    let carol = runtime.create_user();
+    runtime
+        .process(&IntroInstruction::transfer_intro_ownership(
+            &id(),
+            &alice,
+            &carol,
+            "Alice",
+            0,
+        ))
+        .unwrap();
+    assert!(runtime.account(&reply).is_some());
+    let rent = runtime.lamports(&reply);
+    let bob_lamports = runtime.lamports(&bob);
+
+    // The replier need not sign, so anyone can clean up
+    runtime.process(&close_orphan).unwrap();
+    assert!(runtime.account(&reply).is_none());
+    assert_eq!(runtime.lamports(&bob), bob_lamports + rent);
+}
+
+#[test]
+fn orphaned_tombstones_are_closed_too() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    runtime
+        .process(&delete_reply(&bob, &intro, 0, None))
+        .unwrap();
+    let carol = runtime.create_user();
+    runtime
+        .process(&IntroInstruction::transfer_intro_ownership(
+            &id(),
+            &alice,
+            &carol,
+            "Alice",
+            0,
+        ))
+        .unwrap();
+    let rent = assert_tombstone(&runtime, &reply);
+    let bob_lamports = runtime.lamports(&bob);
+
+    runtime
+        .process(&IntroInstruction::close_orphan_reply(
+            &id(),
+            &bob,
+            &intro.intro,
+            0,
+        ))
+        .unwrap();
+    assert!(runtime.account(&reply).is_none());
+    assert_eq!(runtime.lamports(&bob), bob_lamports + rent);
+}
+
+#[test]
+fn reply_pdas_for_lists_the_created_replies() {
+    let (mut runtime, _alice, bob, intro) = setup();
+
+    let created: Vec<_> = (0..3)
+        .map(|index| runtime.add_reply(&bob, &intro, &format!("Reply {}", index)))
+        .collect();
+
+    let counter: ReplyCounter = runtime.get(&intro.reply_counter);
+    let listed = reply_pdas_for(&intro.intro, counter.indices(), &id());
+    assert_eq!(listed, created);
+    for (address, index) in listed.iter().zip(0..) {
+        assert_eq!(
+            runtime.get::<Reply>(address).reply,
+            format!("Reply {}", index)
+        );
+    }
+}
+
+#[test]
+fn reply_counter_must_belong_to_the_intro() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    let bob_intro = IntroPdas::new(&bob, "Bob");
+
+    // Alice's intro with Bob's counter, and the other way round
+    for (replier, intro, counter) in [
+        (&bob, &intro, &bob_intro.reply_counter),
+        (&carol, &bob_intro, &intro.reply_counter),
+    ] {
+        let mut instruction =
+            IntroInstruction::add_reply(&id(), replier, &intro.intro, 0, "Welcome".to_string());
+        instruction.accounts[2].pubkey = *counter;
+        assert_eq!(
+            runtime.process(&instruction),
+            Err(StudentIntroError::InvalidPDA.into())
+        );
+    }
+    for counter in [&intro.reply_counter, &bob_intro.reply_counter] {
+        assert_eq!(runtime.get::<ReplyCounter>(counter).counter, 0);
+    }
+}
+
+#[test]
+fn list_replies_pages_through_the_replies() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let created: Vec<_> = (0..5)
+        .map(|index| runtime.add_reply(&bob, &intro, &format!("Reply {}", index)))
+        .collect();
+    let list = |start: u64, limit: u8| {
+        common::instruction(
+            InstructionTag::ListReplies,
+            &(start, limit),
+            vec![
+                AccountMeta::new_readonly(intro.intro, false),
+                AccountMeta::new_readonly(intro.reply_counter, false),
+            ],
+        )
+    };
+    let page = |runtime: &mut Runtime, start: u64| {
+        runtime.process(&list(start, 3)).unwrap();
+        let (program_id, data) = runtime.return_data().unwrap();
+        assert_eq!(*program_id, id());
+        ReplyPage::try_from_slice(data).unwrap()
+    };
+
+    let first = page(&mut runtime, 0);
+    assert_eq!(first.start, 0);
+    assert_eq!(first.replies, created[..3]);
+    // The second window stops at the last reply
+    let second = page(&mut runtime, 3);
+    assert_eq!(second.start, 3);
+    assert_eq!(second.replies, created[3..]);
+
+    assert_eq!(
+        runtime.process(&list(0, MAX_LIST_REPLIES + 1)),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+}
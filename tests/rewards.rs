@@ -0,0 +1,337 @@
+mod common;
+
+use common::{runtime::Runtime, IntroPdas, TOKEN};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use solana_student_intros_program::{
+    prelude::*,
+    processor::{
+        ADMIN_PUBKEY, DEFAULT_INTRO_REWARD, EDIT_COOLDOWN_SECONDS, MAX_INTRO_REWARD,
+        MIN_REPLY_INTERVAL_SECONDS, REPLY_BURN_AMOUNT, REPLY_REWARD,
+    },
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+/// Alice's intro, and Bob with an intro of his own so he holds tokens for the reply burn.
+fn setup() -> (Runtime, Pubkey, Pubkey, IntroPdas) {
+    let mut runtime = Runtime::with_mint();
+    let alice = runtime.create_user();
+    let bob = runtime.create_user();
+    let intro = runtime.create_intro(&alice, "Alice", "Hello, I am Alice");
+    runtime.create_intro(&bob, "Bob", "Hello, I am Bob");
+    (runtime, alice, bob, intro)
+}
+
+fn create(author: &Pubkey, name: &str, reward_amount: Option<u64>) -> Instruction {
+    IntroInstruction::init_user_input(
+        &id(),
+        author,
+        name.to_string(),
+        format!("Hello, I am {}", name),
+        reward_amount,
+        None,
+    )
+}
+
+fn tip(tipper: &Pubkey, author: &Pubkey, intro: &Pubkey, lamports: u64) -> Instruction {
+    common::instruction(
+        InstructionTag::TipAuthor,
+        &lamports,
+        vec![
+            AccountMeta::new(*tipper, true),
+            AccountMeta::new(*author, false),
+            AccountMeta::new_readonly(*intro, false),
+            common::system_meta(),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn burn(owner: &Pubkey, amount: u64) -> Instruction {
+    common::instruction(
+        InstructionTag::BurnTokens,
+        &amount,
+        vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(common::token_account(owner), false),
+            AccountMeta::new(common::token_mint(), false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            common::config_meta(),
+        ],
+    )
+}
+
+fn supply(runtime: &Runtime) -> u64 {
+    Mint::unpack(&runtime.account(&common::token_mint()).unwrap().data)
+        .unwrap()
+        .supply
+}
+
+#[test]
+fn reply_reward_does_not_outweigh_the_burn() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let balance = runtime.token_balance(&bob);
+    let supply_before = supply(&runtime);
+
+    let reply = runtime.add_reply(&bob, &intro, "Welcome, Alice");
+
+    assert!(runtime.token_balance(&bob) <= balance);
+    assert_eq!(
+        runtime.token_balance(&bob),
+        balance + REPLY_REWARD - REPLY_BURN_AMOUNT
+    );
+    assert_eq!(
+        supply(&runtime),
+        supply_before + REPLY_REWARD - REPLY_BURN_AMOUNT
+    );
+    assert!(runtime
+        .account(&reply_reward_marker_pda(&reply, &bob, &id()).0)
+        .is_some());
+}
+
+#[test]
+fn intro_mints_the_default_reward() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+
+    runtime.process(&create(&carol, "Carol", None)).unwrap();
+
+    assert_eq!(runtime.token_balance(&carol), DEFAULT_INTRO_REWARD);
+    assert_eq!(supply(&runtime), DEFAULT_INTRO_REWARD);
+}
+
+#[test]
+fn intro_mints_a_custom_reward_up_to_the_maximum() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    let dave = runtime.create_user();
+    // Rewards above the configured one need the admin's signature
+    let approved = |author: &Pubkey, name: &str, reward_amount: u64| {
+        let mut instruction = create(author, name, Some(reward_amount));
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(ADMIN_PUBKEY, true));
+        instruction
+    };
+
+    assert_eq!(
+        runtime.process(&approved(&carol, "Carol", MAX_INTRO_REWARD + 1)),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    assert_eq!(runtime.token_balance(&carol), 0);
+
+    runtime
+        .process(&create(&carol, "Carol", Some(3 * TOKEN)))
+        .unwrap();
+    assert_eq!(runtime.token_balance(&carol), 3 * TOKEN);
+    runtime
+        .process(&approved(&dave, "Dave", MAX_INTRO_REWARD))
+        .unwrap();
+    assert_eq!(runtime.token_balance(&dave), MAX_INTRO_REWARD);
+}
+
+#[test]
+fn only_the_admin_raises_the_intro_reward() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", Some(DEFAULT_INTRO_REWARD + 1))),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    // Listing the admin without their signature changes nothing
+    let mut instruction = create(&carol, "Carol", Some(MAX_INTRO_REWARD));
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(ADMIN_PUBKEY, false));
+    assert_eq!(
+        runtime.process(&instruction),
+        Err(ProgramError::MissingRequiredSignature)
+    );
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+    assert_eq!(supply(&runtime), 0);
+
+    runtime
+        .process(&create(&carol, "Carol", Some(DEFAULT_INTRO_REWARD)))
+        .unwrap();
+    assert_eq!(runtime.token_balance(&carol), DEFAULT_INTRO_REWARD);
+}
+
+#[test]
+fn tip_moves_lamports_from_tipper_to_author() {
+    let (mut runtime, alice, bob, intro) = setup();
+    let alice_lamports = runtime.lamports(&alice);
+    let bob_lamports = runtime.lamports(&bob);
+    let lamports = 5_000_000;
+
+    runtime
+        .process(&tip(&bob, &alice, &intro.intro, lamports))
+        .unwrap();
+
+    // The runtime charges no transaction fees, so the tip is the whole difference
+    assert_eq!(runtime.lamports(&alice), alice_lamports + lamports);
+    assert_eq!(runtime.lamports(&bob), bob_lamports - lamports);
+    assert_eq!(
+        runtime.process(&tip(&bob, &alice, &intro.intro, 0)),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+    assert_eq!(runtime.lamports(&alice), alice_lamports + lamports);
+}
+
+#[test]
+fn burn_reduces_balance_and_supply() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    runtime.process(&create(&carol, "Carol", None)).unwrap();
+
+    runtime.process(&burn(&carol, 4 * TOKEN)).unwrap();
+
+    assert_eq!(
+        runtime.token_balance(&carol),
+        DEFAULT_INTRO_REWARD - 4 * TOKEN
+    );
+    assert_eq!(supply(&runtime), DEFAULT_INTRO_REWARD - 4 * TOKEN);
+    assert_eq!(
+        runtime.process(&burn(&carol, 0)),
+        Err(StudentIntroError::InvalidInput.into())
+    );
+}
+
+#[test]
+fn reply_burn_needs_enough_tokens() {
+    let (mut runtime, _alice, bob, intro) = setup();
+    let carol = runtime.create_user();
+
+    runtime.advance_clock(MIN_REPLY_INTERVAL_SECONDS);
+    assert_eq!(
+        runtime.process(&IntroInstruction::add_reply(
+            &id(),
+            &carol,
+            &intro.intro,
+            0,
+            "Welcome, Alice".to_string(),
+        )),
+        Err(StudentIntroError::InsufficientTokens.into())
+    );
+    assert!(runtime.account(&intro.reply(0)).is_none());
+
+    // Exactly the burn amount is enough
+    runtime
+        .process(&burn(&bob, DEFAULT_INTRO_REWARD - REPLY_BURN_AMOUNT))
+        .unwrap();
+    runtime.add_reply(&bob, &intro, "Welcome, Alice");
+    assert_eq!(runtime.token_balance(&bob), REPLY_REWARD);
+}
+
+#[test]
+fn upsert_creates_once_and_mints_once() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    let upsert = |previous_name: Option<&str>, message: &str| {
+        IntroInstruction::init_or_update(
+            &id(),
+            &carol,
+            previous_name,
+            "Carol".to_string(),
+            message.to_string(),
+            None,
+            None,
+        )
+    };
+
+    runtime.process(&upsert(None, "Hello, I am Carol")).unwrap();
+    let intro = IntroPdas::new(&carol, "Carol");
+    assert_eq!(
+        runtime.get::<StudentInfo>(&intro.intro).msg,
+        "Hello, I am Carol"
+    );
+    assert_eq!(runtime.token_balance(&carol), DEFAULT_INTRO_REWARD);
+
+    runtime.advance_clock(EDIT_COOLDOWN_SECONDS);
+    runtime
+        .process(&upsert(Some("Carol"), "Hello, Carol again"))
+        .unwrap();
+    assert_eq!(
+        runtime.get::<StudentInfo>(&intro.intro).msg,
+        "Hello, Carol again"
+    );
+    assert_eq!(runtime.token_balance(&carol), DEFAULT_INTRO_REWARD);
+    assert_eq!(supply(&runtime), DEFAULT_INTRO_REWARD);
+}
+
+#[test]
+fn upsert_right_after_a_create_updates_without_minting_again() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    let upsert = |message: &str, avatar_uri: Option<&str>| {
+        IntroInstruction::init_or_update(
+            &id(),
+            &carol,
+            Some("Carol"),
+            "Carol".to_string(),
+            message.to_string(),
+            Some(DEFAULT_INTRO_REWARD),
+            avatar_uri.map(str::to_string),
+        )
+    };
+
+    runtime
+        .process(&upsert(
+            "Hello, I am Carol",
+            Some("https://example.com/carol.png"),
+        ))
+        .unwrap();
+    runtime
+        .process(&upsert("Hello, Carol again", None))
+        .unwrap();
+
+    let intro: StudentInfo = runtime.get(&IntroPdas::new(&carol, "Carol").intro);
+    assert_eq!(intro.msg, "Hello, Carol again");
+    assert_eq!(intro.avatar_uri, "https://example.com/carol.png");
+    assert_eq!(runtime.token_balance(&carol), DEFAULT_INTRO_REWARD);
+    assert_eq!(supply(&runtime), DEFAULT_INTRO_REWARD);
+}
+
+#[test]
+fn intro_rejects_a_mint_with_another_authority() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    let mut account = runtime.account(&common::token_mint()).unwrap().clone();
+    let mut mint = Mint::unpack(&account.data).unwrap();
+    mint.mint_authority = Some(Pubkey::new_unique()).into();
+    mint.pack_into_slice(&mut account.data);
+    runtime.set_account(common::token_mint(), account);
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", None)),
+        Err(StudentIntroError::IncorrectAccountError.into())
+    );
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+    assert_eq!(runtime.token_balance(&carol), 0);
+}
+
+#[test]
+fn failed_reward_mint_reports_token_mint_failed() {
+    let mut runtime = Runtime::with_mint();
+    let carol = runtime.create_user();
+    let ata = common::token_account(&carol);
+    let mut account = runtime.account(&ata).unwrap().clone();
+    let mut token_account = TokenAccount::unpack(&account.data).unwrap();
+    token_account.state = AccountState::Frozen;
+    token_account.pack_into_slice(&mut account.data);
+    runtime.set_account(ata, account);
+
+    assert_eq!(
+        runtime.process(&create(&carol, "Carol", None)),
+        Err(StudentIntroError::TokenMintFailed.into())
+    );
+    assert!(runtime
+        .logs()
+        .iter()
+        .any(|log| log.starts_with("Token mint CPI failed")));
+    assert!(runtime.account(&user_intro_pda(&carol, &id()).0).is_none());
+}